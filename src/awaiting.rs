@@ -0,0 +1,155 @@
+//! Requests of ours that the server should answer, so that one it seems to
+//! have ignored can be pointed out rather than left to look like nothing
+//! happened.
+
+use std::{fmt, time};
+
+use crate::clip;
+
+/// A command that expects an answer, and who it's about.
+#[derive(Debug, PartialEq)]
+pub enum Request {
+    Whois(String),
+    Who,
+    Watch(String),
+    Invite(String),
+}
+
+impl Request {
+    /// The request a line sent to FIBS makes, if it's one we follow up.
+    pub fn of(ln: &str) -> Option<Request> {
+        let mut words = ln.split_whitespace();
+        let command = words.next()?.to_lowercase();
+        let player = words.next().map(String::from);
+        match (command.as_str(), player) {
+            ("whois", Some(player)) => Some(Request::Whois(player)),
+            ("who", _) => Some(Request::Who),
+            ("watch", Some(player)) => Some(Request::Watch(player)),
+            ("invite", Some(player)) => Some(Request::Invite(player)),
+            _ => None,
+        }
+    }
+
+    // the server turning us down counts, as does anything naming the player
+    // asked about, since none of these has a single fixed reply.
+    fn answered_by(&self, ln: &str) -> bool {
+        if ln.starts_with("** ") {
+            return true;
+        }
+        let names = |player: &str| ln.to_lowercase().contains(&player.to_lowercase());
+        match self {
+            Request::Who => matches!(clip::message_type(ln), Some(5) | Some(6)),
+            Request::Whois(player) | Request::Watch(player) | Request::Invite(player) => names(player),
+        }
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Request::Whois(player) => write!(f, "whois {}", player),
+            Request::Who => write!(f, "who"),
+            Request::Watch(player) => write!(f, "watch {}", player),
+            Request::Invite(player) => write!(f, "invite {}", player),
+        }
+    }
+}
+
+/// Requests sent and not yet answered, oldest first.
+#[derive(Default)]
+pub struct Awaiting {
+    requests: Vec<(Request, time::Instant)>,
+    // whether the server's welcomed us in CLIP; without it, who's answer is
+    // a free-form listing with nothing to know it by.
+    clip: bool,
+}
+
+impl Awaiting {
+    pub fn new() -> Awaiting {
+        Awaiting::default()
+    }
+
+    /// Notes `ln` as sent at `now`, if it's a request.
+    pub fn sent(&mut self, ln: &str, now: time::Instant) {
+        match Request::of(ln) {
+            Some(Request::Who) if !self.clip => {}
+            Some(request) => self.requests.push((request, now)),
+            None => {}
+        }
+    }
+
+    /// Takes a line received, letting go of the oldest request it answers.
+    pub fn received(&mut self, ln: &str) {
+        if clip::message_type(ln) == Some(1) {
+            self.clip = true;
+        }
+        if let Some(i) = self.requests.iter().position(|(r, _)| r.answered_by(ln)) {
+            self.requests.remove(i);
+        }
+    }
+
+    /// The requests left unanswered for `timeout`, which are given up on.
+    pub fn overdue(&mut self, timeout: time::Duration, now: time::Instant) -> Vec<Request> {
+        let (overdue, waiting) = self.requests.drain(..).partition(|(_, at)| now.duration_since(*at) >= timeout);
+        self.requests = waiting;
+        overdue.into_iter().map(|(r, _)| r).collect()
+    }
+
+    /// Forgets everything sent, for a connection that's gone.
+    pub fn clear(&mut self) {
+        self.requests.clear();
+        self.clip = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests() {
+        assert_eq!(Request::of("whois Bob"), Some(Request::Whois(String::from("Bob"))));
+        assert_eq!(Request::of("invite bob 5"), Some(Request::Invite(String::from("bob"))));
+        assert_eq!(Request::of("who ready"), Some(Request::Who));
+        assert_eq!(Request::of("watch"), None);
+        assert_eq!(Request::of("tell bob who are you"), None);
+    }
+
+    #[test]
+    fn answered_and_overdue() {
+        let now = time::Instant::now();
+        let second = time::Duration::from_secs(1);
+        let mut awaiting = Awaiting::new();
+        awaiting.received("1 alice 1041253132 192.168.1.30");
+
+        awaiting.sent("whois bob", now);
+        awaiting.sent("who", now);
+        awaiting.sent("watch carol", now + second);
+        awaiting.received("Information about bob:");
+        awaiting.received("alice shouts: hi");
+
+        let overdue = awaiting.overdue(5 * second, now + 5 * second);
+        assert_eq!(overdue, vec![Request::Who]);
+        assert_eq!(overdue[0].to_string(), "who");
+
+        awaiting.received("** carol is not playing.");
+        assert!(awaiting.overdue(5 * second, now + 10 * second).is_empty());
+    }
+
+    #[test]
+    fn who_without_clip() {
+        let now = time::Instant::now();
+        let timeout = time::Duration::from_secs(5);
+        let mut awaiting = Awaiting::new();
+
+        awaiting.received("** User alice authenticated.");
+        awaiting.sent("who", now);
+        awaiting.received(" ready  bob          -          -        1523.40   311");
+        assert!(awaiting.overdue(timeout, now + timeout).is_empty());
+
+        awaiting.received("1 alice 1041253132 192.168.1.30");
+        awaiting.sent("who", now);
+        awaiting.received("5 bob - - 1 0 1523.40 311 0 1041253132 localhost - -");
+        assert!(awaiting.overdue(timeout, now + timeout).is_empty());
+    }
+}
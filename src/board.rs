@@ -0,0 +1,344 @@
+use std::{fmt, str};
+
+use crate::{trace, Error, Result};
+
+const BOARD_FIELDS: usize = 53;
+
+// the standard starting position, us to roll first.
+static OPENING: &str = "board:You:opponent:1:0:0:\
+    0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+    1:0:0:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0";
+
+/// A game position as sent by FIBS in a `board:` line (boardstyle 3).
+///
+/// `points` holds all 26 board positions as sent by the server: positive
+/// counts are O's checkers, negative counts are X's. `color` tells which of
+/// the two we are, and `direction`/`home`/`bar` which way we move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Board {
+    pub player: String,
+    pub opponent: String,
+    pub match_length: u32,
+    pub player_score: u32,
+    pub opponent_score: u32,
+    pub points: [i8; 26],
+    pub turn: i8,
+    pub player_dice: (u8, u8),
+    pub opponent_dice: (u8, u8),
+    pub cube: u32,
+    pub player_may_double: bool,
+    pub opponent_may_double: bool,
+    pub was_doubled: bool,
+    pub color: i8,
+    pub direction: i8,
+    pub home: u8,
+    pub bar: u8,
+    pub player_home: u8,
+    pub opponent_home: u8,
+    pub player_bar: u8,
+    pub opponent_bar: u8,
+    pub can_move: u8,
+    pub forced_move: bool,
+    pub did_crawford: bool,
+    pub redoubles: u32,
+}
+
+impl Board {
+    /// The index into `points` of a side's `point` (1 to 24), counted from
+    /// that side's own home board.
+    pub fn index(&self, point: usize, ours: bool) -> usize {
+        // we bear off towards `home`, the opponent towards the other end.
+        if (self.home == 0) == ours { point } else { 25 - point }
+    }
+
+    /// Checkers a side has on its points 1 to 24, as seen from that side.
+    pub fn checkers(&self, ours: bool) -> [u8; 24] {
+        let sign = if ours { self.color } else { -self.color };
+        let mut counts = [0; 24];
+
+        for (k, count) in counts.iter_mut().enumerate() {
+            let n = self.points[self.index(k + 1, ours)];
+            if n.signum() == sign {
+                *count = n.unsigned_abs();
+            }
+        }
+
+        counts
+    }
+
+    /// The pips a side has still to move to bear everything off, counting
+    /// checkers on the bar as on its 25 point.
+    pub fn pips(&self, ours: bool) -> u32 {
+        let bar = if ours { self.player_bar } else { self.opponent_bar };
+        let on_board: u32 = self.checkers(ours).iter().enumerate().map(|(k, n)| (k as u32 + 1) * *n as u32).sum();
+        on_board + 25 * bar as u32
+    }
+
+    /// The opening position, for trying out how boards are drawn without a
+    /// game to watch.
+    pub fn opening() -> Board {
+        OPENING.parse().expect("the opening position parses")
+    }
+
+    fn describe_side(&self, ours: bool) -> String {
+        let mut parts: Vec<String> = self.checkers(ours)
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, n)| **n > 0)
+            .map(|(k, n)| format!("{} on the {} point", n, k + 1))
+            .collect();
+
+        let (bar, off) = if ours {
+            (self.player_bar, self.player_home)
+        } else {
+            (self.opponent_bar, self.opponent_home)
+        };
+        if bar > 0 {
+            parts.push(format!("{} on the bar", bar));
+        }
+        if off > 0 {
+            parts.push(format!("{} borne off", off));
+        }
+
+        parts.join(", ")
+    }
+
+    /// A plain-language account of the position, for reading aloud.
+    pub fn describe(&self) -> String {
+        let whose_turn = if self.turn == 0 {
+            String::from("Game over.")
+        } else if self.turn == self.color {
+            match self.player_dice {
+                (0, _) => String::from("Your turn to roll or double."),
+                (a, b) => format!("Your turn, you rolled {} and {}.", a, b),
+            }
+        } else {
+            match self.opponent_dice {
+                (0, _) => format!("{}'s turn to roll.", self.opponent),
+                (a, b) => format!("{} rolled {} and {}.", self.opponent, a, b),
+            }
+        };
+
+        format!("{} versus {}, {} point match, score {} to {}, cube at {}. Your checkers: {}. {}'s checkers: {}. {}",
+            self.player, self.opponent, self.match_length,
+            self.player_score, self.opponent_score, self.cube,
+            self.describe_side(true),
+            self.opponent, self.describe_side(false),
+            whose_turn)
+    }
+}
+
+fn field<T: str::FromStr>(fields: &[&str], i: usize) -> Result<T> {
+    fields
+        .get(i)
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(|| Error::MalformedInputError(format!("bad board field {}: {:?}", i, fields.get(i))))
+}
+
+// the fields past the dice can go missing or change between servers; we
+// get by without them, noting the ones we had to make up.
+fn optional<T: str::FromStr>(fields: &[&str], i: usize, default: T, missing: &mut Vec<usize>) -> T {
+    field(fields, i).unwrap_or_else(|_| {
+        missing.push(i);
+        default
+    })
+}
+
+impl Board {
+    /// Parses a `board:` line, making do if it has more or fewer fields than
+    /// boardstyle 3's 53: only the points, turn and dice have to be there.
+    /// Along with the board comes a warning if anything had to be guessed.
+    pub fn parse(s: &str) -> Result<(Board, Option<String>)> {
+        let parsed = Board::parse_fields(s);
+        trace::trace(trace::Category::Board, || match &parsed {
+            Ok((board, warning)) => format!("{} v {}, turn {}, dice {:?}/{:?}{}: {}",
+                board.player, board.opponent, board.turn, board.player_dice, board.opponent_dice,
+                warning.as_ref().map(|w| format!(" ({})", w)).unwrap_or_default(), s),
+            Err(e) => format!("{}: {}", e, s),
+        });
+        parsed
+    }
+
+    fn parse_fields(s: &str) -> Result<(Board, Option<String>)> {
+        let fields: Vec<&str> = s.trim_end().split(':').collect();
+
+        if fields[0] != "board" {
+            return Err(Error::MalformedInputError(format!("not a board: {}", s)));
+        }
+
+        let mut points = [0; 26];
+        for (i, p) in points.iter_mut().enumerate() {
+            *p = field(&fields, 6 + i)?;
+        }
+        let turn = field(&fields, 32)?;
+        let player_dice = (field(&fields, 33)?, field(&fields, 34)?);
+        let opponent_dice = (field(&fields, 35)?, field(&fields, 36)?);
+
+        let mut missing = Vec::new();
+        let m = &mut missing;
+        let board = Board {
+            player: String::from(fields[1]),
+            opponent: String::from(fields[2]),
+            match_length: optional(&fields, 3, 0, m),
+            player_score: optional(&fields, 4, 0, m),
+            opponent_score: optional(&fields, 5, 0, m),
+            points,
+            turn,
+            player_dice,
+            opponent_dice,
+            cube: optional(&fields, 37, 1, m),
+            player_may_double: optional::<u8>(&fields, 38, 0, m) != 0,
+            opponent_may_double: optional::<u8>(&fields, 39, 0, m) != 0,
+            was_doubled: optional::<u8>(&fields, 40, 0, m) != 0,
+            color: optional(&fields, 41, 1, m),
+            direction: optional(&fields, 42, -1, m),
+            home: optional(&fields, 43, 0, m),
+            bar: optional(&fields, 44, 25, m),
+            player_home: optional(&fields, 45, 0, m),
+            opponent_home: optional(&fields, 46, 0, m),
+            player_bar: optional(&fields, 47, 0, m),
+            opponent_bar: optional(&fields, 48, 0, m),
+            can_move: optional(&fields, 49, 0, m),
+            forced_move: optional::<u8>(&fields, 50, 0, m) != 0,
+            did_crawford: optional::<u8>(&fields, 51, 0, m) != 0,
+            redoubles: optional(&fields, 52, 0, m),
+        };
+
+        let warning = if !missing.is_empty() {
+            Some(format!("board fields {:?} missing or unreadable", missing))
+        } else if fields.len() != BOARD_FIELDS {
+            Some(format!("expected {} board fields, got {}", BOARD_FIELDS, fields.len()))
+        } else {
+            None
+        };
+        Ok((board, warning))
+    }
+}
+
+impl str::FromStr for Board {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Board> {
+        Board::parse(s).map(|(board, _)| board)
+    }
+}
+
+// writes the board back out as a boardstyle 3 line, which is what most
+// backgammon tools expect to be pasted when importing from FIBS.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "board:{}:{}:{}:{}:{}",
+            self.player, self.opponent, self.match_length, self.player_score, self.opponent_score)?;
+        for p in self.points.iter() {
+            write!(f, ":{}", p)?;
+        }
+        write!(f, ":{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}",
+            self.turn,
+            self.player_dice.0, self.player_dice.1,
+            self.opponent_dice.0, self.opponent_dice.1,
+            self.cube,
+            self.player_may_double as u8, self.opponent_may_double as u8,
+            self.was_doubled as u8,
+            self.color, self.direction, self.home, self.bar,
+            self.player_home, self.opponent_home,
+            self.player_bar, self.opponent_bar,
+            self.can_move, self.forced_move as u8, self.did_crawford as u8,
+            self.redoubles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static START: &str = "board:You:bob:3:0:0:\
+        0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+        1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0";
+
+    #[test]
+    fn full_board() {
+        let (board, warning) = Board::parse(START).unwrap();
+        assert_eq!(warning, None);
+        assert_eq!(board.to_string().replace(' ', ""), START.replace(' ', ""));
+    }
+
+    #[test]
+    fn copied_as_sent() {
+        // mid-game, doubled, with checkers on the bar and borne off: what
+        // /copyboard puts on the clipboard is the line FIBS sent.
+        let line = "board:You:bob:5:2:3:\
+            1:-2:0:0:0:0:4:0:2:0:0:0:-5:5:0:0:0:-3:0:-4:0:0:0:0:2:0:\
+            -1:0:0:6:2:2:0:1:0:1:-1:0:25:1:1:0:1:0:0:1:0";
+        let line = line.replace(' ', "");
+        let (board, _) = Board::parse(&line).unwrap();
+        assert_eq!(board.to_string(), line);
+    }
+
+    #[test]
+    fn every_field() {
+        let (board, _) = Board::parse(START).unwrap();
+
+        assert_eq!((board.player.as_str(), board.opponent.as_str()), ("You", "bob"));
+        assert_eq!((board.match_length, board.player_score, board.opponent_score), (3, 0, 0));
+        assert_eq!(board.points[1], -2);
+        assert_eq!(board.points[6], 5);
+        assert_eq!(board.points[24], 2);
+        assert_eq!((board.points[0], board.points[25]), (0, 0));
+        assert_eq!(board.turn, 1);
+        assert_eq!((board.player_dice, board.opponent_dice), ((3, 1), (0, 0)));
+        assert_eq!(board.cube, 1);
+        assert!(board.player_may_double && board.opponent_may_double && !board.was_doubled);
+        assert_eq!((board.color, board.direction, board.home, board.bar), (1, -1, 0, 25));
+        assert_eq!((board.player_home, board.opponent_home, board.player_bar, board.opponent_bar), (0, 0, 0, 0));
+        assert_eq!(board.can_move, 2);
+        assert!(!board.forced_move && !board.did_crawford);
+        assert_eq!(board.redoubles, 0);
+    }
+
+    #[test]
+    fn truncated_board() {
+        let truncated: Vec<&str> = START.split(':').take(45).collect();
+        let (board, warning) = Board::parse(&truncated.join(":")).unwrap();
+
+        assert_eq!(board.player_dice, (3, 1));
+        assert_eq!(board.color, 1);
+        assert_eq!(board.player_bar, 0);
+        assert!(warning.unwrap().contains("45"));
+    }
+
+    #[test]
+    fn extended_board() {
+        let (board, warning) = Board::parse(&format!("{}:7:extra", START)).unwrap();
+
+        assert_eq!(board.redoubles, 0);
+        assert_eq!(warning, Some(String::from("expected 53 board fields, got 55")));
+    }
+
+    #[test]
+    fn opening() {
+        let board = Board::opening();
+        assert_eq!(board.checkers(true).iter().sum::<u8>(), 15);
+        assert_eq!(board.checkers(false).iter().sum::<u8>(), 15);
+        assert_eq!(board.checkers(true)[5], 5);
+        assert_eq!(board.checkers(false)[23], 2);
+    }
+
+    #[test]
+    fn pip_counts() {
+        let board = Board::opening();
+        assert_eq!((board.pips(true), board.pips(false)), (167, 167));
+
+        // our back checker hit, from our 24 point to the bar.
+        let (hit, _) = Board::parse("board:You:bob:3:0:0:\
+            0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:1:0:\
+            1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:1:0:2:0:0:0").unwrap();
+        assert_eq!(hit.pips(true), 168);
+    }
+
+    #[test]
+    fn no_dice() {
+        let truncated: Vec<&str> = START.split(':').take(34).collect();
+        assert!(Board::parse(&truncated.join(":")).is_err());
+    }
+}
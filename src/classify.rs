@@ -0,0 +1,442 @@
+use crate::{clip, trace};
+
+#[derive(Debug, PartialEq)]
+pub enum ChatKind {
+    Tell,
+    Say,
+    Shout,
+    Whisper,
+    Kibitz,
+}
+
+/// What a line received from FIBS is about, as far as the client cares.
+#[derive(Debug, PartialEq)]
+pub enum LineKind {
+    /// Something a player said, and who said it.
+    Chat(ChatKind, String),
+    /// Someone inviting us to a match.
+    Invite(String),
+    /// The server confirming whether we're ready to play.
+    Ready(bool),
+    /// A match starting against someone; `true` for an unlimited (money) match.
+    MatchStart(String, bool),
+    /// Us starting to watch someone, and who they're playing and to how
+    /// many points, as far as the server said.
+    Watching(String, Option<String>, Option<u32>),
+    /// The cube being turned on us, and by whom.
+    Double(String),
+    Beaver(String),
+    Raccoon(String),
+    /// A maintenance countdown, with the seconds left if we could find them.
+    Shutdown(Option<u32>),
+    ShutdownCancelled,
+    /// The server turning down something we sent.
+    Error(String),
+    /// A protocol error reported through CLIP, rather than as text.
+    ClipError(String),
+    /// Someone logging in.
+    Login(String),
+    /// The away message of someone we told something.
+    Away(String, String),
+    /// A tell of ours delivered to someone, or, with them gone, saved for
+    /// them to read when they're next on.
+    Delivered(String),
+    Saved(String),
+    /// A match finishing, ours or one we're watching.
+    MatchEnd,
+    /// Our own rating and experience, as far as they could be made out.
+    Standing(Option<f64>, Option<u32>),
+    /// The server confirming a `set`: the setting and its new value.
+    Setting(String, String),
+    Other,
+}
+
+// the wording of these varies between server versions and admins, so look for
+// the gist rather than exact strings.
+fn is_shutdown(lower: &str) -> bool {
+    lower.contains("shut down") || lower.contains("shutdown") || lower.contains("shutting down")
+}
+
+fn countdown(lower: &str) -> Option<u32> {
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    words.windows(2).find_map(|w| {
+        let n: u32 = w[0].parse().ok()?;
+        if w[1].starts_with("minute") {
+            Some(n * 60)
+        } else if w[1].starts_with("second") {
+            Some(n)
+        } else {
+            None
+        }
+    })
+}
+
+// what the client makes of the CLIP messages it cares about; the rest are
+// left to be read as text, as a message (9) may be about a shutdown.
+fn from_clip(message: clip::ClipMessage) -> Option<LineKind> {
+    match message {
+        clip::ClipMessage::Error(msg) => Some(LineKind::ClipError(msg)),
+        clip::ClipMessage::Says { name, .. } => Some(LineKind::Chat(ChatKind::Tell, name)),
+        clip::ClipMessage::Shouts { name, .. } => Some(LineKind::Chat(ChatKind::Shout, name)),
+        clip::ClipMessage::Whispers { name, .. } => Some(LineKind::Chat(ChatKind::Whisper, name)),
+        clip::ClipMessage::Kibitzes { name, .. } => Some(LineKind::Chat(ChatKind::Kibitz, name)),
+        clip::ClipMessage::Login { name, .. } => Some(LineKind::Login(name)),
+        clip::ClipMessage::Delivered(name) => Some(LineKind::Delivered(name)),
+        clip::ClipMessage::Saved(name) => Some(LineKind::Saved(name)),
+        clip::ClipMessage::OwnInfo { rating, experience, .. } => Some(LineKind::Standing(Some(rating), Some(experience))),
+        _ => None,
+    }
+}
+
+// the default mode's spelt-out chat; CLIP's numbered kind is from_clip's.
+fn chat(ln: &str) -> Option<LineKind> {
+    let (sender, rest) = ln.split_once(' ')?;
+    let kind = match rest.split_once(": ")?.0 {
+        "tells" => ChatKind::Tell,
+        "says" => ChatKind::Say,
+        "shouts" => ChatKind::Shout,
+        "whispers" => ChatKind::Whisper,
+        "kibitzes" => ChatKind::Kibitz,
+        _ => return None,
+    };
+    Some(LineKind::Chat(kind, String::from(sender)))
+}
+
+/// What was said in a chat line, without who said it or how.
+pub fn message(ln: &str) -> Option<&str> {
+    let (first, rest) = ln.split_once(' ')?;
+    if ["12", "13", "14", "15"].contains(&first) {
+        return rest.split_once(' ').map(|(_, msg)| msg);
+    }
+    rest.split_once(": ").map(|(_, msg)| msg)
+}
+
+fn invite(ln: &str) -> Option<LineKind> {
+    let (player, rest) = ln.split_once(" wants to ")?;
+    if (rest.starts_with("play") || rest.starts_with("resume")) && rest.ends_with("with you.") {
+        return Some(LineKind::Invite(String::from(player)));
+    }
+    None
+}
+
+fn match_start(ln: &str) -> Option<LineKind> {
+    let rest = ln.strip_prefix("You are now playing with ")?;
+    let (opponent, rest) = rest.split_once(". ")?;
+    Some(LineKind::MatchStart(String::from(opponent), rest.contains("unlimited match")))
+}
+
+// "You're now watching alice.", or with the game: "You're now watching
+// alice, who is playing bob in a 5 point match."
+fn watching(ln: &str) -> Option<LineKind> {
+    let rest = ln.strip_prefix("You're now watching ")?.trim_end_matches('.');
+    let Some((player, game)) = rest.split_once(", who is playing ") else {
+        return Some(LineKind::Watching(String::from(rest), None, None));
+    };
+    let (opponent, length) = match game.split_once(' ') {
+        Some((opponent, length)) => (opponent, length),
+        None => (game, ""),
+    };
+    let length = length
+        .trim_matches(|c: char| c == '(' || c == ')')
+        .trim_start_matches("in a ")
+        .strip_suffix(" point match")
+        .and_then(|n| n.parse().ok());
+    Some(LineKind::Watching(String::from(player), Some(String::from(opponent)), length))
+}
+
+fn cube(ln: &str) -> Option<LineKind> {
+    let (player, rest) = ln.split_once(' ')?;
+    if player == "You" {
+        return None;
+    }
+
+    if rest.starts_with("doubles.") {
+        Some(LineKind::Double(String::from(player)))
+    } else if rest.starts_with("beavers") {
+        Some(LineKind::Beaver(String::from(player)))
+    } else if rest.starts_with("raccoons") {
+        Some(LineKind::Raccoon(String::from(player)))
+    } else {
+        None
+    }
+}
+
+// FIBS has no one form for these, but they all start the same few ways.
+fn error(ln: &str) -> Option<LineKind> {
+    let msg = ln.strip_prefix("** ")?;
+    ["Unknown command", "Error", "Invalid", "Please ", "You can't", "Don't know", "There's no"]
+        .iter()
+        .any(|start| msg.starts_with(start))
+        .then(|| LineKind::Error(String::from(msg)))
+}
+
+fn login(ln: &str) -> Option<LineKind> {
+    let (player, rest) = ln.split_once(' ')?;
+    (rest == "logs in.").then(|| LineKind::Login(String::from(player)))
+}
+
+fn away(ln: &str) -> Option<LineKind> {
+    let ln = ln.strip_prefix("** ").unwrap_or(ln);
+    let (player, message) = ln.split_once(" is away: ")?;
+    if player.contains(' ') {
+        return None;
+    }
+    Some(LineKind::Away(String::from(player), String::from(message)))
+}
+
+// "Value of 'boardstyle' set to 3."
+fn setting(ln: &str) -> Option<LineKind> {
+    let (name, value) = ln.strip_prefix("Value of '")?.split_once("' set to ")?;
+    Some(LineKind::Setting(String::from(name), String::from(value.trim_end_matches('.'))))
+}
+
+/// Who won a match that just ended, its length, and the winner's and the
+/// loser's scores: "bob wins the 3 point match 3-1 ." We're "You".
+pub fn match_result(ln: &str) -> Option<(String, u32, u32, u32)> {
+    let (winner, rest) = ln.split_once(" wins the ").or_else(|| Some(("You", ln.strip_prefix("You win the ")?)))?;
+    let (length, rest) = rest.split_once(" point match ")?;
+    let (won, lost) = rest.trim_end_matches(['.', ' ']).split_once('-')?;
+    Some((String::from(winner), length.parse().ok()?, won.parse().ok()?, lost.parse().ok()?))
+}
+
+fn match_end(ln: &str) -> bool {
+    (ln.contains(" wins the ") || ln.starts_with("You win the ")) && ln.contains(" point match")
+}
+
+/// Who said `ln`, if it's chat. The same as `classify` makes of it, but
+/// untraced, for the display to call as often as it redraws.
+pub fn sender(ln: &str) -> Option<String> {
+    let kind = match clip::parse(ln) {
+        Some(message) => from_clip(message),
+        None => chat(ln),
+    };
+    match kind {
+        Some(LineKind::Chat(_, sender)) => Some(sender),
+        _ => None,
+    }
+}
+
+pub fn classify(ln: &str) -> LineKind {
+    let kind = kind(ln);
+    trace::trace(trace::Category::Clip, || match clip::message_type(ln) {
+        Some(n) => format!("[{}] {:?}: {}", n, kind, ln),
+        None => format!("{:?}: {}", kind, ln),
+    });
+    kind
+}
+
+fn kind(ln: &str) -> LineKind {
+    if let Some(kind) = clip::parse(ln).and_then(from_clip) {
+        return kind;
+    }
+    if let Some(chat) = chat(ln) {
+        return chat;
+    }
+    if let Some(invite) = invite(ln) {
+        return invite;
+    }
+    if let Some(start) = match_start(ln) {
+        return start;
+    }
+    if let Some(watching) = watching(ln) {
+        return watching;
+    }
+    if let Some(cube) = cube(ln) {
+        return cube;
+    }
+    if ln.starts_with("** You're now ready to invite or join someone") {
+        return LineKind::Ready(true);
+    }
+    if ln.starts_with("** You're now refusing to play with someone") {
+        return LineKind::Ready(false);
+    }
+    if let Some(error) = error(ln) {
+        return error;
+    }
+    if let Some(login) = login(ln) {
+        return login;
+    }
+    if let Some(away) = away(ln) {
+        return away;
+    }
+    if let Some(setting) = setting(ln) {
+        return setting;
+    }
+    if match_end(ln) {
+        return LineKind::MatchEnd;
+    }
+
+    let lower = ln.to_lowercase();
+
+    if is_shutdown(&lower) {
+        if lower.contains("cancel") || lower.contains("abort") {
+            return LineKind::ShutdownCancelled;
+        }
+        return LineKind::Shutdown(countdown(&lower));
+    }
+
+    LineKind::Other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shutdown_countdowns() {
+        assert_eq!(classify("** FIBS will shut down in 5 minutes."), LineKind::Shutdown(Some(300)));
+        assert_eq!(classify("System shutdown in 30 seconds!"), LineKind::Shutdown(Some(30)));
+        assert_eq!(classify("The server is shutting down now."), LineKind::Shutdown(None));
+    }
+
+    #[test]
+    fn shutdown_cancelled() {
+        assert_eq!(classify("** Shutdown cancelled."), LineKind::ShutdownCancelled);
+    }
+
+    #[test]
+    fn chat_is_not_mistaken_for_a_shutdown() {
+        assert_eq!(
+            classify("alice shouts: FIBS will shut down in 5 minutes lol"),
+            LineKind::Chat(ChatKind::Shout, String::from("alice"))
+        );
+        assert_eq!(classify("12 bob shutdown soon?"), LineKind::Chat(ChatKind::Tell, String::from("bob")));
+    }
+
+    #[test]
+    fn chat_messages() {
+        assert_eq!(message("12 bob good game: thanks"), Some("good game: thanks"));
+        assert_eq!(message("bob tells: good game"), Some("good game"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            classify("** Unknown command: 'sett'"),
+            LineKind::Error(String::from("Unknown command: 'sett'"))
+        );
+        assert_eq!(classify("** You're now ready to invite or join someone."), LineKind::Ready(true));
+    }
+
+    #[test]
+    fn settings() {
+        assert_eq!(
+            classify("Value of 'boardstyle' set to 2."),
+            LineKind::Setting(String::from("boardstyle"), String::from("2"))
+        );
+    }
+
+    #[test]
+    fn clip_errors() {
+        assert_eq!(
+            classify("20 unexpected message type 42"),
+            LineKind::ClipError(String::from("unexpected message type 42"))
+        );
+        // the same complaint as text is the ordinary kind.
+        assert_eq!(
+            classify("** Error: unexpected message type 42"),
+            LineKind::Error(String::from("Error: unexpected message type 42"))
+        );
+    }
+
+    #[test]
+    fn logins() {
+        assert_eq!(classify("7 alice alice logs in."), LineKind::Login(String::from("alice")));
+        assert_eq!(classify("alice logs in."), LineKind::Login(String::from("alice")));
+        assert_eq!(classify("alice says: who logs in."), LineKind::Chat(ChatKind::Say, String::from("alice")));
+    }
+
+    #[test]
+    fn away_messages() {
+        assert_eq!(
+            classify("bob is away: back in 5"),
+            LineKind::Away(String::from("bob"), String::from("back in 5"))
+        );
+        assert_eq!(
+            classify("** bob is away: back in 5"),
+            LineKind::Away(String::from("bob"), String::from("back in 5"))
+        );
+    }
+
+    #[test]
+    fn tells_delivered_or_saved() {
+        assert_eq!(classify("10 bob"), LineKind::Delivered(String::from("bob")));
+        assert_eq!(classify("11 bob"), LineKind::Saved(String::from("bob")));
+        assert_eq!(classify("10 bob alice"), LineKind::Other);
+    }
+
+    #[test]
+    fn own_info() {
+        assert_eq!(
+            classify("2 myself 1 1 0 0 0 0 1 1 2396 0 1 0 1 3457.85 0 0 0 0 0 Australia/Melbourne"),
+            LineKind::Standing(Some(3457.85), Some(2396))
+        );
+        assert_eq!(classify("2 myself 1 1 0"), LineKind::Other);
+    }
+
+    #[test]
+    fn match_ends() {
+        assert_eq!(classify("You win the 5 point match 5-3 ."), LineKind::MatchEnd);
+        assert_eq!(classify("bob wins the 3 point match 3-1 ."), LineKind::MatchEnd);
+        assert_eq!(classify("bob wins the game and gets 1 point."), LineKind::Other);
+
+        assert_eq!(match_result("You win the 5 point match 5-3 ."), Some((String::from("You"), 5, 5, 3)));
+        assert_eq!(match_result("bob wins the 3 point match 3-1 ."), Some((String::from("bob"), 3, 3, 1)));
+    }
+
+    #[test]
+    fn invitations() {
+        assert_eq!(
+            classify("bob wants to play a 5 point match with you."),
+            LineKind::Invite(String::from("bob"))
+        );
+        assert_eq!(
+            classify("bob wants to resume a saved match with you."),
+            LineKind::Invite(String::from("bob"))
+        );
+    }
+
+    #[test]
+    fn match_start_and_cube_actions() {
+        assert_eq!(
+            classify("You are now playing with bob. It's an unlimited match."),
+            LineKind::MatchStart(String::from("bob"), true)
+        );
+        assert_eq!(
+            classify("You are now playing with bob. It's a 5 point match."),
+            LineKind::MatchStart(String::from("bob"), false)
+        );
+        assert_eq!(
+            classify("bob doubles. Type 'accept' or 'reject'."),
+            LineKind::Double(String::from("bob"))
+        );
+        assert_eq!(classify("bob beavers."), LineKind::Beaver(String::from("bob")));
+        assert_eq!(classify("You double. Please wait for bob to accept or reject."), LineKind::Other);
+    }
+
+    #[test]
+    fn watch_starts() {
+        assert_eq!(classify("You're now watching bob."), LineKind::Watching(String::from("bob"), None, None));
+        assert_eq!(
+            classify("You're now watching bob, who is playing alice in a 5 point match."),
+            LineKind::Watching(String::from("bob"), Some(String::from("alice")), Some(5))
+        );
+        assert_eq!(
+            classify("You're now watching bob, who is playing alice (3 point match)."),
+            LineKind::Watching(String::from("bob"), Some(String::from("alice")), Some(3))
+        );
+        assert_eq!(
+            classify("You're now watching bob, who is playing alice."),
+            LineKind::Watching(String::from("bob"), Some(String::from("alice")), None)
+        );
+    }
+
+    #[test]
+    fn ordinary_lines() {
+        assert_eq!(classify("You're not watching anyone."), LineKind::Other);
+    }
+}
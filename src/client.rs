@@ -0,0 +1,261 @@
+use std::{io, net, sync, thread, time};
+use std::io::prelude::*;
+
+use crate::board::Board;
+use crate::classify::{self, LineKind};
+use crate::connection::Connection;
+use crate::{login, network, telnet, trace, Result};
+
+// how long a read waits while we're watching for the login prompts, which
+// only count once the server has gone quiet.
+const PROMPT_POLL: time::Duration = time::Duration::from_millis(100);
+
+/// What the server sends, as it comes in.
+#[derive(Debug)]
+pub enum Event {
+    /// The server's let us in. Anything sent before this was held until
+    /// now.
+    LoggedIn,
+    /// The server turned the name or password down. The connection's
+    /// closed, and `Disconnected` follows.
+    LoginFailed,
+    Board(Board),
+    Line(String, LineKind),
+    /// The server closed the connection; nothing follows.
+    Disconnected,
+}
+
+/// A session with FIBS for bots, other front-ends and tests: it logs in,
+/// then every line from the server comes out of the receiver handed back
+/// with it, and lines for the server go in through `send`.
+pub struct Client {
+    conn: sync::Arc<sync::Mutex<Connection>>,
+}
+
+impl Client {
+    pub fn connect(hostname: &str, port: u16, user: &str, password: &str) -> Result<(Client, sync::mpsc::Receiver<Event>)> {
+        Client::start(network::connect(hostname, port, network::DEFAULT_CONNECT_TIMEOUT)?, user, password)
+    }
+
+    /// Logs in over a connection that's already open, e.g. one from
+    /// `network::connect_any`.
+    pub fn start(tcp: net::TcpStream, user: &str, password: &str) -> Result<(Client, sync::mpsc::Receiver<Event>)> {
+        let conn = sync::Arc::new(sync::Mutex::new(Connection::new(tcp.try_clone()?)));
+        let (tx, rx) = sync::mpsc::channel();
+
+        let reading = sync::Arc::clone(&conn);
+        let (user, password) = (String::from(user), String::from(password));
+        thread::spawn(move || {
+            // the other end hanging up, or turning us away, is the one way
+            // this ends.
+            let _ = read(tcp, &reading, &user, &password, &tx);
+            let _ = tx.send(Event::Disconnected);
+        });
+
+        Ok((Client { conn }, rx))
+    }
+
+    /// Sends a line to FIBS, or holds on to it until we're logged in.
+    pub fn send(&self, ln: &str) -> Result<()> {
+        self.conn.lock()?.send(ln);
+        Ok(())
+    }
+}
+
+fn read(
+    mut tcp: net::TcpStream,
+    conn: &sync::Mutex<Connection>,
+    user: &str,
+    password: &str,
+    tx: &sync::mpsc::Sender<Event>,
+) -> Result<()> {
+    let mut login = login::Login::new();
+    let mut ln = Vec::new();
+    let mut buf = [0; 4096];
+    let mut telnet = telnet::Filter::new();
+
+    tcp.set_read_timeout(Some(PROMPT_POLL))?;
+
+    loop {
+        let n = match tcp.read(&mut buf) {
+            Ok(0) => {
+                trace::trace(trace::Category::Net, || String::from("closed by the server"));
+                return Ok(());
+            }
+            Ok(n) => {
+                trace::trace(trace::Category::Net, || format!("read {} bytes: {:?}", n, String::from_utf8_lossy(&buf[..n])));
+                n
+            }
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut bytes = buf[..n].to_vec();
+        let replies = telnet.strip(&mut bytes);
+        if !replies.is_empty() {
+            tcp.write_all(&replies)?;
+        }
+
+        for b in bytes.iter() {
+            if !login.logged_in() {
+                if let Some(step) = login.feed(*b, time::Instant::now()) {
+                    if !logging_in(step, &mut tcp, conn, user, password, tx)? {
+                        return Ok(());
+                    }
+                }
+            } else if *b == b'\n' {
+                let s = String::from_utf8_lossy(&ln).trim_end_matches('\r').to_string();
+                tx.send(event(s))?;
+                ln.clear();
+            } else {
+                ln.push(*b);
+            }
+        }
+
+        if let Some(step) = login.settled(time::Instant::now()) {
+            if !logging_in(step, &mut tcp, conn, user, password, tx)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// answers the server for `step` of the login; false once it's turned us
+// down and hung up on.
+fn logging_in(
+    step: login::Step,
+    tcp: &mut net::TcpStream,
+    conn: &sync::Mutex<Connection>,
+    user: &str,
+    password: &str,
+    tx: &sync::mpsc::Sender<Event>,
+) -> Result<bool> {
+    match step {
+        login::Step::Greeted(_) => {
+            let mut conn = conn.lock()?;
+            conn.prompted();
+            conn.send(user);
+        }
+        login::Step::AskedPassword => {
+            let mut conn = conn.lock()?;
+            conn.prompted_for_password();
+            conn.send(password);
+        }
+        // asking again would only be turned down again.
+        login::Step::AskedAgain | login::Step::Outcome(login::Outcome::Incorrect, _) => {
+            tx.send(Event::LoginFailed)?;
+            tcp.shutdown(net::Shutdown::Both)?;
+            return Ok(false);
+        }
+        login::Step::Outcome(login::Outcome::Welcome(_), ln) => {
+            conn.lock()?.flush()?;
+            tcp.set_read_timeout(None)?;
+            tx.send(Event::LoggedIn)?;
+            tx.send(event(ln))?;
+        }
+        login::Step::Line(ln) => tx.send(event(ln))?,
+    }
+    Ok(true)
+}
+
+fn event(ln: String) -> Event {
+    if ln.starts_with("board:") {
+        if let Ok((board, _)) = Board::parse(&ln) {
+            return Event::Board(board);
+        }
+    }
+    let kind = classify::classify(&ln);
+    Event::Line(ln, kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock;
+
+    static START: &str = "board:You:bob:3:0:0:\
+        0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+        1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0";
+
+    // one line from the client, up to its carriage return.
+    fn line(tcp: &mut net::TcpStream) -> String {
+        let mut ln = Vec::new();
+        let mut b = [0];
+        while tcp.read(&mut b).unwrap() == 1 && b[0] != b'\r' {
+            ln.push(b[0]);
+        }
+        String::from_utf8(ln).unwrap()
+    }
+
+    #[test]
+    fn logs_in_and_hands_back_lines() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut tcp, _) = listener.accept().unwrap();
+            tcp.write_all(b"\r\nWelcome to FIBS\r\nlogin: ").unwrap();
+            assert_eq!(line(&mut tcp), "alice");
+            tcp.write_all(b"password: ").unwrap();
+            assert_eq!(line(&mut tcp), "secret");
+            tcp.write_all(b"\r\n** User alice authenticated.\r\n").unwrap();
+            assert_eq!(line(&mut tcp), "who");
+            tcp.write_all(format!("{}\r\n12 bob hello\r\n", START).as_bytes()).unwrap();
+        });
+
+        let (client, events) = Client::connect("127.0.0.1", port, "alice", "secret").unwrap();
+        // held until we're in.
+        client.send("who").unwrap();
+
+        assert!(matches!(events.recv().unwrap(), Event::LoggedIn));
+        assert!(matches!(events.recv().unwrap(), Event::Line(ln, _) if ln == "** User alice authenticated."));
+        assert!(matches!(events.recv().unwrap(), Event::Board(board) if board.opponent == "bob"));
+        assert!(matches!(events.recv().unwrap(), Event::Line(ln, _) if ln == "12 bob hello"));
+        assert!(matches!(events.recv().unwrap(), Event::Disconnected));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn fragmented_anywhere() {
+        let chunkings = [
+            mock::Chunks::Every(1),
+            mock::Chunks::Sizes(vec![3, 1, 7]),
+            mock::Chunks::Random { seed: 1, max: 9 },
+            mock::Chunks::Random { seed: 2, max: 9 },
+        ];
+        for chunks in chunkings {
+            let (port, server) = mock::serve(chunks.clone(), Some(mock::SHORT_DELAY), |mut fibs| {
+                // a negotiation, then a MOTD that mentions the prompt.
+                fibs.send_bytes(&[255, 251, 1]);
+                fibs.send("\r\nType guest at login: to register\r\nlogin: ");
+                assert_eq!(fibs.line(), "alice");
+                fibs.send("password: ");
+                assert_eq!(fibs.line(), "secret");
+                fibs.send(&format!("\r\n1 alice 1041253132 192.168.1.30\r\n12 bob héllo ✓\r\n{}\r\n", START));
+            });
+
+            let (_client, events) = Client::connect("127.0.0.1", port, "alice", "secret").unwrap();
+            assert!(matches!(events.recv().unwrap(), Event::LoggedIn), "{:?}", chunks);
+            assert!(matches!(events.recv().unwrap(), Event::Line(ln, _) if ln.starts_with("1 alice")), "{:?}", chunks);
+            assert!(matches!(events.recv().unwrap(), Event::Line(ln, _) if ln == "12 bob héllo ✓"), "{:?}", chunks);
+            assert!(matches!(events.recv().unwrap(), Event::Board(board) if board.opponent == "bob"), "{:?}", chunks);
+            server.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn turned_down() {
+        let (port, server) = mock::serve(mock::Chunks::Whole, None, |mut fibs| {
+            fibs.send("\r\nlogin: ");
+            assert_eq!(fibs.line(), "alice");
+            fibs.send("password: ");
+            assert_eq!(fibs.line(), "wrong");
+            fibs.send("\r\n** Login incorrect.\r\nlogin: ");
+        });
+
+        let (client, events) = Client::connect("127.0.0.1", port, "alice", "wrong").unwrap();
+        client.send("who").unwrap();
+        assert!(matches!(events.recv().unwrap(), Event::LoginFailed));
+        assert!(matches!(events.recv().unwrap(), Event::Disconnected));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{trace, who};
+
+// the highest message number in the CLIP spec (19, "you kibitz").
+const LAST_MESSAGE_TYPE: u8 = 19;
+
+/// Not in the spec, and FIBS itself never sends it, but servers that report
+/// protocol errors through CLIP take the next number: `20 <message>`.
+pub const ERROR: u8 = 20;
+
+/// The most of each kind of field kept from a CLIP line; the server's word
+/// isn't taken for how long a field can be. FIBS names are well under this,
+/// and a host name can't be longer.
+pub const MAX_NAME: usize = 32;
+pub const MAX_HOST: usize = 255;
+pub const MAX_MESSAGE: usize = 1000;
+
+/// `field` cut to `max` chars if it's longer, the last of them `…` to show
+/// it was, with a warning traced naming it as `what`.
+pub fn capped(field: &str, max: usize, what: &str) -> String {
+    let Some((cut, _)) = field.char_indices().nth(max.saturating_sub(1)) else {
+        return String::from(field);
+    };
+    if field[cut..].chars().nth(1).is_none() {
+        return String::from(field);
+    }
+    trace::trace(trace::Category::Clip, || format!("{} of {} chars cut to {}", what, field.chars().count(), max));
+    let mut capped = String::from(&field[..cut]);
+    capped.push('…');
+    capped
+}
+
+/// The CLIP message number a line starts with, if it looks like one.
+pub fn message_type(ln: &str) -> Option<u8> {
+    let n = ln.split(' ').next()?;
+    n.parse().ok().filter(|n| (1..=LAST_MESSAGE_TYPE).contains(n) || *n == ERROR)
+}
+
+/// The message of a CLIP error line.
+pub fn error(ln: &str) -> Option<&str> {
+    (message_type(ln) == Some(ERROR)).then(|| ln.split_once(' ').map(|(_, msg)| msg).unwrap_or(""))
+}
+
+/// `ln` prefixed with its CLIP message number, for debugging the parsers.
+pub fn annotate(ln: &str) -> String {
+    match message_type(ln) {
+        Some(n) => format!("[{}] {}", n, ln),
+        None => String::from(ln),
+    }
+}
+
+/// A CLIP line, split into the fields the spec gives it. Our own info is
+/// only picked apart as far as anything uses it.
+#[derive(Debug, PartialEq)]
+pub enum ClipMessage {
+    /// 1: who we logged in as, and when and from where we last did.
+    Welcome { name: String, last_login: u64, last_host: String },
+    /// 2: who we are, and of our settings whether we're away or ready, and
+    /// our experience and rating.
+    OwnInfo { name: String, away: bool, experience: u32, rating: f64, ready: bool },
+    /// 3 and 4: the MOTD starts and ends.
+    MotdBegin,
+    MotdEnd,
+    /// 5, and 6 after the last of them.
+    WhoInfo(who::WhoInfo),
+    WhoEnd,
+    /// 7 and 8: someone logging in or out, and the server's words for it.
+    Login { name: String, message: String },
+    Logout { name: String, message: String },
+    /// 9: a message left for us while we were away, and when.
+    Message { from: String, time: u64, message: String },
+    /// 10 and 11: a message of ours went to someone, or was kept for them.
+    Delivered(String),
+    Saved(String),
+    /// 12 to 15: someone talking to us, to everyone, or in a game.
+    Says { name: String, message: String },
+    Shouts { name: String, message: String },
+    Whispers { name: String, message: String },
+    Kibitzes { name: String, message: String },
+    /// 16 to 19: the server repeating what we said, with who to for a tell.
+    YouSay { name: String, message: String },
+    YouShout(String),
+    YouWhisper(String),
+    YouKibitz(String),
+    /// 20: see `ERROR`.
+    Error(String),
+}
+
+/// The CLIP message `ln` is, if it's one and has the fields it should.
+pub fn parse(ln: &str) -> Option<ClipMessage> {
+    let n = message_type(ln)?;
+    let rest = ln.split_once(' ').map(|(_, rest)| rest).unwrap_or("");
+    let fields: Vec<&str> = rest.split(' ').collect();
+    // a name, then everything after it.
+    let said = || {
+        let (name, message) = rest.split_once(' ').unwrap_or((rest, ""));
+        (!name.is_empty()).then(|| (capped(name, MAX_NAME, "name"), capped(message, MAX_MESSAGE, "message")))
+    };
+    let name = || Some(capped(fields[0], MAX_NAME, "name")).filter(|name| !name.is_empty() && fields.len() == 1);
+    let text = |text| capped(text, MAX_MESSAGE, "message");
+
+    let message = match n {
+        1 if fields.len() == 3 => ClipMessage::Welcome {
+            name: capped(fields[0], MAX_NAME, "name"),
+            last_login: fields[1].parse().ok()?,
+            last_host: capped(fields[2], MAX_HOST, "host"),
+        },
+        2 if fields.len() > 16 => ClipMessage::OwnInfo {
+            name: capped(fields[0], MAX_NAME, "name"),
+            away: fields[5] == "1",
+            experience: fields[9].parse().ok()?,
+            rating: fields[14].parse().ok()?,
+            ready: fields[16] == "1",
+        },
+        3 if rest.is_empty() => ClipMessage::MotdBegin,
+        4 if rest.is_empty() => ClipMessage::MotdEnd,
+        5 => ClipMessage::WhoInfo(who::WhoInfo::parse(ln)?),
+        6 if rest.is_empty() => ClipMessage::WhoEnd,
+        7 => {
+            let (name, message) = said()?;
+            ClipMessage::Login { name, message }
+        }
+        8 => {
+            let (name, message) = said()?;
+            ClipMessage::Logout { name, message }
+        }
+        9 => {
+            let (from, rest) = rest.split_once(' ')?;
+            let (time, message) = rest.split_once(' ').unwrap_or((rest, ""));
+            ClipMessage::Message { from: capped(from, MAX_NAME, "name"), time: time.parse().ok()?, message: capped(message, MAX_MESSAGE, "message") }
+        }
+        10 => ClipMessage::Delivered(name()?),
+        11 => ClipMessage::Saved(name()?),
+        12 => {
+            let (name, message) = said()?;
+            ClipMessage::Says { name, message }
+        }
+        13 => {
+            let (name, message) = said()?;
+            ClipMessage::Shouts { name, message }
+        }
+        14 => {
+            let (name, message) = said()?;
+            ClipMessage::Whispers { name, message }
+        }
+        15 => {
+            let (name, message) = said()?;
+            ClipMessage::Kibitzes { name, message }
+        }
+        16 => {
+            let (name, message) = said()?;
+            ClipMessage::YouSay { name, message }
+        }
+        17 => ClipMessage::YouShout(text(rest)),
+        18 => ClipMessage::YouWhisper(text(rest)),
+        19 => ClipMessage::YouKibitz(text(rest)),
+        ERROR => ClipMessage::Error(text(rest)),
+        _ => return None,
+    };
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn login_sequence() {
+        assert_eq!(
+            parse("1 alice 1041253132 192.168.1.30"),
+            Some(ClipMessage::Welcome { name: String::from("alice"), last_login: 1041253132, last_host: String::from("192.168.1.30") })
+        );
+        assert_eq!(
+            parse("2 myself 1 1 0 0 0 0 1 1 2396 0 1 0 1 3457.85 0 0 0 0 0 Australia/Melbourne"),
+            Some(ClipMessage::OwnInfo { name: String::from("myself"), away: false, experience: 2396, rating: 3457.85, ready: false })
+        );
+        assert_eq!(parse("3"), Some(ClipMessage::MotdBegin));
+        assert_eq!(parse("4"), Some(ClipMessage::MotdEnd));
+        assert!(matches!(parse("5 bob alice - 0 1 1723.45 2301 12 1041253132 host.example.com - -"), Some(ClipMessage::WhoInfo(info)) if info.name == "bob"));
+        assert_eq!(parse("6"), Some(ClipMessage::WhoEnd));
+    }
+
+    #[test]
+    fn players_and_messages() {
+        assert_eq!(
+            parse("7 alice alice logs in."),
+            Some(ClipMessage::Login { name: String::from("alice"), message: String::from("alice logs in.") })
+        );
+        assert_eq!(
+            parse("8 alice alice drops connection."),
+            Some(ClipMessage::Logout { name: String::from("alice"), message: String::from("alice drops connection.") })
+        );
+        assert_eq!(
+            parse("9 bob 1041253132 see you tomorrow"),
+            Some(ClipMessage::Message { from: String::from("bob"), time: 1041253132, message: String::from("see you tomorrow") })
+        );
+        assert_eq!(parse("10 bob"), Some(ClipMessage::Delivered(String::from("bob"))));
+        assert_eq!(parse("11 bob"), Some(ClipMessage::Saved(String::from("bob"))));
+    }
+
+    #[test]
+    fn chat() {
+        assert_eq!(parse("12 bob hello there"), Some(ClipMessage::Says { name: String::from("bob"), message: String::from("hello there") }));
+        assert_eq!(parse("13 bob hi all"), Some(ClipMessage::Shouts { name: String::from("bob"), message: String::from("hi all") }));
+        assert_eq!(parse("15 bob nice roll"), Some(ClipMessage::Kibitzes { name: String::from("bob"), message: String::from("nice roll") }));
+        assert_eq!(parse("16 bob hello"), Some(ClipMessage::YouSay { name: String::from("bob"), message: String::from("hello") }));
+        assert_eq!(parse("17 hi all"), Some(ClipMessage::YouShout(String::from("hi all"))));
+        assert_eq!(parse("20 unexpected message type 42"), Some(ClipMessage::Error(String::from("unexpected message type 42"))));
+    }
+
+    #[test]
+    fn over_long_fields_cut() {
+        let long = "x".repeat(5000);
+        let cut = format!("{}…", "x".repeat(MAX_NAME - 1));
+
+        assert_eq!(
+            parse(&format!("12 {} {}", long, long)),
+            Some(ClipMessage::Says { name: cut.clone(), message: format!("{}…", "x".repeat(MAX_MESSAGE - 1)) })
+        );
+        assert_eq!(parse(&format!("10 {}", long)), Some(ClipMessage::Delivered(cut.clone())));
+        assert!(matches!(
+            parse(&format!("1 alice 1041253132 {}", long)),
+            Some(ClipMessage::Welcome { last_host, .. }) if last_host.chars().count() == MAX_HOST && last_host.ends_with('…')
+        ));
+        assert!(matches!(
+            parse(&format!("5 {} {} - 0 1 1723.45 2301", long, long)),
+            Some(ClipMessage::WhoInfo(info)) if info.name == cut && info.opponent == Some(cut.clone())
+        ));
+
+        // exactly the limit, and multi-byte chars, are left as they are.
+        let full = "é".repeat(MAX_NAME);
+        assert_eq!(capped(&full, MAX_NAME, "name"), full);
+        assert_eq!(capped(&format!("{}é", full), MAX_NAME, "name"), format!("{}…", "é".repeat(MAX_NAME - 1)));
+    }
+
+    #[test]
+    fn not_quite_clip() {
+        assert_eq!(parse("2 myself 1 1 0"), None);
+        assert_eq!(parse("3 players are waiting"), None);
+        assert_eq!(parse("5 minutes until the tournament"), None);
+        assert_eq!(parse("10 bob alice"), None);
+        assert_eq!(parse("bob says: hi"), None);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::{Error, Result};
+
+#[cfg(feature = "clipboard")]
+use std::{io::prelude::*, process};
+
+// tried in order; the first one that runs successfully wins.
+#[cfg(feature = "clipboard")]
+static CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+    &["pbcopy"],
+];
+
+#[cfg(feature = "clipboard")]
+fn copy_with(argv: &[&str], text: &str) -> Result<()> {
+    let mut child = process::Command::new(argv[0])
+        .args(&argv[1..])
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .spawn()?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    if child.wait()?.success() {
+        Ok(())
+    } else {
+        Err(Error::IOError(format!("{} exited unsuccessfully", argv[0])))
+    }
+}
+
+#[cfg(feature = "clipboard")]
+pub fn copy(text: &str) -> Result<()> {
+    CLIPBOARD_COMMANDS
+        .iter()
+        .find(|argv| copy_with(argv, text).is_ok())
+        .map(|_| ())
+        .ok_or_else(|| Error::IOError(String::from("no clipboard program available")))
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy(_text: &str) -> Result<()> {
+    Err(Error::IOError(String::from("built without clipboard support")))
+}
@@ -0,0 +1,129 @@
+use std::{collections, env, fs, path};
+use std::io::prelude::*;
+
+use crate::Result;
+
+/// The 16 standard terminal colors, by the names `/color` accepts; a color
+/// is its index here.
+pub static NAMES: [&str; 16] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    "lightblack", "lightred", "lightgreen", "lightyellow", "lightblue", "lightmagenta", "lightcyan", "lightwhite",
+];
+
+// the ones readable on both dark and light backgrounds, for hashing names onto.
+static PALETTE: [u8; 12] = [1, 2, 3, 4, 5, 6, 9, 10, 11, 12, 13, 14];
+
+pub fn by_name(name: &str) -> Option<u8> {
+    NAMES.iter().position(|n| n.eq_ignore_ascii_case(name)).map(|i| i as u8)
+}
+
+/// A player's color: their override if they have one, otherwise one picked
+/// from a hash of their name, so it's the same every session.
+pub fn for_player(player: &str, overrides: &collections::HashMap<String, u8>) -> u8 {
+    let player = player.to_lowercase();
+    if let Some(color) = overrides.get(&player) {
+        return *color;
+    }
+
+    // FNV-1a, which unlike std's hashers is guaranteed not to change under us.
+    let hash = player.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+    PALETTE[(hash % PALETTE.len() as u64) as usize]
+}
+
+fn path() -> Option<path::PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(path::PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| path::Path::new(&home).join(".config")))?;
+    Some(dir.join("fibsterm").join("colors"))
+}
+
+/// Color overrides saved by `/color`, one "player color" pair per line.
+pub fn load() -> collections::HashMap<String, u8> {
+    let contents = path()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .unwrap_or_default();
+
+    contents
+        .lines()
+        .filter_map(|ln| {
+            let (player, color) = ln.split_once(' ')?;
+            Some((player.to_lowercase(), by_name(color.trim())?))
+        })
+        .collect()
+}
+
+pub fn save(overrides: &collections::HashMap<String, u8>) -> Result<()> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut players: Vec<&String> = overrides.keys().collect();
+    players.sort();
+
+    let mut f = fs::File::create(path)?;
+    for player in players {
+        writeln!(f, "{} {}", player, NAMES[overrides[player] as usize])?;
+    }
+
+    Ok(())
+}
+
+/// The colors of the client's own frame and prompt: the accent for the
+/// borders and titles, and the prompt's. None leaves the terminal's own.
+#[derive(Clone, Copy, Default)]
+pub struct Chrome {
+    pub accent: Option<u8>,
+    pub prompt: Option<u8>,
+}
+
+impl Chrome {
+    pub fn frame(&self, s: &str) -> String {
+        tint(s, self.accent)
+    }
+
+    pub fn prompt(&self, s: &str) -> String {
+        tint(s, self.prompt)
+    }
+}
+
+// `s` in `color`, and back to the terminal's own after it so nothing
+// written next comes out in it too.
+fn tint(s: &str, color: Option<u8>) -> String {
+    match color {
+        Some(c) => format!("{}{}{}", termion::color::Fg(termion::color::AnsiValue(c)), s, termion::color::Fg(termion::color::Reset)),
+        None => String::from(s),
+    }
+}
+
+/// `ln` with the first mention of `player` in their color.
+pub fn paint(ln: &str, player: &str, color: u8) -> String {
+    match ln.find(player) {
+        Some(i) => format!("{}{}{}{}{}",
+            &ln[..i],
+            termion::color::Fg(termion::color::AnsiValue(color)),
+            player,
+            termion::color::Fg(termion::color::Reset),
+            &ln[i + player.len()..]),
+        None => String::from(ln),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chrome_never_bleeds() {
+        let reset = termion::color::Fg(termion::color::Reset).to_string();
+        let chrome = Chrome { accent: Some(6), prompt: Some(10) };
+        assert!(chrome.frame("║").ends_with(&reset));
+        assert!(chrome.prompt(">").ends_with(&reset));
+
+        assert_eq!(Chrome::default().frame("║"), "║");
+        assert_eq!(Chrome::default().prompt(">"), ">");
+    }
+}
@@ -0,0 +1,107 @@
+/// A client-side command, entered in the INPUT box with a leading `/`.
+///
+/// Anything not starting with `/` is sent to FIBS verbatim.
+pub enum Command {
+    CopyBoard,
+    CopyGnubgId,
+    Reconnect,
+    Mute(String),
+    Unmute(String),
+    Mutes,
+    Ready,
+    Dnd,
+    HistoryBack,
+    HistoryForward,
+    /// Show the next of the games we have boards for.
+    NextGame,
+    ClipNumbers,
+    Color(String, String),
+    Panic,
+    Rank,
+    /// `/t <player> <message>`.
+    Tell(String, String),
+    /// `/r <message>`, to whoever we last told or were told by.
+    Reply(String),
+    /// `/tells` lists the tells we've had; `/tells <n>` makes `/r` answer
+    /// the nth.
+    Tells(Option<usize>),
+    /// Shows the opening position, drawn as boards are.
+    DemoBoard,
+    /// `/trace <category> on|off`, `all` for every category; `/trace` alone
+    /// says what's on.
+    Trace(Option<(String, bool)>),
+    /// `/exportwho <file> [csv|json]`: the players we've had WHO lines for,
+    /// written out; see `who::export`.
+    ExportWho(String, Option<String>),
+    /// Looks the servers up again and lists their addresses, marking the
+    /// one we're connected to.
+    Resolve,
+    /// `/quit`, Ctrl-C or Ctrl-D: log off and put the terminal back.
+    Quit,
+    Unknown(String),
+}
+
+// what follows the first `n` words of `ln`, as typed.
+fn rest(ln: &str, n: usize) -> Option<&str> {
+    let mut rest = ln.trim_start();
+    for _ in 0..n {
+        rest = rest.split_once(char::is_whitespace)?.1.trim_start();
+    }
+    Some(rest).filter(|r| !r.is_empty())
+}
+
+/// Whether `ln` is `/quit`, after which there's no more input to read.
+pub fn quits(ln: &str) -> bool {
+    matches!(Command::parse(ln), Some(Command::Quit))
+}
+
+impl Command {
+    pub fn parse(ln: &str) -> Option<Command> {
+        let ln = ln.strip_prefix('/')?;
+        let mut words = ln.split_whitespace();
+
+        let cmd = match words.next() {
+            Some("copyboard") => Some(Command::CopyBoard),
+            Some("gnubgid") => Some(Command::CopyGnubgId),
+            Some("reconnect") => Some(Command::Reconnect),
+            Some("mute") => words.next().map(|p| Command::Mute(String::from(p))),
+            Some("unmute") => words.next().map(|p| Command::Unmute(String::from(p))),
+            Some("mutes") => Some(Command::Mutes),
+            Some("ready") => Some(Command::Ready),
+            Some("dnd") => Some(Command::Dnd),
+            Some("back") => Some(Command::HistoryBack),
+            Some("forward") => Some(Command::HistoryForward),
+            Some("next") => Some(Command::NextGame),
+            Some("clipnums") => Some(Command::ClipNumbers),
+            Some("color") => match (words.next(), words.next()) {
+                (Some(player), Some(color)) => Some(Command::Color(String::from(player), String::from(color))),
+                _ => None,
+            },
+            Some("panic") => Some(Command::Panic),
+            Some("rank") => Some(Command::Rank),
+            Some("t") => match (words.next(), rest(ln, 2)) {
+                (Some(player), Some(message)) => Some(Command::Tell(String::from(player), String::from(message))),
+                _ => None,
+            },
+            Some("r") => rest(ln, 1).map(|message| Command::Reply(String::from(message))),
+            Some("demoboard") => Some(Command::DemoBoard),
+            Some("trace") => match (words.next(), words.next()) {
+                (None, _) => Some(Command::Trace(None)),
+                (Some(what), Some("on")) => Some(Command::Trace(Some((String::from(what), true)))),
+                (Some(what), Some("off")) => Some(Command::Trace(Some((String::from(what), false)))),
+                _ => None,
+            },
+            Some("exportwho") => words.next().map(|path| Command::ExportWho(String::from(path), words.next().map(String::from))),
+            Some("resolve") => Some(Command::Resolve),
+            Some("quit") => Some(Command::Quit),
+            Some("tells") => match words.next() {
+                Some(n) => n.parse().ok().map(|n| Command::Tells(Some(n))),
+                None => Some(Command::Tells(None)),
+            },
+            _ => None,
+        };
+
+        // still ours even if we couldn't make sense of it; don't send it to FIBS.
+        cmd.or_else(|| Some(Command::Unknown(String::from(ln))))
+    }
+}
@@ -0,0 +1,596 @@
+use std::{env, path, str, time};
+
+use crate::idle::{Exemptions, IdleReset};
+use crate::terminal;
+use crate::updates::QueuePolicy;
+use crate::{colors, network, render, trace, who};
+
+const DEFAULT_RECONNECT_WINDOW_SECS: u64 = 300;
+static DEFAULT_AWAY_MESSAGE: &str = "Away from the keyboard.";
+static DEFAULT_DND_MESSAGE: &str = "Sorry, I'm not taking invitations right now.";
+
+/// What's left on the terminal when the TUI exits.
+#[derive(Clone, Copy, PartialEq)]
+pub enum OnExit {
+    /// Nothing; the screen is cleared.
+    Clear,
+    /// The last board, printed below where the TUI was so it stays in the
+    /// terminal's scrollback.
+    Summary,
+}
+
+/// Which of Enter and Alt+Enter sends the INPUT box, the other starting a
+/// new line in it.
+#[derive(Clone, Copy, PartialEq)]
+pub enum EnterKey {
+    Send,
+    Newline,
+}
+
+impl EnterKey {
+    /// Whether `key` sends the INPUT box, as this is set.
+    pub fn sends(self, key: &termion::event::Key) -> bool {
+        use termion::event::Key;
+        matches!((self, key), (EnterKey::Send, Key::Char('\n')) | (EnterKey::Newline, Key::Alt('\r' | '\n')))
+    }
+}
+
+/// When a dropped connection is picked up again by itself.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Reconnect {
+    Always,
+    /// Only in the middle of a match of our own; otherwise a disconnect
+    /// ends the client.
+    InMatch,
+}
+
+/// Whether a reconnect logs back in by itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReconnectLogin {
+    /// With the stored credentials, without asking.
+    Reuse,
+    /// At the prompts, as the first time, so nothing is sent for us on a
+    /// machine that's shared.
+    Prompt,
+}
+
+/// How we're told a reconnect worked, or that we've given up trying.
+#[derive(Clone, Copy)]
+pub struct ReconnectNotify {
+    /// A notification beside the FIBS box; an urgent one on giving up.
+    pub visual: bool,
+    /// The terminal bell.
+    pub bell: bool,
+}
+
+/// What the panic key does, for when someone walks up to the screen.
+#[derive(Clone, Copy)]
+pub struct PanicActions {
+    /// Empty the FIBS box.
+    pub clear: bool,
+    /// Mark us away.
+    pub away: bool,
+    /// Turn on do-not-disturb.
+    pub dnd: bool,
+    /// Hide chat until the key is pressed again.
+    pub quiet: bool,
+}
+
+/// Client settings, read from `FIBS_*` environment variables and flags.
+pub struct Config {
+    /// How long after a disconnect a reconnect picks up the old session view
+    /// (scrollback and game state) rather than starting from scratch.
+    /// `FIBS_RECONNECT_WINDOW`, in seconds.
+    pub reconnect_window: time::Duration,
+    /// `FIBS_RECONNECT=always` (the default) or `match`.
+    pub reconnect: Reconnect,
+    /// How many times to try reconnecting by ourselves before leaving it to
+    /// `/reconnect`. `FIBS_RECONNECT_ATTEMPTS`, 10 by default.
+    pub reconnect_attempts: u32,
+    /// `FIBS_RECONNECT_LOGIN=reuse` or `prompt`. Reuse by default when a
+    /// password is stored, and prompt when one isn't; there's nothing to
+    /// reuse without one either way.
+    pub reconnect_login: ReconnectLogin,
+    /// `FIBS_RECONNECT_NOTIFY=visual,bell`; both by default, `off` for
+    /// neither.
+    pub reconnect_notify: ReconnectNotify,
+    /// Plain line-by-line output and input instead of the boxed TUI, for
+    /// screen readers. `--accessible` or `FIBS_ACCESSIBLE=1`.
+    pub accessible: bool,
+    /// Caption each board with the move that led to it, e.g. "bob moved
+    /// 8/5 6/5". `--describe-moves` or `FIBS_DESCRIBE_MOVES=1`.
+    pub describe_moves: bool,
+    /// Cap on updates queued for the front-end; unbounded if unset.
+    /// `FIBS_UPDATE_QUEUE`.
+    pub update_queue: Option<usize>,
+    /// What to do once that queue is full. `FIBS_UPDATE_POLICY=block` (the
+    /// default) or `coalesce`.
+    pub update_policy: QueuePolicy,
+    /// The most the TUI draws a second; what comes in between is drawn
+    /// together with the next frame. `FIBS_MAX_FPS`, 60 by default; 0 draws
+    /// every update as it comes, and lower saves power on slow machines.
+    pub max_fps: Option<u32>,
+    /// Players muted from the start, comma separated. `FIBS_MUTES`.
+    pub mutes: Vec<String>,
+    /// Told to anyone who invites us while do-not-disturb is on.
+    /// `FIBS_DND_MESSAGE`.
+    pub dnd_message: String,
+    /// Mark us away after this long idle; off if unset. `FIBS_AUTO_AWAY`, in
+    /// minutes.
+    pub auto_away: Option<time::Duration>,
+    pub away_message: String,
+    /// What resets the idle timer. `FIBS_IDLE_RESET=key` (the default) or
+    /// `sent`.
+    pub idle_reset: IdleReset,
+    /// Games that keep us from going away while idle, comma separated.
+    /// `FIBS_IDLE_EXEMPT=playing,watching`; `playing` by default.
+    pub idle_exempt: Exemptions,
+    /// Box drawing characters. `FIBS_THEME=unicode` or `ascii`; otherwise
+    /// whichever the terminal's locale supports, and ascii if the unicode
+    /// ones would come out two columns wide and break the frame. That can
+    /// be said outright with `--box-width=2` or `FIBS_BOX_WIDTH=2`, or
+    /// denied with 1.
+    pub theme: &'static terminal::Theme,
+    /// `FIBS_COLOR=always` (or `on`) or `never` (`off`); otherwise, as for
+    /// `auto`, on when stdout is a terminal that isn't dumb and `NO_COLOR`
+    /// isn't set.
+    pub color: bool,
+    /// The colors of our own frame and prompt, when there's color at all:
+    /// `FIBS_ACCENT` for the borders and titles, cyan by default, and
+    /// `FIBS_PROMPT_COLOR` for the `>`, lightgreen by default. Either can be
+    /// any name `/color` takes, or `none` for the terminal's own.
+    pub chrome: colors::Chrome,
+    /// `FIBS_MOUSE=on` or `off`; otherwise on for terminals known to report
+    /// the mouse.
+    pub mouse: bool,
+    /// Prefix CLIP lines with their message number when displayed.
+    /// `--debug-clip` or `FIBS_DEBUG_CLIP=1`; `/clipnums` toggles it.
+    pub clip_numbers: bool,
+    /// Rejoin long messages the server wrapped at 80 columns and rewrap them
+    /// to the width of the FIBS box. Guesswork, so off by default.
+    /// `--unwrap` or `FIBS_UNWRAP=1`.
+    pub unwrap: bool,
+    /// Drop the spaces lines trail off with, as they come in, except on
+    /// boards. `FIBS_TRIM=off` keeps them.
+    pub trim: bool,
+    /// FIBS commands sent after each login, separated by semicolons, e.g.
+    /// `FIBS_ON_LOGIN="toggle ready; set timezone Europe/London"`. The
+    /// boardstyle we parse is set first without being asked for.
+    pub on_login: Vec<String>,
+    /// Log in and print only tells, invitations and friends logging in, with
+    /// no TUI. `--monitor` or `FIBS_MONITOR=1`.
+    pub monitor: bool,
+    /// Players whose logins the monitor reports, comma separated.
+    /// `FIBS_FRIENDS`.
+    pub friends: Vec<String>,
+    /// Credentials for logging in unattended. `FIBS_USER`, `FIBS_PASSWORD`.
+    pub user: Option<String>,
+    pub password: Option<String>,
+    /// `FIBS_ON_EXIT=clear` (the default) or `summary`.
+    pub on_exit: OnExit,
+    /// The panic key: `FIBS_PANIC_KEY=F12` (the default), any other function
+    /// key, or `ctrl-<letter>`. Pressed again, it undoes what it can.
+    pub panic_key: termion::event::Key,
+    /// `FIBS_PANIC=clear,away,dnd,quiet`; all of them by default.
+    pub panic: PanicActions,
+    /// `FIBS_ENTER=send` (the default; Alt+Enter starts a new line) or
+    /// `newline` (Alt+Enter sends). Each line is sent to FIBS on its own.
+    pub enter: EnterKey,
+    /// Colors for WHO lines, when color is on. `FIBS_WHO_NEW` is the
+    /// experience below which a player counts as new, `FIBS_WHO_GRADES` the
+    /// ratings each grade starts at, and `FIBS_WHO_COLORS` color names for
+    /// new players and then each grade, all comma separated, e.g.
+    /// `FIBS_WHO_GRADES=1500,1700,1900`,
+    /// `FIBS_WHO_COLORS=lightblack,white,lightgreen,lightcyan,lightyellow`.
+    pub who_grading: who::Grading,
+    /// Servers to try in turn, e.g. `FIBS_SERVERS=fibs.com:4321,mirror.example.org`,
+    /// moving on to the next when one can't be reached. A missing port is
+    /// the usual one. `FIBS_HOSTNAME` and `FIBS_PORT` if unset.
+    pub servers: Vec<(String, u16)>,
+    /// How long each server gets to answer. `FIBS_CONNECT_TIMEOUT`, in
+    /// seconds; 10 by default.
+    pub connect_timeout: time::Duration,
+    /// How long the connection may sit quiet before the kernel checks it's
+    /// still there. `FIBS_KEEPALIVE_SECS`, 60 by default; 0 for never.
+    pub keepalive: Option<time::Duration>,
+    /// How long whois, who, watch and invite get to be answered before
+    /// we say they weren't. `FIBS_COMMAND_TIMEOUT`, in seconds; 10 by
+    /// default, 0 for never.
+    pub command_timeout: Option<time::Duration>,
+    /// How big boards are drawn, from 1 up; F7 and F8 zoom out and in.
+    /// Never bigger than fits. `FIBS_ZOOM`.
+    pub zoom: u8,
+    /// How stacks too tall to draw are shown: `FIBS_OVERFLOW=count` (the
+    /// default, the top checker replaced by the stack's size), `plus` (by
+    /// how many aren't drawn, e.g. "+3") or `compress` (all stacks scaled
+    /// down to fit the tallest).
+    pub overflow: render::Overflow,
+    /// How the points are numbered: `FIBS_POINT_LABELS=standard` (the
+    /// default; 1 to 24 from our home), `relative` (from the home of
+    /// whoever is on roll) or `off`.
+    pub labels: render::Labels,
+    /// How much of the FIBS box the live board takes, as a fraction, with
+    /// chat below it; F10 and F11 shrink and grow it. `FIBS_SPLIT`, 0.5 by
+    /// default; 0 to show boards in the buffer instead.
+    pub split: f32,
+    /// How many games, ours and watched, to keep boards for at once; F9
+    /// switches between them. `FIBS_WATCH_LIMIT`, 4 by default.
+    pub watch_limit: usize,
+    /// Where to record everything received from FIBS, with timings, for
+    /// `--replay`. `FIBS_RECORD`.
+    pub record: Option<path::PathBuf>,
+    /// A recording to play back instead of connecting: `--replay <file>`.
+    pub replay: Option<path::PathBuf>,
+    /// How long notifications stay up, `FIBS_NOTIFY_SECS` (5 by default),
+    /// and how many at once, `FIBS_NOTIFY_MAX` (3; 0 for none).
+    pub notify_timeout: time::Duration,
+    pub notify_max: usize,
+    /// Where to POST our match results as they end, e.g.
+    /// `FIBS_WEBHOOK=http://example.org/fibs/results`; see
+    /// `webhook::MatchResult` for what's sent. Needs the `webhook` feature.
+    pub webhook: Option<String>,
+    /// How many tells `/tells` remembers. `FIBS_TELLS`, 20 by default.
+    pub tells: usize,
+    /// How many lines typed Up and Down go back through.
+    /// `FIBS_INPUT_HISTORY`, 500 by default.
+    pub input_history: usize,
+    /// Parsers to trace from the start, e.g. `FIBS_TRACE=board,clip`; `/trace`
+    /// switches them at runtime.
+    pub trace: Vec<trace::Category>,
+    /// Where traces go. `FIBS_TRACE_FILE`, `fibsterm.trace` by default.
+    pub trace_file: path::PathBuf,
+    /// How each traced line is written; see `trace::Format`, e.g.
+    /// `FIBS_TRACE_FORMAT='{unix} [{category}] {text}'`. Checked at startup.
+    pub trace_format: String,
+    /// Settings that couldn't be made sense of, and what was used instead,
+    /// to be shown once the client's up.
+    pub warnings: Vec<String>,
+}
+
+// how settings are looked up by name: in the environment, or in a map for
+// tests, which mustn't change the environment under each other.
+type Env<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+fn flag(env: Env, arg: &str, name: &str) -> bool {
+    env::args().skip(1).any(|a| a == arg)
+        || matches!(env(name).as_deref(), Some("1") | Some("true") | Some("yes"))
+}
+
+// an explicit on/off setting, falling back to what the terminal can do.
+fn switch(env: Env, name: &str, detected: bool) -> bool {
+    match env(name).as_deref() {
+        Some("on") | Some("1") | Some("true") | Some("yes") | Some("always") => true,
+        Some("off") | Some("0") | Some("false") | Some("no") | Some("never") => false,
+        _ => detected,
+    }
+}
+
+// one of the frame's colors, by name; `none` for none. a name we don't know
+// is the default, and said so in `warnings`, as /color would.
+fn chrome_color(env: Env, name: &str, default: u8, warnings: &mut Vec<String>) -> Option<u8> {
+    match env(name).as_deref() {
+        Some("none") => None,
+        Some(val) => colors::by_name(val).or_else(|| {
+            warnings.push(format!("unknown color {:?} for {}; try one of {}", val, name, colors::NAMES.join(", ")));
+            Some(default)
+        }),
+        None => Some(default),
+    }
+}
+
+fn key(name: &str) -> Option<termion::event::Key> {
+    let lower = name.to_lowercase();
+    if let Some(n) = lower.strip_prefix('f') {
+        return n.parse().ok().filter(|n| (1..=12).contains(n)).map(termion::event::Key::F);
+    }
+    let c = lower.strip_prefix("ctrl-")?;
+    let mut chars = c.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_lowercase() => Some(termion::event::Key::Ctrl(c)),
+        _ => None,
+    }
+}
+
+fn who_grading(env: Env) -> who::Grading {
+    let default = who::Grading::default();
+    let list = |name| -> Option<Vec<String>> {
+        env(name).map(|val| val.split(',').map(|v| String::from(v.trim())).collect())
+    };
+
+    who::Grading {
+        new: var(env, "FIBS_WHO_NEW").unwrap_or(default.new),
+        thresholds: list("FIBS_WHO_GRADES")
+            .and_then(|grades| grades.iter().map(|g| g.parse().ok()).collect())
+            .unwrap_or(default.thresholds),
+        palette: list("FIBS_WHO_COLORS")
+            .and_then(|names| names.iter().map(|n| colors::by_name(n)).collect())
+            .unwrap_or(default.palette),
+    }
+}
+
+// `--box-width=N`, or FIBS_BOX_WIDTH.
+fn box_width(env: Env) -> Option<u8> {
+    env::args()
+        .skip(1)
+        .find_map(|a| a.strip_prefix("--box-width=").and_then(|n| n.parse().ok()))
+        .or_else(|| var(env, "FIBS_BOX_WIDTH"))
+}
+
+fn servers(env: Env) -> Vec<(String, u16)> {
+    let servers: Vec<(String, u16)> = env("FIBS_SERVERS")
+        .map(|val| {
+            val.split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| match s.rsplit_once(':').and_then(|(host, port)| Some((host, port.parse().ok()?))) {
+                    Some((host, port)) => (String::from(host), port),
+                    None => (String::from(s), network::DEFAULT_FIBS_PORT),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if !servers.is_empty() {
+        return servers;
+    }
+
+    let hostname = env("FIBS_HOSTNAME").unwrap_or_else(|| String::from(network::DEFAULT_FIBS_SERVER));
+    vec![(hostname, var(env, "FIBS_PORT").unwrap_or(network::DEFAULT_FIBS_PORT))]
+}
+
+fn var<T: str::FromStr>(env: Env, name: &str) -> Option<T> {
+    env(name).and_then(|val| val.parse().ok())
+}
+
+impl Config {
+    pub fn from_env() -> Config {
+        Config::from_lookup(&|name| env::var(name).ok())
+    }
+
+    /// The configuration as `env` has it, by variable name.
+    pub fn from_lookup(env: Env) -> Config {
+        let color = switch(env, "FIBS_COLOR", terminal::capabilities().color);
+        let mut warnings = Vec::new();
+        let chrome = colors::Chrome {
+            accent: chrome_color(env, "FIBS_ACCENT", 6, &mut warnings),
+            prompt: chrome_color(env, "FIBS_PROMPT_COLOR", 10, &mut warnings),
+        };
+        // no time at all to connect in is every connection failing.
+        let connect_timeout = match var(env, "FIBS_CONNECT_TIMEOUT") {
+            Some(0) => {
+                let default = network::DEFAULT_CONNECT_TIMEOUT.as_secs();
+                warnings.push(format!("FIBS_CONNECT_TIMEOUT must be at least 1; waiting {}s instead", default));
+                None
+            }
+            secs => secs,
+        };
+
+        Config {
+            reconnect_window: time::Duration::from_secs(
+                var(env, "FIBS_RECONNECT_WINDOW").unwrap_or(DEFAULT_RECONNECT_WINDOW_SECS)
+            ),
+            reconnect: match env("FIBS_RECONNECT").as_deref() {
+                Some("match") => Reconnect::InMatch,
+                _ => Reconnect::Always,
+            },
+            reconnect_attempts: var(env, "FIBS_RECONNECT_ATTEMPTS").unwrap_or(10),
+            reconnect_login: match env("FIBS_RECONNECT_LOGIN").as_deref() {
+                Some("reuse") => ReconnectLogin::Reuse,
+                Some("prompt") => ReconnectLogin::Prompt,
+                _ if env("FIBS_PASSWORD").is_some_and(|p| !p.is_empty()) => ReconnectLogin::Reuse,
+                _ => ReconnectLogin::Prompt,
+            },
+            reconnect_notify: match env("FIBS_RECONNECT_NOTIFY") {
+                Some(val) => ReconnectNotify {
+                    visual: val.split(',').any(|a| a.trim() == "visual"),
+                    bell: val.split(',').any(|a| a.trim() == "bell"),
+                },
+                None => ReconnectNotify { visual: true, bell: true },
+            },
+            accessible: flag(env, "--accessible", "FIBS_ACCESSIBLE"),
+            describe_moves: flag(env, "--describe-moves", "FIBS_DESCRIBE_MOVES"),
+            update_queue: var(env, "FIBS_UPDATE_QUEUE").filter(|bound| *bound > 0),
+            update_policy: match env("FIBS_UPDATE_POLICY").as_deref() {
+                Some("coalesce") => QueuePolicy::Coalesce,
+                _ => QueuePolicy::Block,
+            },
+            max_fps: Some(var(env, "FIBS_MAX_FPS").unwrap_or(60)).filter(|fps| *fps > 0),
+            mutes: env("FIBS_MUTES")
+                .map(|val| val.split(',').map(|p| String::from(p.trim())).filter(|p| !p.is_empty()).collect())
+                .unwrap_or_default(),
+            dnd_message: env("FIBS_DND_MESSAGE").unwrap_or_else(|| String::from(DEFAULT_DND_MESSAGE)),
+            auto_away: var::<u64>(env, "FIBS_AUTO_AWAY")
+                .filter(|mins| *mins > 0)
+                .map(|mins| time::Duration::from_secs(mins * 60)),
+            away_message: env("FIBS_AWAY_MESSAGE").unwrap_or_else(|| String::from(DEFAULT_AWAY_MESSAGE)),
+            idle_reset: match env("FIBS_IDLE_RESET").as_deref() {
+                Some("sent") => IdleReset::Sent,
+                _ => IdleReset::AnyKey,
+            },
+            idle_exempt: match env("FIBS_IDLE_EXEMPT") {
+                Some(val) => Exemptions {
+                    playing: val.split(',').any(|e| e.trim() == "playing"),
+                    watching: val.split(',').any(|e| e.trim() == "watching"),
+                },
+                None => Exemptions { playing: true, watching: false },
+            },
+            theme: match env("FIBS_THEME").as_deref() {
+                Some("ascii") => &terminal::ASCII,
+                Some("unicode") => &terminal::UNICODE,
+                _ if !terminal::capabilities().unicode => &terminal::ASCII,
+                _ => match box_width(env) {
+                    Some(2) => &terminal::ASCII,
+                    Some(_) => &terminal::UNICODE,
+                    None if terminal::capabilities().wide_boxes => &terminal::ASCII,
+                    None => &terminal::UNICODE,
+                },
+            },
+            color,
+            chrome: if color { chrome } else { colors::Chrome::default() },
+            mouse: switch(env, "FIBS_MOUSE", terminal::capabilities().mouse),
+            clip_numbers: flag(env, "--debug-clip", "FIBS_DEBUG_CLIP"),
+            unwrap: flag(env, "--unwrap", "FIBS_UNWRAP"),
+            trim: switch(env, "FIBS_TRIM", true),
+            on_login: env("FIBS_ON_LOGIN")
+                .map(|val| val.split(';').map(|c| String::from(c.trim())).filter(|c| !c.is_empty()).collect())
+                .unwrap_or_default(),
+            monitor: flag(env, "--monitor", "FIBS_MONITOR"),
+            friends: env("FIBS_FRIENDS")
+                .map(|val| val.split(',').map(|p| String::from(p.trim())).filter(|p| !p.is_empty()).collect())
+                .unwrap_or_default(),
+            user: env("FIBS_USER").filter(|u| !u.is_empty()),
+            password: env("FIBS_PASSWORD").filter(|p| !p.is_empty()),
+            on_exit: match env("FIBS_ON_EXIT").as_deref() {
+                Some("summary") => OnExit::Summary,
+                _ => OnExit::Clear,
+            },
+            panic_key: env("FIBS_PANIC_KEY")
+                .and_then(|name| key(&name))
+                .unwrap_or(termion::event::Key::F(12)),
+            panic: match env("FIBS_PANIC") {
+                Some(val) => PanicActions {
+                    clear: val.split(',').any(|a| a.trim() == "clear"),
+                    away: val.split(',').any(|a| a.trim() == "away"),
+                    dnd: val.split(',').any(|a| a.trim() == "dnd"),
+                    quiet: val.split(',').any(|a| a.trim() == "quiet"),
+                },
+                None => PanicActions { clear: true, away: true, dnd: true, quiet: true },
+            },
+            enter: match env("FIBS_ENTER").as_deref() {
+                Some("newline") => EnterKey::Newline,
+                _ => EnterKey::Send,
+            },
+            who_grading: who_grading(env),
+            zoom: var(env, "FIBS_ZOOM").unwrap_or(render::MIN_ZOOM).clamp(render::MIN_ZOOM, render::MAX_ZOOM),
+            overflow: match env("FIBS_OVERFLOW").as_deref() {
+                Some("plus") => render::Overflow::Plus,
+                Some("compress") => render::Overflow::Compress,
+                _ => render::Overflow::Count,
+            },
+            labels: match env("FIBS_POINT_LABELS").as_deref() {
+                Some("relative") => render::Labels::Relative,
+                Some("off") => render::Labels::Off,
+                _ => render::Labels::Standard,
+            },
+            split: var(env, "FIBS_SPLIT").unwrap_or(0.5_f32).clamp(0.0, 1.0),
+            watch_limit: var(env, "FIBS_WATCH_LIMIT").unwrap_or(4),
+            tells: var(env, "FIBS_TELLS").unwrap_or(20),
+            input_history: var(env, "FIBS_INPUT_HISTORY").unwrap_or(500),
+            webhook: env("FIBS_WEBHOOK").filter(|url| !url.is_empty()),
+            notify_timeout: time::Duration::from_secs(var(env, "FIBS_NOTIFY_SECS").unwrap_or(5)),
+            notify_max: var(env, "FIBS_NOTIFY_MAX").unwrap_or(3),
+            record: env("FIBS_RECORD").map(path::PathBuf::from),
+            replay: env::args().skip_while(|a| a != "--replay").nth(1).map(path::PathBuf::from),
+            servers: servers(env),
+            keepalive: Some(var(env, "FIBS_KEEPALIVE_SECS").unwrap_or(60))
+                .filter(|secs| *secs > 0)
+                .map(time::Duration::from_secs),
+            command_timeout: Some(var(env, "FIBS_COMMAND_TIMEOUT").unwrap_or(10))
+                .filter(|secs| *secs > 0)
+                .map(time::Duration::from_secs),
+            connect_timeout: connect_timeout
+                .map(time::Duration::from_secs)
+                .unwrap_or(network::DEFAULT_CONNECT_TIMEOUT),
+            trace: env("FIBS_TRACE")
+                .map(|val| val.split(',').filter_map(|c| c.trim().parse().ok()).collect())
+                .unwrap_or_default(),
+            trace_file: env("FIBS_TRACE_FILE")
+                .map(path::PathBuf::from)
+                .unwrap_or_else(|| path::PathBuf::from("fibsterm.trace")),
+            trace_format: env("FIBS_TRACE_FORMAT").unwrap_or_else(|| String::from(trace::Format::DEFAULT)),
+            warnings,
+        }
+    }
+
+    /// The user and password a reconnect logs back in with, if it's to
+    /// reuse them.
+    pub fn reconnect_credentials(&self) -> Option<(&str, &str)> {
+        match (self.reconnect_login, &self.user, &self.password) {
+            (ReconnectLogin::Reuse, Some(user), Some(password)) => Some((user, password)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections;
+
+    // settings as if these were all that's set.
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: collections::HashMap<String, String> =
+            vars.iter().map(|(name, val)| (String::from(*name), String::from(*val))).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn hostname_and_port() {
+        let env = lookup(&[("FIBS_HOSTNAME", "example.org"), ("FIBS_PORT", "5000")]);
+        assert_eq!(servers(&env), vec![(String::from("example.org"), 5000)]);
+    }
+
+    #[test]
+    fn enter_or_alt_enter_sends() {
+        use termion::event::Key;
+        assert!(EnterKey::Send.sends(&Key::Char('\n')));
+        assert!(!EnterKey::Send.sends(&Key::Alt('\r')));
+        assert!(EnterKey::Newline.sends(&Key::Alt('\r')));
+        assert!(EnterKey::Newline.sends(&Key::Alt('\n')));
+        assert!(!EnterKey::Newline.sends(&Key::Char('\n')));
+    }
+
+    #[test]
+    fn color_auto_always_or_never() {
+        for detected in [false, true] {
+            assert!(switch(&lookup(&[("FIBS_COLOR", "always")]), "FIBS_COLOR", detected));
+            assert!(!switch(&lookup(&[("FIBS_COLOR", "never")]), "FIBS_COLOR", detected));
+            assert_eq!(switch(&lookup(&[("FIBS_COLOR", "auto")]), "FIBS_COLOR", detected), detected);
+            assert_eq!(switch(&lookup(&[]), "FIBS_COLOR", detected), detected);
+        }
+    }
+
+    #[test]
+    fn unknown_chrome_colors_warned_of() {
+        let config = Config::from_lookup(&lookup(&[
+            ("FIBS_COLOR", "always"),
+            ("FIBS_ACCENT", "mauve"),
+            ("FIBS_PROMPT_COLOR", "none"),
+        ]));
+        assert_eq!(config.chrome.accent, Some(6));
+        assert_eq!(config.chrome.prompt, None);
+        assert_eq!(config.warnings.len(), 1);
+        assert!(config.warnings[0].starts_with("unknown color \"mauve\" for FIBS_ACCENT"));
+
+        let config = Config::from_lookup(&lookup(&[("FIBS_COLOR", "never"), ("FIBS_ACCENT", "red")]));
+        assert_eq!(config.chrome.accent, None);
+    }
+
+    #[test]
+    fn connect_timeout_never_zero() {
+        let config = Config::from_lookup(&lookup(&[("FIBS_CONNECT_TIMEOUT", "3")]));
+        assert_eq!(config.connect_timeout, time::Duration::from_secs(3));
+        assert!(config.warnings.is_empty());
+
+        let config = Config::from_lookup(&lookup(&[("FIBS_CONNECT_TIMEOUT", "0")]));
+        assert_eq!(config.connect_timeout, network::DEFAULT_CONNECT_TIMEOUT);
+        assert_eq!(config.warnings, vec!["FIBS_CONNECT_TIMEOUT must be at least 1; waiting 10s instead"]);
+    }
+
+    #[test]
+    fn reconnect_login_defaults_to_prompting_without_a_password() {
+        let config = Config::from_lookup(&lookup(&[("FIBS_USER", "alice")]));
+        assert_eq!(config.reconnect_login, ReconnectLogin::Prompt);
+        assert_eq!(config.reconnect_credentials(), None);
+
+        let config = Config::from_lookup(&lookup(&[("FIBS_USER", "alice"), ("FIBS_PASSWORD", "secret")]));
+        assert_eq!(config.reconnect_login, ReconnectLogin::Reuse);
+        assert_eq!(config.reconnect_credentials(), Some(("alice", "secret")));
+
+        let config = Config::from_lookup(&lookup(&[
+            ("FIBS_USER", "alice"),
+            ("FIBS_PASSWORD", "secret"),
+            ("FIBS_RECONNECT_LOGIN", "prompt"),
+        ]));
+        assert_eq!(config.reconnect_credentials(), None);
+    }
+}
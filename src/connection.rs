@@ -0,0 +1,180 @@
+use std::{collections, net, time};
+use std::io::prelude::*;
+
+use crate::{awaiting, limits, Result};
+
+/// The writing side of the FIBS connection, shared by everything that sends.
+///
+/// It outlives any one socket: when a write fails the socket is dropped, and
+/// whatever is sent until the next `replace` is kept to go out after the
+/// next login. Lines sent before login are kept the same way, except the
+/// one answering a login or password prompt, which is dropped if it can't
+/// go out.
+pub struct Connection {
+    tcp: Option<net::TcpStream>,
+    pending: collections::VecDeque<String>,
+    logged_in: bool,
+    prompted: bool,
+    // the next line sent is a password, and isn't to be shown as typed.
+    masked: bool,
+    // lowercased, of the last tell sent.
+    last_tell: Option<String>,
+    // whoever we last told or were told by, for replies.
+    reply_to: Option<String>,
+    // what's been asked of the server and not yet answered.
+    awaiting: awaiting::Awaiting,
+    // what FIBS could refuse for its limits, and has.
+    limits: limits::Limits,
+}
+
+impl Connection {
+    pub fn new(tcp: net::TcpStream) -> Connection {
+        Connection { tcp: Some(tcp), pending: collections::VecDeque::new(), logged_in: false, prompted: false, masked: false, last_tell: None, reply_to: None, awaiting: awaiting::Awaiting::new(), limits: limits::Limits::new() }
+    }
+
+    /// Sends a line to FIBS, or holds on to it if we're not connected or not
+    /// logged in yet.
+    pub fn send(&mut self, ln: &str) {
+        if let Some(rest) = ln.strip_prefix("tell ").or(ln.strip_prefix("tellx ")) {
+            let player = rest.split(' ').next();
+            self.last_tell = player.map(|p| p.to_lowercase());
+            self.reply_to = player.map(String::from);
+        }
+
+        let answering = self.prompted && !self.logged_in;
+        let sent = match &mut self.tcp {
+            Some(tcp) if self.logged_in || self.prompted => {
+                self.prompted = false;
+                self.masked = false;
+                self.awaiting.sent(ln, time::Instant::now());
+                self.limits.sent(ln, time::Instant::now());
+                tcp.write_all(format!("{}\r", ln).as_bytes()).is_ok()
+            }
+            Some(_) => {
+                self.pending.push_back(String::from(ln));
+                return;
+            }
+            None => false,
+        };
+
+        if !sent {
+            self.tcp = None;
+            // a name or password held for after the next login would go
+            // out as a command.
+            if !answering {
+                self.pending.push_back(String::from(ln));
+            }
+        }
+    }
+
+    /// Who we last sent a tell to.
+    pub fn last_tell(&self) -> Option<&str> {
+        self.last_tell.as_deref()
+    }
+
+    /// Someone told us something; replies go to them now.
+    pub fn heard_from(&mut self, player: &str) {
+        self.reply_to = Some(String::from(player));
+    }
+
+    pub fn reply_to(&self) -> Option<&str> {
+        self.reply_to.as_deref()
+    }
+
+    /// A line from the server, which may answer something we asked.
+    pub fn received(&mut self, ln: &str) {
+        self.awaiting.received(ln);
+        self.limits.received(ln, time::Instant::now());
+    }
+
+    /// Why `ln` shouldn't be sent, if FIBS is sure to refuse it; see
+    /// `limits::Limits::blocked`.
+    pub fn blocked(&self, ln: &str) -> Option<String> {
+        self.limits.blocked(ln, time::Instant::now())
+    }
+
+    /// What's gone unanswered for `timeout`, given up on now.
+    pub fn unanswered(&mut self, timeout: time::Duration, now: time::Instant) -> Vec<awaiting::Request> {
+        self.awaiting.overdue(timeout, now)
+    }
+
+    /// Where we're connected to, if we are.
+    pub fn peer(&self) -> Option<net::SocketAddr> {
+        self.tcp.as_ref().and_then(|tcp| tcp.peer_addr().ok())
+    }
+
+    pub fn replace(&mut self, tcp: net::TcpStream) {
+        self.awaiting.clear();
+        self.limits.clear();
+        self.tcp = Some(tcp);
+        self.logged_in = false;
+        self.prompted = false;
+        self.masked = false;
+    }
+
+    /// The server is waiting on a login name or password, so the next line
+    /// sent is credentials and goes straight out.
+    pub fn prompted(&mut self) {
+        self.prompted = true;
+    }
+
+    /// As `prompted`, for the password, which is masked as it's typed.
+    pub fn prompted_for_password(&mut self) {
+        self.prompted = true;
+        self.masked = true;
+    }
+
+    /// Whether what's being typed is the password, not to be shown.
+    pub fn masked(&self) -> bool {
+        self.masked
+    }
+
+    /// Lines held while we were disconnected.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Sends everything held while disconnected or logging in; call once
+    /// logged in.
+    pub fn flush(&mut self) -> Result<()> {
+        self.logged_in = true;
+        while let Some(ln) = self.pending.pop_front() {
+            match &mut self.tcp {
+                Some(tcp) => {
+                    self.awaiting.sent(&ln, time::Instant::now());
+                    self.limits.sent(&ln, time::Instant::now());
+                    tcp.write_all(format!("{}\r", ln).as_bytes())?
+                }
+                None => {
+                    self.pending.push_front(ln);
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credentials_not_held_for_later() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tcp = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let _server = listener.accept().unwrap();
+        let mut conn = Connection::new(tcp.try_clone().unwrap());
+
+        // the connection drops as the password's typed.
+        tcp.shutdown(net::Shutdown::Write).unwrap();
+        conn.prompted_for_password();
+        conn.send("secret");
+        assert!(!conn.masked());
+        conn.send("who");
+
+        assert_eq!(conn.pending(), 1);
+        assert_eq!(conn.pending.front().map(String::as_str), Some("who"));
+    }
+}
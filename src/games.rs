@@ -0,0 +1,166 @@
+use crate::board::Board;
+
+struct Game {
+    board: Board,
+    // boards that came in while another game was shown.
+    unread: usize,
+}
+
+/// The games we have boards for, ours and any we're watching, and which of
+/// them is shown. Only the shown game's boards go on to the display; the
+/// others are kept, and counted, until switched to.
+pub struct Games {
+    games: Vec<Game>,
+    shown: usize,
+    limit: usize,
+    // who we've just started watching; their next game is switched to.
+    watched: Option<String>,
+}
+
+fn same_game(a: &Board, b: &Board) -> bool {
+    a.player == b.player && a.opponent == b.opponent
+}
+
+impl Games {
+    pub fn new(limit: usize) -> Games {
+        Games { games: Vec::new(), shown: 0, limit: limit.max(1), watched: None }
+    }
+
+    /// Switches to `player`'s game with the next board from it, as we've
+    /// asked to watch them.
+    pub fn watch(&mut self, player: &str) {
+        self.watched = Some(String::from(player));
+    }
+
+    /// Files a board under its game, and says whether it's the one shown. A
+    /// game of our own is always switched to when it starts, as is one we've
+    /// just started watching.
+    pub fn route(&mut self, board: &Board) -> bool {
+        let watched = self.watched.as_ref().is_some_and(|p| *p == board.player || *p == board.opponent);
+        if watched {
+            self.watched = None;
+        }
+        match self.games.iter().position(|g| same_game(&g.board, board)) {
+            Some(i) => {
+                self.games[i].board = board.clone();
+                if watched {
+                    self.shown = i;
+                    self.games[i].unread = 0;
+                } else if i != self.shown {
+                    self.games[i].unread += 1;
+                }
+            }
+            None => {
+                // over the limit, the longest unread of the others makes room.
+                if self.games.len() == self.limit {
+                    let shown = self.shown;
+                    let dropped = (0..self.games.len())
+                        .filter(|i| *i != shown)
+                        .max_by_key(|i| self.games[*i].unread)
+                        .unwrap_or(shown);
+                    self.games.remove(dropped);
+                    if dropped < self.shown {
+                        self.shown -= 1;
+                    }
+                }
+                self.games.push(Game { board: board.clone(), unread: 0 });
+                if self.games.len() == 1 || board.player == "You" || watched {
+                    self.shown = self.games.len() - 1;
+                } else {
+                    self.games.last_mut().unwrap().unread = 1;
+                }
+            }
+        }
+
+        same_game(&self.games[self.shown].board, board)
+    }
+
+    /// Shows the next game, returning its latest board.
+    pub fn next(&mut self) -> Option<&Board> {
+        if self.games.is_empty() {
+            return None;
+        }
+        self.shown = (self.shown + 1) % self.games.len();
+        let game = &mut self.games[self.shown];
+        game.unread = 0;
+        Some(&game.board)
+    }
+
+    /// e.g. "game 1 of 3, 5 unread", when there's more than one.
+    pub fn indicator(&self) -> Option<String> {
+        if self.games.len() < 2 {
+            return None;
+        }
+        let unread: usize = self.games.iter().map(|g| g.unread).sum();
+        Some(format!("game {} of {}, {} unread", self.shown + 1, self.games.len(), unread))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::{self, LineKind};
+
+    static START: &str = "board:alice:bob:3:0:0:\
+        0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+        1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0";
+
+    fn board(player: &str, opponent: &str) -> Board {
+        let mut board: Board = START.parse().unwrap();
+        board.player = String::from(player);
+        board.opponent = String::from(opponent);
+        board
+    }
+
+    #[test]
+    fn boards_route_to_their_game() {
+        let mut games = Games::new(4);
+
+        assert!(games.route(&board("alice", "bob")));
+        assert!(!games.route(&board("carol", "dave")));
+        assert!(!games.route(&board("carol", "dave")));
+        assert!(games.route(&board("alice", "bob")));
+        assert_eq!(games.indicator(), Some(String::from("game 1 of 2, 2 unread")));
+
+        assert_eq!(games.next().map(|b| b.player.as_str()), Some("carol"));
+        assert_eq!(games.indicator(), Some(String::from("game 2 of 2, 0 unread")));
+    }
+
+    #[test]
+    fn our_game_is_switched_to() {
+        let mut games = Games::new(4);
+
+        games.route(&board("alice", "bob"));
+        assert!(games.route(&board("You", "carol")));
+        assert!(!games.route(&board("alice", "bob")));
+    }
+
+    #[test]
+    fn watched_game_is_switched_to() {
+        let mut games = Games::new(4);
+        games.route(&board("alice", "bob"));
+
+        // as FIBS starts a watch: who with, then their board.
+        let watch = "You're now watching carol, who is playing dave in a 5 point match.";
+        let LineKind::Watching(player, opponent, length) = classify::classify(watch) else {
+            panic!("not a watch start: {}", watch);
+        };
+        assert_eq!((opponent.as_deref(), length), (Some("dave"), Some(5)));
+        games.watch(&player);
+
+        assert!(games.route(&board("carol", "dave")));
+        assert_eq!(games.indicator(), Some(String::from("game 2 of 2, 0 unread")));
+        // only the once.
+        assert!(!games.route(&board("alice", "bob")));
+    }
+
+    #[test]
+    fn limit() {
+        let mut games = Games::new(2);
+
+        games.route(&board("alice", "bob"));
+        games.route(&board("carol", "dave"));
+        games.route(&board("erin", "frank"));
+        assert_eq!(games.indicator(), Some(String::from("game 1 of 2, 1 unread")));
+    }
+}
@@ -0,0 +1,124 @@
+use crate::board::Board;
+
+static BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// checker counts on a side's points 1..24 as seen from that side, then its bar.
+fn side(board: &Board, ours: bool) -> [u8; 25] {
+    let mut counts = [0; 25];
+
+    counts[..24].copy_from_slice(&board.checkers(ours));
+    counts[24] = if ours { board.player_bar } else { board.opponent_bar };
+
+    counts
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut s = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..=chunk.len() {
+            s.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+
+    s
+}
+
+/// Encodes the position as a GNU Backgammon position ID.
+///
+/// Each side, starting with the one on roll, is written as a run of 1 bits
+/// per checker followed by a 0 bit, for its points 1 through 24 and then its
+/// bar. The resulting 80 bits are packed least significant bit first and
+/// base64 encoded without padding.
+pub fn to_gnubg_id(board: &Board) -> String {
+    let ours = side(board, true);
+    let theirs = side(board, false);
+
+    let on_roll_first = if board.turn == -board.color {
+        [theirs, ours]
+    } else {
+        [ours, theirs]
+    };
+
+    let mut key = [0u8; 10];
+    let mut bit = 0;
+    for counts in on_roll_first.iter() {
+        for n in counts.iter() {
+            for _ in 0..*n {
+                key[bit / 8] |= 1 << (bit % 8);
+                bit += 1;
+            }
+            bit += 1;
+        }
+    }
+
+    base64(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static START: &str = "board:You:someplayer:3:0:0:\
+        0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+        1:6:2:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0";
+
+    fn board(s: &str) -> Board {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn starting_position() {
+        assert_eq!(to_gnubg_id(&board(START)), "4HPwATDgc/ABMA");
+    }
+
+    #[test]
+    fn starting_position_from_the_other_side() {
+        let mut b = board(START);
+        b.color = -1;
+        b.direction = 1;
+        b.home = 25;
+        b.bar = 0;
+        b.turn = -1;
+        b.points.iter_mut().for_each(|p| *p = -*p);
+        b.points.reverse();
+        assert_eq!(to_gnubg_id(&b), "4HPwATDgc/ABMA");
+    }
+
+    #[test]
+    fn empty_board() {
+        let mut b = board(START);
+        b.points = [0; 26];
+        assert_eq!(to_gnubg_id(&b), "AAAAAAAAAAAAAA");
+    }
+
+    #[test]
+    fn one_checker_each_on_the_ace_point() {
+        let mut b = board(START);
+        b.points = [0; 26];
+        b.points[1] = 1;
+        b.points[24] = -1;
+        assert_eq!(to_gnubg_id(&b), "AQAABAAAAAAAAA");
+    }
+
+    #[test]
+    fn checker_on_the_bar() {
+        let mut b = board(START);
+        b.points = [0; 26];
+        b.points[1] = 1;
+        b.opponent_bar = 1;
+        assert_eq!(to_gnubg_id(&b), "AQAAAAAABAAAAA");
+    }
+
+    #[test]
+    fn side_on_roll_is_encoded_first() {
+        let mut b = board(START);
+        b.points = [0; 26];
+        b.points[1] = 1;
+        b.opponent_bar = 1;
+        b.turn = -1;
+        assert_eq!(to_gnubg_id(&b), "AAAABQAAAAAAAA");
+    }
+}
@@ -0,0 +1,30 @@
+use crate::classify::LineKind;
+
+/// What a line handler wants done with a line.
+// nothing in the tree builds these; they're for handlers registered below.
+#[allow(dead_code)]
+pub enum Action {
+    /// Leave the line be.
+    Pass,
+    /// Drop it: not shown, and not acted on by the client.
+    Suppress,
+    /// Show this instead. The client still acts on the original.
+    Replace(String),
+    /// Send a command to FIBS, then carry on with the line as it is.
+    Send(String),
+}
+
+/// Sees every line from FIBS that isn't a board, along with what the client
+/// made of it.
+///
+/// Handlers run on the main loop in the order they're registered, each
+/// seeing the line as any before it replaced it, and before the client's
+/// own handling; the first to suppress a line stops the rest. They hold up
+/// everything else while they run, so must be quick and never block: hand
+/// anything slow to a thread of its own.
+pub type Handler = Box<dyn Fn(&str, &LineKind) -> Action>;
+
+/// The registration point: add handlers here.
+pub fn handlers() -> Vec<Handler> {
+    Vec::new()
+}
@@ -0,0 +1,49 @@
+use std::time;
+
+/// What counts as activity for the auto-away timer.
+#[derive(Clone, Copy, PartialEq)]
+pub enum IdleReset {
+    /// Any key pressed.
+    AnyKey,
+    /// Only lines actually sent, to FIBS or to the client.
+    Sent,
+}
+
+/// When the user last did something, shared between the input thread and
+/// the main loop.
+pub struct Activity {
+    last_key: time::Instant,
+    last_sent: time::Instant,
+}
+
+impl Activity {
+    pub fn new() -> Activity {
+        let now = time::Instant::now();
+        Activity { last_key: now, last_sent: now }
+    }
+
+    pub fn key(&mut self) {
+        self.last_key = time::Instant::now();
+    }
+
+    pub fn sent(&mut self) {
+        let now = time::Instant::now();
+        self.last_key = now;
+        self.last_sent = now;
+    }
+
+    pub fn idle_for(&self, reset: IdleReset, now: time::Instant) -> time::Duration {
+        let since = match reset {
+            IdleReset::AnyKey => self.last_key,
+            IdleReset::Sent => self.last_sent,
+        };
+        now.saturating_duration_since(since)
+    }
+}
+
+/// Activity that keeps us from being marked away however long we're idle.
+#[derive(Clone, Copy)]
+pub struct Exemptions {
+    pub playing: bool,
+    pub watching: bool,
+}
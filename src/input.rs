@@ -0,0 +1,254 @@
+/// The line being typed into the INPUT box, and where in it the cursor is,
+/// in chars. Nothing goes before the start of the line, so backspacing
+/// there, or deleting at its end, does nothing.
+#[derive(Default)]
+pub struct InputLine {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl InputLine {
+    pub fn new() -> InputLine {
+        InputLine::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// The cursor's column, counting from the start of the line.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Types `c` at the cursor.
+    pub fn insert(&mut self, c: char) {
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    /// Takes away the char before the cursor; false if there wasn't one.
+    pub fn backspace(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.cursor -= 1;
+        self.chars.remove(self.cursor);
+        true
+    }
+
+    /// Takes away the char under the cursor; false if there wasn't one.
+    pub fn delete(&mut self) -> bool {
+        if self.cursor == self.chars.len() {
+            return false;
+        }
+        self.chars.remove(self.cursor);
+        true
+    }
+
+    /// Moves the cursor a char back, if it can go.
+    pub fn left(&mut self) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.cursor -= 1;
+        true
+    }
+
+    /// Moves the cursor a char on, if it can go.
+    pub fn right(&mut self) -> bool {
+        if self.cursor == self.chars.len() {
+            return false;
+        }
+        self.cursor += 1;
+        true
+    }
+
+    /// The line as shown, with each newline typed drawn as `newline`.
+    pub fn shown(&self, newline: &str) -> String {
+        self.chars.iter().map(|&c| if c == '\n' { String::from(newline) } else { c.to_string() }).collect()
+    }
+
+    /// The line as shown when it's a password: a `*` for each char.
+    pub fn masked(&self) -> String {
+        "*".repeat(self.chars.len())
+    }
+
+    /// The line, leaving nothing typed.
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        self.chars.drain(..).collect()
+    }
+
+    /// The line as typed, newlines and all.
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    /// Replaces the line with `s`, the cursor at its end.
+    pub fn set(&mut self, s: &str) {
+        self.chars = s.chars().collect();
+        self.cursor = self.chars.len();
+    }
+}
+
+/// The lines sent, oldest first, for Up and Down to go back through. While
+/// going through them, the line that was being typed is kept as the draft,
+/// to come back to past the newest.
+pub struct History {
+    lines: Vec<String>,
+    max: usize,
+    // the line shown, if it's one of ours.
+    at: Option<usize>,
+    draft: String,
+}
+
+impl History {
+    pub fn new(max: usize) -> History {
+        History { lines: Vec::new(), max, at: None, draft: String::new() }
+    }
+
+    /// Remembers a line sent, forgetting the oldest if there are too many,
+    /// and starts again from the newest.
+    pub fn push(&mut self, ln: String) {
+        self.at = None;
+        if ln.is_empty() || self.max == 0 || self.lines.last() == Some(&ln) {
+            return;
+        }
+        if self.lines.len() == self.max {
+            self.lines.remove(0);
+        }
+        self.lines.push(ln);
+    }
+
+    /// The line before the one shown, `typed` being what's in the INPUT box
+    /// now; None if there's nothing older, the oldest staying put.
+    pub fn up(&mut self, typed: &str) -> Option<&str> {
+        let at = match self.at {
+            None if self.lines.is_empty() => return None,
+            None => {
+                self.draft = String::from(typed);
+                self.lines.len() - 1
+            }
+            Some(0) => return None,
+            Some(at) => at - 1,
+        };
+        self.at = Some(at);
+        Some(&self.lines[at])
+    }
+
+    /// The line after the one shown, or the draft past the newest; None if
+    /// we're already at the draft.
+    pub fn down(&mut self) -> Option<&str> {
+        let at = self.at?;
+        if at + 1 < self.lines.len() {
+            self.at = Some(at + 1);
+            return Some(&self.lines[at + 1]);
+        }
+        self.at = None;
+        Some(&self.draft)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(s: &str) -> InputLine {
+        let mut ln = InputLine::new();
+        s.chars().for_each(|c| ln.insert(c));
+        ln
+    }
+
+    #[test]
+    fn backspace_stops_at_the_prompt() {
+        let mut ln = typed("whi");
+        assert!(ln.backspace());
+        ln.insert('o');
+        assert_eq!(ln.shown("⏎"), "who");
+
+        assert!(ln.backspace() && ln.backspace() && ln.backspace());
+        assert!(!ln.backspace());
+        assert!(ln.is_empty());
+        assert_eq!(ln.cursor(), 0);
+    }
+
+    #[test]
+    fn masked_password() {
+        let mut ln = typed("sekrit");
+        assert_eq!(ln.masked(), "******");
+        ln.backspace();
+        ln.backspace();
+        assert_eq!(ln.masked(), "****");
+        assert_eq!(ln.take(), "sekr");
+        assert_eq!(ln.masked(), "");
+    }
+
+    #[test]
+    fn editing_mid_line() {
+        let mut ln = typed("tell bob hi");
+        for _ in 0..3 {
+            ln.left();
+        }
+        // on the space before "hi".
+        assert!(ln.delete());
+        ln.insert(',');
+        ln.insert(' ');
+        assert_eq!(ln.shown("⏎"), "tell bob, hi");
+        assert_eq!(ln.cursor(), 10);
+
+        assert!(ln.right() && ln.right());
+        assert!(!ln.right());
+        assert!(!ln.delete());
+    }
+
+    #[test]
+    fn empty_history() {
+        let mut history = History::new(500);
+        assert_eq!(history.up("draft"), None);
+        assert_eq!(history.down(), None);
+
+        history.push(String::new());
+        assert_eq!(history.up(""), None);
+    }
+
+    #[test]
+    fn up_and_down_stop_at_either_end() {
+        let mut history = History::new(500);
+        history.push(String::from("invite bob"));
+        history.push(String::from("roll"));
+
+        assert_eq!(history.up("mov"), Some("roll"));
+        assert_eq!(history.up("roll"), Some("invite bob"));
+        assert_eq!(history.up("invite bob"), None);
+        assert_eq!(history.down(), Some("roll"));
+        // past the newest, back to what was being typed.
+        assert_eq!(history.down(), Some("mov"));
+        assert_eq!(history.down(), None);
+
+        // sending starts again from the newest.
+        history.up("");
+        history.push(String::from("move 8-5 6-5"));
+        assert_eq!(history.up(""), Some("move 8-5 6-5"));
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut history = History::new(2);
+        for ln in ["a", "b", "b", "c"] {
+            history.push(String::from(ln));
+        }
+        assert_eq!(history.up(""), Some("c"));
+        assert_eq!(history.up(""), Some("b"));
+        assert_eq!(history.up(""), None);
+    }
+
+    #[test]
+    fn newlines_shown_and_taken() {
+        let mut ln = typed("who\nwhois bob");
+        assert_eq!(ln.shown("\\"), "who\\whois bob");
+        assert_eq!(ln.take(), "who\nwhois bob");
+        assert!(ln.is_empty());
+        assert_eq!(ln.cursor(), 0);
+    }
+}
@@ -0,0 +1,43 @@
+use std::{io, vec};
+
+use termion::event::Key;
+use termion::input::TermRead;
+
+/// Where the input thread's keys come from: the terminal, or a script of
+/// them for tests and anything else without one.
+pub trait KeySource: Send + 'static {
+    /// The next key, or None once there are no more.
+    fn next_key(&mut self) -> Option<io::Result<Key>>;
+}
+
+/// Keys as typed on the terminal.
+pub struct Stdin(termion::input::Keys<io::Stdin>);
+
+impl Default for Stdin {
+    fn default() -> Stdin {
+        Stdin(io::stdin().keys())
+    }
+}
+
+impl KeySource for Stdin {
+    fn next_key(&mut self) -> Option<io::Result<Key>> {
+        self.0.next()
+    }
+}
+
+/// Keys given up front, then nothing.
+#[allow(dead_code)]
+pub struct Scripted(vec::IntoIter<Key>);
+
+#[allow(dead_code)]
+impl Scripted {
+    pub fn new(keys: Vec<Key>) -> Scripted {
+        Scripted(keys.into_iter())
+    }
+}
+
+impl KeySource for Scripted {
+    fn next_key(&mut self) -> Option<io::Result<Key>> {
+        self.0.next().map(Ok)
+    }
+}
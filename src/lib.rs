@@ -0,0 +1,105 @@
+//! A FIBS client without the terminal: connecting and logging in, the
+//! CLIP and board parsers, and what can be made of a game from them. The
+//! `fibsterm` binary is one front-end on top of it; see `client` for the
+//! way in for others.
+
+pub mod awaiting;
+pub mod board;
+pub mod classify;
+pub mod clip;
+pub mod client;
+pub mod connection;
+pub mod gnubg;
+pub mod limits;
+pub mod login;
+pub mod matcher;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+pub mod moves;
+pub mod network;
+pub mod pips;
+pub mod render;
+pub mod telnet;
+pub mod text;
+pub mod trace;
+pub mod who;
+
+use std::{ffi, fmt, io, result, sync};
+
+#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    IOError(String),
+    MalformedInputError(String),
+    GAIError(String),
+    SyncError(String),
+    ConfigError(String),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::IOError(s) => write!(f, "I/O error: {}", s),
+            Error::MalformedInputError(s) => write!(f, "malformed input: {}", s),
+            Error::GAIError(s) => write!(f, "name resolution failed: {}", s),
+            Error::SyncError(s) => write!(f, "{}", s),
+            Error::ConfigError(s) => write!(f, "configuration: {}", s),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::IOError(e.to_string())
+    }
+}
+
+impl From<ffi::NulError> for Error {
+    fn from(e: ffi::NulError) -> Error {
+        let nul_pos = e.nul_position();
+        let mut bytes = e.into_vec();
+        bytes.truncate(nul_pos);
+
+        Error::MalformedInputError(
+            format!(
+                "interior nul byte found at position {}, immediately following {}",
+                nul_pos,
+                String::from_utf8_lossy(bytes.as_slice())
+            )
+        )
+    }
+}
+
+impl From<libc::c_int> for Error {
+    fn from(e: libc::c_int) -> Error {
+        unsafe {
+            Error::GAIError(String::from(ffi::CStr::from_ptr(libc::gai_strerror(e)).to_string_lossy()))
+        }
+    }
+}
+
+impl From<sync::mpsc::TryRecvError> for Error {
+    fn from(_: sync::mpsc::TryRecvError) -> Error {
+        Error::SyncError(String::from("fibs thread disconnected"))
+    }
+}
+
+impl From<sync::mpsc::RecvError> for Error {
+    fn from(_: sync::mpsc::RecvError) -> Error {
+        Error::SyncError(String::from("fibs thread disconnected"))
+    }
+}
+
+impl<T> From<sync::mpsc::SendError<T>> for Error {
+    fn from(_: sync::mpsc::SendError<T>) -> Error {
+        Error::SyncError(String::from("tui thread disconnected"))
+    }
+}
+
+impl<T> From<sync::PoisonError<T>> for Error {
+    fn from(_: sync::PoisonError<T>) -> Error {
+        Error::SyncError(String::from("tui thread disconnected"))
+    }
+}
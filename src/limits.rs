@@ -0,0 +1,203 @@
+//! FIBS turns some things down for limits of its own: an invitation to
+//! someone already invited, too many tells too quickly. Those refusals are
+//! picked out so they can be shown for what they are, and what we know the
+//! server would refuse isn't sent at all.
+
+use std::{collections, time};
+
+/// How long an invitation is taken to be waiting on an answer. FIBS keeps
+/// them until they're answered, but one may be answered without a line we
+/// recognize, and shouldn't block inviting that player again forever.
+pub const INVITE_PENDING: time::Duration = time::Duration::from_secs(120);
+
+/// How long the kind of thing FIBS last refused for a limit is held back.
+pub const HOLD: time::Duration = time::Duration::from_secs(10);
+
+// lowercased, as they appear in FIBS' refusals.
+const PHRASES: &[&str] = &["too many", "limit", "already invited", "slow down", "wait a while"];
+
+/// Whether `ln` is the server refusing something for hitting a limit.
+pub fn rejection(ln: &str) -> bool {
+    let Some(rest) = ln.strip_prefix("** ") else {
+        return false;
+    };
+    let lower = rest.to_lowercase();
+    PHRASES.iter().any(|p| lower.contains(p))
+}
+
+// how FIBS' answers to an invitation go on after the player's name: them
+// joining us, or turning it down, or not being there to answer.
+const INVITE_ANSWERS: &[&str] = &[
+    " has joined you",
+    " is refusing games",
+    " is already playing with someone else",
+    " is not logged in",
+    " declines your invitation",
+];
+
+// who `ln` is FIBS answering an invitation to for, if it is.
+fn invite_answer(ln: &str) -> Option<&str> {
+    let rest = ln.strip_prefix("** ").unwrap_or(ln);
+    let rest = rest.strip_prefix("Player ").unwrap_or(rest);
+    if let Some(player) = rest.strip_prefix("There is no one called ") {
+        return Some(player.trim_end_matches('.'));
+    }
+    // the name, and only the name, before what it answers.
+    INVITE_ANSWERS.iter()
+        .find_map(|answer| rest.split_once(answer).map(|(player, _)| player))
+        .filter(|player| !player.is_empty() && !player.contains(' '))
+}
+
+/// The actions FIBS limits.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Invite,
+    Tell,
+}
+
+impl Action {
+    // the action a line sent to FIBS is, and who it's to.
+    fn of(ln: &str) -> Option<(Action, &str)> {
+        let mut words = ln.split_whitespace();
+        let action = match words.next()?.to_lowercase().as_str() {
+            "invite" => Action::Invite,
+            "tell" | "tellx" => Action::Tell,
+            _ => return None,
+        };
+        Some((action, words.next()?))
+    }
+
+    fn plural(&self) -> &'static str {
+        match self {
+            Action::Invite => "invitations",
+            Action::Tell => "tells",
+        }
+    }
+}
+
+/// What's been sent that FIBS could refuse for a limit, and what it has.
+#[derive(Default)]
+pub struct Limits {
+    // lowercased player, and when they were invited.
+    invites: collections::HashMap<String, time::Instant>,
+    // the last limited action sent.
+    last: Option<Action>,
+    // what was last refused for a limit, and when.
+    held: Option<(Action, time::Instant)>,
+}
+
+impl Limits {
+    pub fn new() -> Limits {
+        Limits::default()
+    }
+
+    /// Why `ln` shouldn't be sent at `now`, if FIBS is sure to refuse it.
+    pub fn blocked(&self, ln: &str, now: time::Instant) -> Option<String> {
+        let (action, player) = Action::of(ln)?;
+        if let Some((held, at)) = self.held {
+            let since = now.duration_since(at);
+            if held == action && since < HOLD {
+                return Some(format!("FIBS refused {} for a limit; wait {}s", action.plural(), (HOLD - since).as_secs_f32().ceil()));
+            }
+        }
+        match self.invites.get(&player.to_lowercase()) {
+            Some(at) if action == Action::Invite && now.duration_since(*at) < INVITE_PENDING => {
+                Some(format!("{} is already invited, {}s ago", player, now.duration_since(*at).as_secs()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Notes `ln` as sent at `now`.
+    pub fn sent(&mut self, ln: &str, now: time::Instant) {
+        if let Some((action, player)) = Action::of(ln) {
+            self.last = Some(action);
+            if action == Action::Invite {
+                self.invites.insert(player.to_lowercase(), now);
+            }
+        }
+    }
+
+    /// Takes a line received at `now`: a refusal holds back the last action
+    /// sent, and an answer about someone invited lets their invitation go.
+    pub fn received(&mut self, ln: &str, now: time::Instant) {
+        if rejection(ln) {
+            if let Some(action) = self.last {
+                self.held = Some((action, now));
+            }
+        }
+        if let Some(player) = invite_answer(ln) {
+            self.invites.remove(&player.to_lowercase());
+        }
+    }
+
+    /// Forgets everything, for a connection that's gone.
+    pub fn clear(&mut self) {
+        self.invites.clear();
+        self.last = None;
+        self.held = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejections() {
+        assert!(rejection("** You can't send that many tells. Please slow down."));
+        assert!(rejection("** Too many invitations pending."));
+        assert!(rejection("** You already invited bob."));
+        assert!(!rejection("** bob is already playing with someone else."));
+        assert!(!rejection("alice says: too many gammons today"));
+    }
+
+    #[test]
+    fn invites_pending_until_answered() {
+        let now = time::Instant::now();
+        let mut limits = Limits::new();
+
+        limits.sent("invite Bob 5", now);
+        limits.received("** You invited bob to a 5 point match.", now);
+        assert_eq!(limits.blocked("invite bob 3", now), Some(String::from("bob is already invited, 0s ago")));
+        assert_eq!(limits.blocked("invite carol 3", now), None);
+        assert_eq!(limits.blocked("tell bob hi", now), None);
+
+        // timed out.
+        assert_eq!(limits.blocked("invite bob", now + INVITE_PENDING), None);
+
+        limits.received("** bob is refusing games.", now);
+        assert_eq!(limits.blocked("invite bob", now), None);
+    }
+
+    #[test]
+    fn only_answers_let_invites_go() {
+        let now = time::Instant::now();
+        let mut limits = Limits::new();
+
+        limits.sent("invite bob 5", now);
+        limits.received("alice shouts: anyone seen bob is refusing games?", now);
+        limits.received("bobby logs in.", now);
+        limits.received("** Player bobby has joined you for a 5 point match.", now);
+        assert!(limits.blocked("invite bob", now).is_some());
+
+        limits.received("** Player bob has joined you for a 5 point match.", now);
+        assert_eq!(limits.blocked("invite bob", now), None);
+
+        limits.sent("invite carol", now);
+        limits.received("** There is no one called carol.", now);
+        assert_eq!(limits.blocked("invite carol", now), None);
+    }
+
+    #[test]
+    fn refused_actions_held_back() {
+        let now = time::Instant::now();
+        let mut limits = Limits::new();
+
+        limits.sent("tell bob hi", now);
+        limits.received("** Too many tells; slow down.", now);
+        assert_eq!(limits.blocked("tell carol hi", now), Some(String::from("FIBS refused tells for a limit; wait 10s")));
+        assert_eq!(limits.blocked("invite carol", now), None);
+        assert_eq!(limits.blocked("tell carol hi", now + HOLD), None);
+    }
+}
@@ -0,0 +1,424 @@
+use std::time;
+
+use crate::{clip, matcher, text, trace};
+
+// how long the server has to go quiet after a prompt before we believe it.
+const PROMPT_SETTLE: time::Duration = time::Duration::from_millis(250);
+
+// shown in place of a MOTD when the server goes straight to the prompt.
+const NO_MOTD: &str = "Connected — please log in.";
+
+// how much of what the server said last is shown when it hangs up on us.
+const REASON_LINES: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Prompt {
+    Login,
+    Password,
+}
+
+/// Watches the bytes of the greeting for FIBS' login and password prompts.
+///
+/// Matching the prompt text alone isn't enough, since MOTDs like to explain
+/// what to type at "login: ". A prompt only counts once the server has sent
+/// it at the start of a line, or straight after the one before, and then
+/// stopped sending, waiting on us.
+pub struct PromptDetector {
+    matcher: matcher::PromptMatcher<Prompt>,
+    // bytes since the start of the line or the last prompt.
+    since_start: usize,
+    // the prompt the bytes fed end with, if any.
+    at: Option<Prompt>,
+    last_byte_at: Option<time::Instant>,
+}
+
+impl Default for PromptDetector {
+    fn default() -> PromptDetector {
+        PromptDetector::new()
+    }
+}
+
+impl PromptDetector {
+    pub fn new() -> PromptDetector {
+        PromptDetector {
+            matcher: matcher::PromptMatcher::new(vec![(b"login: ".as_slice(), Prompt::Login), (b"password: ".as_slice(), Prompt::Password)]),
+            since_start: 0,
+            at: None,
+            last_byte_at: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.matcher.reset();
+        self.since_start = 0;
+        self.at = None;
+        self.last_byte_at = None;
+    }
+
+    pub fn feed(&mut self, b: u8, now: time::Instant) {
+        self.since_start += 1;
+        self.last_byte_at = Some(now);
+        let was = self.at.take();
+
+        match self.matcher.feed(b) {
+            Some((prompt, len)) if len == self.since_start => {
+                trace::trace(trace::Category::Login, || format!("at {:?}", prompt));
+                self.at = Some(prompt);
+                self.since_start = 0;
+            }
+            _ if b == b'\n' => self.since_start = 0,
+            _ => {}
+        }
+        if let (Some(p), None) = (was, self.at) {
+            trace::trace(trace::Category::Login, || format!("past {:?} on {:?}", p, b as char));
+        }
+    }
+
+    /// The prompt the server is sitting at, if it has gone quiet after one.
+    pub fn settled(&self, now: time::Instant) -> Option<Prompt> {
+        let quiet = self.last_byte_at
+            .map(|t| now.duration_since(t) >= PROMPT_SETTLE)
+            .unwrap_or(false);
+
+        let prompt = self.at.filter(|_| quiet);
+        if let Some(p) = &prompt {
+            trace::trace(trace::Category::Login, || format!("settled at {:?}", p));
+        }
+        prompt
+    }
+}
+
+/// What the server made of the password.
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    /// Back to the login prompt.
+    Incorrect,
+    /// In, as whoever the server says if it did.
+    Welcome(Option<String>),
+}
+
+/// Whether a line sent after the password says how the login went. CLIP's
+/// welcome is `1 <name> <last login> <last host>`, followed by our own info
+/// (2) and the MOTD (3); without CLIP there's "** User <name> authenticated."
+/// and the welcome banner.
+pub fn outcome(ln: &str) -> Option<Outcome> {
+    let lower = ln.to_lowercase();
+    if lower.contains("login incorrect") {
+        return Some(Outcome::Incorrect);
+    }
+    let name = |s: &str| s.split(' ').next().filter(|n| !n.is_empty()).map(String::from);
+    match clip::message_type(ln) {
+        Some(1) | Some(2) => return Some(Outcome::Welcome(ln.split_once(' ').and_then(|(_, rest)| name(rest)))),
+        Some(3) => return Some(Outcome::Welcome(None)),
+        _ => {}
+    }
+    if let Some(rest) = ln.strip_prefix("** User ") {
+        if rest.ends_with(" authenticated.") {
+            return Some(Outcome::Welcome(name(rest)));
+        }
+    }
+    lower.starts_with("welcome").then_some(Outcome::Welcome(None))
+}
+
+/// How far a login has got.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stage {
+    /// The server's greeting, before the login prompt.
+    Greeting,
+    /// At the login prompt, where CLIP's `login` may be typed in place of a
+    /// name.
+    Name,
+    Password,
+    In,
+}
+
+/// What a login has come to with what the server's just sent.
+#[derive(Debug, PartialEq)]
+pub enum Step {
+    /// At the login prompt, after this greeting.
+    Greeted(String),
+    /// At the password prompt.
+    AskedPassword,
+    /// Back at the login prompt after the password, turned down.
+    AskedAgain,
+    /// A line saying how it went, as `outcome` has it.
+    Outcome(Outcome, String),
+    /// Any other line after the password.
+    Line(String),
+}
+
+/// Follows a login through the bytes the server sends, up to the line that
+/// says we're in: the prompts, once the server's waiting at them, and the
+/// lines that say how it went, whether after the password or straight after
+/// CLIP's `login` at the login prompt. What's sent in answer is up to the
+/// caller.
+pub struct Login {
+    stage: Stage,
+    prompts: PromptDetector,
+    // since the last prompt: the greeting, or why the server hung up.
+    received: Vec<u8>,
+    // where the line coming in starts in `received`.
+    line_start: usize,
+}
+
+impl Default for Login {
+    fn default() -> Login {
+        Login::new()
+    }
+}
+
+impl Login {
+    pub fn new() -> Login {
+        Login { stage: Stage::Greeting, prompts: PromptDetector::new(), received: Vec::new(), line_start: 0 }
+    }
+
+    /// Starts over, for a new connection.
+    pub fn reset(&mut self) {
+        *self = Login::new();
+    }
+
+    pub fn stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn logged_in(&self) -> bool {
+        self.stage == Stage::In
+    }
+
+    /// What the server's sent since the last prompt.
+    pub fn received(&self) -> String {
+        String::from_utf8_lossy(&self.received).into_owned()
+    }
+
+    /// Takes a byte from the server, and the line it ends if that says
+    /// anything. Once we're in, the bytes are the caller's.
+    pub fn feed(&mut self, b: u8, now: time::Instant) -> Option<Step> {
+        if self.stage == Stage::In {
+            return None;
+        }
+        self.prompts.feed(b, now);
+        self.received.push(b);
+        if b != b'\n' || self.stage == Stage::Greeting {
+            return None;
+        }
+
+        let ln = String::from(String::from_utf8_lossy(&self.received[self.line_start..]).trim_end());
+        self.line_start = self.received.len();
+        match outcome(&ln) {
+            Some(Outcome::Welcome(name)) => {
+                self.stage = Stage::In;
+                Some(Step::Outcome(Outcome::Welcome(name), ln))
+            }
+            Some(outcome) => Some(Step::Outcome(outcome, ln)),
+            // at the login prompt, only for why the server hung up.
+            None if ln.is_empty() || self.stage == Stage::Name => None,
+            None => Some(Step::Line(ln)),
+        }
+    }
+
+    /// The prompt the server's gone quiet at, if it's one that moves the
+    /// login on.
+    pub fn settled(&mut self, now: time::Instant) -> Option<Step> {
+        let step = match (self.stage, self.prompts.settled(now)?) {
+            (Stage::Greeting, Prompt::Login) => {
+                self.stage = Stage::Name;
+                Step::Greeted(self.received())
+            }
+            (Stage::Name, Prompt::Password) => {
+                self.stage = Stage::Password;
+                Step::AskedPassword
+            }
+            (Stage::Password, Prompt::Login) => {
+                self.stage = Stage::Name;
+                Step::AskedAgain
+            }
+            _ => return None,
+        };
+        self.prompts.reset();
+        self.received.clear();
+        self.line_start = 0;
+        Some(step)
+    }
+}
+
+/// The greeting up to the login prompt, as shown: without its leading blank
+/// lines, and with a placeholder if there's nothing before the prompt.
+pub fn motd(greeting: &str) -> String {
+    let greeting = text::skip_blank_lines(greeting);
+    let before_prompt = greeting.rsplit_once('\n').map(|(motd, _)| motd).unwrap_or("");
+    if before_prompt.trim().is_empty() {
+        let prompt = greeting.rsplit('\n').next().unwrap_or("");
+        return format!("{}\r\n\r\n{}", NO_MOTD, prompt);
+    }
+    String::from(greeting)
+}
+
+/// What to say when the server hangs up before we've logged in: that, and
+/// the last few lines it sent, which are usually why.
+pub fn closed_during_login(received: &str) -> Vec<String> {
+    let said: Vec<&str> = received.lines().map(str::trim_end).filter(|ln| !ln.is_empty()).collect();
+    let mut lines = vec![String::from("server closed connection during login")];
+    lines.extend(said[said.len().saturating_sub(REASON_LINES)..].iter().map(|ln| format!("  {}", ln)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net, sync};
+    use std::io::prelude::*;
+
+    use crate::network;
+
+    fn feed(detector: &mut PromptDetector, s: &str, now: time::Instant) {
+        for b in s.bytes() {
+            detector.feed(b, now);
+        }
+    }
+
+    #[test]
+    fn login_prompt_after_motd() {
+        let now = time::Instant::now();
+        let mut detector = PromptDetector::new();
+
+        feed(&mut detector, "\r\nWelcome to FIBS\r\nlogin: ", now);
+
+        assert_eq!(detector.settled(now), None);
+        assert_eq!(detector.settled(now + PROMPT_SETTLE), Some(Prompt::Login));
+    }
+
+    #[test]
+    fn login_mentioned_in_motd() {
+        let now = time::Instant::now();
+        let mut detector = PromptDetector::new();
+
+        feed(&mut detector, "\r\nWelcome to FIBS\r\nlogin: guest to register\r\n", now);
+        assert_eq!(detector.settled(now + PROMPT_SETTLE), None);
+
+        feed(&mut detector, "login: ", now);
+        assert_eq!(detector.settled(now + PROMPT_SETTLE), Some(Prompt::Login));
+    }
+
+    #[test]
+    fn empty_motd() {
+        let now = time::Instant::now();
+        let mut detector = PromptDetector::new();
+        let greeting = "\r\n\r\n  \r\nlogin: ";
+
+        feed(&mut detector, greeting, now);
+
+        assert_eq!(detector.settled(now + PROMPT_SETTLE), Some(Prompt::Login));
+        assert_eq!(motd(greeting), format!("{}\r\n\r\nlogin: ", NO_MOTD));
+        assert_eq!(motd("\r\nWelcome to FIBS\r\nlogin: "), "Welcome to FIBS\r\nlogin: ");
+    }
+
+    #[test]
+    fn login_prompt_interrupted_by_more_motd() {
+        let now = time::Instant::now();
+        let mut detector = PromptDetector::new();
+
+        feed(&mut detector, "\r\nlogin: ", now);
+        feed(&mut detector, "is where you type your name\r\n", now);
+
+        assert_eq!(detector.settled(now + PROMPT_SETTLE), None);
+    }
+
+    #[test]
+    fn password_prompt_after_the_login_prompt() {
+        let now = time::Instant::now();
+        let mut detector = PromptDetector::new();
+
+        feed(&mut detector, "\r\nlogin: password: ", now);
+        assert_eq!(detector.settled(now + PROMPT_SETTLE), Some(Prompt::Password));
+
+        detector.reset();
+        feed(&mut detector, "\r\npassword: ", now);
+        assert_eq!(detector.settled(now + PROMPT_SETTLE), Some(Prompt::Password));
+    }
+
+    #[test]
+    fn login_outcomes() {
+        assert_eq!(outcome("** Login incorrect."), Some(Outcome::Incorrect));
+        assert_eq!(outcome("login incorrect"), Some(Outcome::Incorrect));
+
+        assert_eq!(outcome("1 alice 1041253132 192.168.1.30"), Some(Outcome::Welcome(Some(String::from("alice")))));
+        assert_eq!(outcome("3"), Some(Outcome::Welcome(None)));
+        assert_eq!(outcome("2"), Some(Outcome::Welcome(None)));
+        assert_eq!(outcome("** User alice authenticated."), Some(Outcome::Welcome(Some(String::from("alice")))));
+        assert_eq!(outcome("Welcome to FIBS. You just logged in as guest."), Some(Outcome::Welcome(None)));
+
+        assert_eq!(outcome(""), None);
+        assert_eq!(outcome("login: "), None);
+    }
+
+    // what `login` makes of `s` sent at `now`, and of the quiet after.
+    fn steps(login: &mut Login, s: &str, now: time::Instant) -> Vec<Step> {
+        let mut steps: Vec<Step> = s.bytes().filter_map(|b| login.feed(b, now)).collect();
+        steps.extend(login.settled(now + PROMPT_SETTLE));
+        steps
+    }
+
+    #[test]
+    fn logged_in_after_the_password() {
+        let now = time::Instant::now();
+        let mut login = Login::new();
+
+        assert_eq!(steps(&mut login, "\r\nWelcome to FIBS\r\nlogin: ", now), vec![Step::Greeted(String::from("\r\nWelcome to FIBS\r\nlogin: "))]);
+        assert_eq!(steps(&mut login, "password: ", now), vec![Step::AskedPassword]);
+        assert_eq!(steps(&mut login, "\r\n** Login incorrect.\r\nlogin: ", now), vec![
+            Step::Outcome(Outcome::Incorrect, String::from("** Login incorrect.")),
+            Step::AskedAgain,
+        ]);
+        assert_eq!(login.stage(), Stage::Name);
+
+        steps(&mut login, "password: ", now);
+        assert_eq!(steps(&mut login, "\r\nLast login: never\r\n** User alice authenticated.\r\n", now), vec![
+            Step::Line(String::from("Last login: never")),
+            Step::Outcome(Outcome::Welcome(Some(String::from("alice"))), String::from("** User alice authenticated.")),
+        ]);
+        assert!(login.logged_in());
+        assert_eq!(login.feed(b'\n', now), None);
+    }
+
+    #[test]
+    fn clip_login_at_the_login_prompt() {
+        let now = time::Instant::now();
+        let mut login = Login::new();
+
+        steps(&mut login, "\r\nWelcome to FIBS\r\nlogin: ", now);
+        assert_eq!(steps(&mut login, "\r\n1 alice 1041253132 192.168.1.30\r\n", now), vec![
+            Step::Outcome(Outcome::Welcome(Some(String::from("alice"))), String::from("1 alice 1041253132 192.168.1.30")),
+        ]);
+        assert!(login.logged_in());
+
+        login.reset();
+        assert_eq!(login.stage(), Stage::Greeting);
+    }
+
+    #[test]
+    fn closed_after_the_banner() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut tcp, _) = listener.accept().unwrap();
+            tcp.write_all(b"\r\nWelcome to FIBS\r\n\r\nToo many connections from your address.\r\n").unwrap();
+        });
+
+        let tcp = network::connect("127.0.0.1", port, network::DEFAULT_CONNECT_TIMEOUT).unwrap();
+        let (tx, rx) = sync::mpsc::sync_channel(4096);
+        network::spawn_fibs_thread(tcp, tx).unwrap();
+        server.join().unwrap();
+
+        // everything up to the hang-up, as the main loop would have it.
+        let received: Vec<u8> = rx.iter().collect();
+        let mut detector = PromptDetector::new();
+        for b in &received {
+            detector.feed(*b, time::Instant::now());
+        }
+        assert_eq!(detector.settled(time::Instant::now() + PROMPT_SETTLE), None);
+        assert_eq!(
+            closed_during_login(&String::from_utf8_lossy(&received)),
+            vec!["server closed connection during login", "  Welcome to FIBS", "  Too many connections from your address."]
+        );
+    }
+}
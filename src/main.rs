@@ -1,177 +1,403 @@
-use libc;
-
-use core::ptr;
+mod clipboard;
+mod colors;
+mod command;
+mod config;
+mod games;
+mod hooks;
+mod idle;
+mod input;
+mod keys;
+mod monitor;
+mod notify;
+mod plain;
+mod replay;
+mod screen;
+mod scrollback;
+mod split;
+mod terminal;
+mod updates;
+mod webhook;
+mod winch;
+
+use fibsterm::{board, classify, clip, connection, gnubg, limits, login, moves, network, pips, render, text, trace, who, Error, Result};
 
 use std::{
     collections,
     env,
-    ffi,
+    fmt,
+    fs,
     io,
     sync,
     net,
-    result,
     thread,
+    time,
     vec,
 };
 use std::io::prelude::*;
 
 extern crate termion;
 
-use termion::input::TermRead;
-use termion::raw::IntoRawMode;
 
-static DEFAULT_FIBS_SERVER: &str = "fibs.com";
-const DEFAULT_FIBS_PORT: u16 = 4321;
+// shutdown warnings at or under this many seconds get the loud banner.
+const SHUTDOWN_IMMINENT_SECS: u32 = 120;
 
-#[derive(Debug)]
-enum Error {
-    IOError(String),
-    MalformedInputError(String),
-    GAIError(String),
-    SyncError(String),
-}
+// how long the main loop waits on the server before seeing to what's on a
+// timer; see tick.
+const TICK: time::Duration = time::Duration::from_millis(50);
 
-struct State {
-    fibs_state: FibsState,
-}
+// FIBS' match length for unlimited (money) matches.
+const UNLIMITED_MATCH: u32 = 9999;
 
-enum Update {
-    MOTD(String),
-    AppendChars(String),
-    AppendLine(String),
-    Input(String),
-}
+// the boardstyle whose `board:` lines we parse.
+const BOARDSTYLE: u8 = 3;
 
-enum FibsState {
-    MOTD = 0,
-    WaitLogin,
-    WaitPassword,
-}
+// boards kept for stepping back through a game.
+const HISTORY_LIMIT: usize = 500;
 
-type Result<T> = result::Result<T, Error>;
+// how narrow notifications beside the FIBS box can get before they go at the
+// end of the status line instead, and how much room is left for them when
+// the box grows to fill a wide terminal.
+const NOTIFY_MIN_WIDTH: u16 = 12;
+const NOTIFY_ROOM: u16 = 32;
 
-impl From<io::Error> for Error {
-    fn from(e: io::Error) -> Error {
-        Error::IOError(e.to_string())
-    }
-}
+// how far Left and Right scroll the MOTD across, in columns.
+const MOTD_SCROLL: isize = 8;
 
-impl From<ffi::NulError> for Error {
-    fn from(e: ffi::NulError) -> Error {
-        let nul_pos = e.nul_position();
-        let mut bytes = e.into_vec();
-        bytes.truncate(nul_pos);
+// how CLIP errors start in the FIBS box.
+const CLIP_ERROR_LABEL: &str = "CLIP error: ";
 
-        Error::MalformedInputError(
-            format!(
-                "interior nul byte found at position {}, immediately following {}",
-                nul_pos,
-                String::from_utf8_lossy(bytes.as_slice())
-            )
-        )
-    }
-}
+// the wait before the second attempt at reconnecting by ourselves, doubling
+// with each one after up to the cap.
+const RECONNECT_BACKOFF: time::Duration = time::Duration::from_secs(1);
+const RECONNECT_BACKOFF_CAP: time::Duration = time::Duration::from_secs(30);
 
-impl From<libc::c_int> for Error {
-    fn from(e: libc::c_int) -> Error {
-        unsafe {
-            Error::GAIError(String::from(ffi::CStr::from_ptr(libc::gai_strerror(e)).to_string_lossy()))
-        }
-    }
+// the gap between on-login commands.
+const LOGIN_COMMAND_SPACING: time::Duration = time::Duration::from_millis(500);
+
+struct State {
+    login: login::Login,
+    board: Option<board::Board>,
+    disconnected_at: Option<time::Instant>,
+    // the next attempt at reconnecting by ourselves, and when it's due.
+    reconnect: Option<(u32, time::Instant)>,
+    // connected again, but not yet logged in.
+    reconnected: bool,
+    // reconnected within the reconnect window; don't wipe the old view with a new MOTD.
+    resuming: bool,
+    // logging back in with the stored credentials, as FIBS_RECONNECT_LOGIN
+    // allows, until it goes through or they're turned down.
+    relogin: bool,
+    // lowercased names of players whose chat we don't show.
+    muted: collections::HashSet<String>,
+    status: Status,
+    // boards of the current game, oldest first, and which one we're looking at
+    // if we've stepped back from the live board.
+    history: collections::VecDeque<board::Board>,
+    viewing: Option<usize>,
+    // the pip counts over the current game's boards, for the score line.
+    race: pips::Trend,
+    // unlimited matches allow beavers and raccoons.
+    money: bool,
+    // when the last board came in, and whether it was someone else's game.
+    last_board_at: Option<time::Instant>,
+    watching: bool,
+    // every game we have boards for; only the shown one's get past routing.
+    games: games::Games,
+    // we marked ourselves away for being idle, and should come back on activity.
+    auto_away: bool,
+    clip_numbers: bool,
+    // lowercased player name to color, as set with /color.
+    player_colors: collections::HashMap<String, u8>,
+    // lines held back until login, as last shown.
+    pending: usize,
+    // on-login commands still to send, and the last one sent and when, to
+    // pin any error on it.
+    login_commands: collections::VecDeque<String>,
+    login_command: Option<(String, time::Instant)>,
+    // the match score above the FIBS box, as last shown; None outside a match.
+    score: Option<String>,
+    notifications: notify::Notifications,
+    // tells we've had, oldest first: who from, what they said and when.
+    tells: collections::VecDeque<(String, String, time::Instant)>,
+    // everyone online, as of their last WHO line, by name.
+    who: collections::BTreeMap<String, who::WhoInfo>,
+    // see hooks::handlers.
+    handlers: Vec<hooks::Handler>,
+    // the panic key has been pressed, and chat is hidden if it was set to.
+    safe: bool,
+    quiet: bool,
 }
 
-impl From<sync::mpsc::TryRecvError> for Error {
-    fn from(_: sync::mpsc::TryRecvError) -> Error {
-        Error::SyncError(String::from("fibs thread disconnected"))
-    }
+#[allow(clippy::upper_case_acronyms)]
+enum Update {
+    MOTD(String),
+    AppendChars(String),
+    AppendLine(String),
+    // a protocol error from the server, kept apart from its text errors.
+    ClipError(String),
+    // the INPUT line as shown, and the cursor's column in it.
+    Input(String, usize),
+    Banner(Option<Banner>),
+    Board(board::Board),
+    Status(Status),
+    // the notifications up now, oldest first, and whether each is urgent.
+    Notifications(Vec<(String, bool)>),
+    // the terminal bell.
+    Bell,
+    History(Option<HistoryView>),
+    // lines shown over the FIBS box until dismissed with Esc.
+    Popup(Option<Vec<String>>),
+    ClipNumbers(bool),
+    PlayerColor(String, u8),
+    Pending(usize),
+    Score(Option<String>),
+    // zoom the drawn board in (positive) or out.
+    Zoom(i8),
+    // give the board more of the FIBS box (positive) or less.
+    Split(i8),
+    // scroll the MOTD right (positive) or left, in columns.
+    Across(isize),
+    // page the FIBS box back through the buffer, or on towards the newest,
+    // by its height this many times.
+    ScrollUp(usize),
+    ScrollDown(usize),
+    // we're done; the front-end stops.
+    Quit,
+    // the terminal's new size, in columns and rows.
+    Resize(u16, u16),
+    // everything drawn again, as it is now.
+    Redraw,
+    // the FIBS box and status line drawn again, with what a capped frame's
+    // worth of updates did to them.
+    Frame,
+    Clear,
 }
 
-impl From<sync::mpsc::RecvError> for Error {
-    fn from(_: sync::mpsc::RecvError) -> Error {
-        Error::SyncError(String::from("fibs thread disconnected"))
-    }
+// an earlier board of the game, shown in place of the FIBS buffer.
+struct HistoryView {
+    board: board::Board,
+    index: usize,
+    len: usize,
 }
 
-impl<T> From<sync::mpsc::SendError<T>> for Error {
-    fn from(_: sync::mpsc::SendError<T>) -> Error {
-        Error::SyncError(String::from("tui thread disconnected"))
-    }
+// shown under the INPUT box.
+#[derive(Clone, Default)]
+struct Status {
+    // as last confirmed by the server; unknown until it tells us.
+    ready: Option<bool>,
+    // client side: decline invitations for us.
+    dnd: bool,
+    // what can be typed right now, e.g. when the cube has been turned.
+    hints: Option<String>,
+    // ours, as last reported by the server.
+    rating: Option<f64>,
+    experience: Option<u32>,
+    // connecting, logging in, logged in or disconnected, and as whom once
+    // the server's said.
+    connection: Option<&'static str>,
+    user: Option<String>,
+    // host:port we're connected to.
+    server: Option<String>,
+    // how many are online, once there's been a WHO list.
+    online: Option<usize>,
+    // which game is shown, when boards are coming in for more than one.
+    games: Option<String>,
 }
 
-impl<T> From<sync::PoisonError<T>> for Error {
-    fn from(_: sync::PoisonError<T>) -> Error {
-        Error::SyncError(String::from("tui thread disconnected"))
+impl Status {
+    fn standing(&self) -> Option<String> {
+        match (self.rating, self.experience) {
+            (Some(rating), Some(experience)) => Some(format!("rating {:.2}, experience {}", rating, experience)),
+            (Some(rating), None) => Some(format!("rating {:.2}", rating)),
+            (None, Some(experience)) => Some(format!("experience {}", experience)),
+            (None, None) => None,
+        }
     }
 }
 
-fn resolvev4(hostname: String, port: u16) -> Result<net::SocketAddrV4> {
-    let c_hostname = ffi::CString::new(hostname)?;
-    let c_port = ffi::CString::new(port.to_string())?;
-    let mut res = libc::addrinfo {
-        ai_flags: 0,
-        ai_family: 0,
-        ai_socktype: 0,
-        ai_protocol: 0,
-        ai_addrlen: 0,
-        ai_addr: ptr::null_mut(),
-        ai_canonname: ptr::null_mut(),
-        ai_next: ptr::null_mut(),
-    };
-    let mut cursor: *mut libc::addrinfo = &mut res;
-    unsafe {
-        match libc::getaddrinfo(c_hostname.as_ptr(), c_port.as_ptr(), ptr::null(), &mut cursor) {
-            0 => {
-                let res_addr = (*cursor).ai_addr as *mut libc::sockaddr_in;
-                Ok(net::SocketAddrV4::new(
-                        net::Ipv4Addr::from((*res_addr).sin_addr.s_addr.swap_bytes()),
-                        (*res_addr).sin_port.swap_bytes(),
-                ))
-            }
-            e => Err(e.into())
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ready = match self.ready {
+            Some(true) => "ready",
+            Some(false) => "not ready",
+            None => "ready?",
+        };
+        if let Some(connection) = self.connection {
+            write!(f, "{} | ", connection)?;
+        }
+        match (&self.user, &self.server) {
+            (Some(user), Some(server)) => write!(f, "{}@{} | ", user, server)?,
+            (None, Some(server)) => write!(f, "{} | ", server)?,
+            (Some(user), None) => write!(f, "{} | ", user)?,
+            (None, None) => {}
+        }
+        if let Some(online) = self.online {
+            write!(f, "{} online | ", online)?;
+        }
+        write!(f, "{} | dnd {}", ready, if self.dnd { "on" } else { "off" })?;
+        if let Some(standing) = self.standing() {
+            write!(f, " | {}", standing)?;
+        }
+        if let Some(games) = &self.games {
+            write!(f, " | {}, F9 next", games)?;
         }
+        if let Some(hints) = &self.hints {
+            write!(f, " | {}", hints)?;
+        }
+        Ok(())
     }
 }
 
-fn spawn_fibs_thread(mut tcp: net::TcpStream, tx: sync::mpsc::SyncSender<u8>) -> Result<thread::JoinHandle<Result<()>>> {
-    Ok(thread::spawn(move || -> Result<()> {
-        let mut buf = [0; 4096];
+// shown above the FIBS box until replaced or cleared.
+struct Banner {
+    text: String,
+    urgent: bool,
+}
 
-        loop {
-            let n = tcp.read(&mut buf)?;
 
-            for i in 0..n {
-                tx.send(buf[i])?;
-            };
-        }
-    }))
-}
+fn spawn_input_thread<K: keys::KeySource>(
+    mut keys: K,
+    conn: sync::Arc<sync::Mutex<connection::Connection>>,
+    activity: sync::Arc<sync::Mutex<idle::Activity>>,
+    updates_tx: updates::UpdateSender,
+    commands_tx: sync::mpsc::Sender<command::Command>,
+    tui_ready: sync::Arc<sync::Barrier>,
+    config: &config::Config,
+) -> Result<thread::JoinHandle<Result<()>>> {
+    let panic_key = config.panic_key;
+    let enter = config.enter;
+    let theme = config.theme;
+    let mut history = input::History::new(config.input_history);
 
-fn spawn_input_thread(mut tcp: net::TcpStream, updates_tx: sync::mpsc::Sender<Update>) -> Result<thread::JoinHandle<Result<()>>> {
     Ok(thread::spawn(move || -> Result<()> {
-        let stdin = io::stdin();
-        let mut ln = String::new();
-
-        for k in stdin.keys() {
+        let mut ln = input::InputLine::new();
+        let send = |ln: &str| -> Result<()> {
+            for ln in ln.split('\n') {
+                submit(String::from(ln), &conn, &activity, &updates_tx, &commands_tx)?;
+            }
+            Ok(())
+        };
+        // a password is shown as stars.
+        let show = |ln: &input::InputLine| -> Result<()> {
+            let shown = if conn.lock()?.masked() { ln.masked() } else { ln.shown(theme.newline) };
+            updates_tx.send(Update::Input(shown, ln.cursor()))?;
+            Ok(())
+        };
+
+        // keys typed before the TUI has drawn the INPUT box wait in the
+        // terminal until it has, rather than being echoed over nothing.
+        tui_ready.wait();
+
+        while let Some(k) = keys.next_key() {
+            activity.lock()?.key();
             match k {
+                Ok(k) if k == panic_key => {
+                    commands_tx.send(command::Command::Panic)?;
+                }
+                // raw mode keeps these from the terminal's usual signals.
+                Ok(termion::event::Key::Ctrl('c' | 'd')) => {
+                    commands_tx.send(command::Command::Quit)?;
+                    return Ok(());
+                }
+                Ok(k) if enter.sends(&k) => {
+                    let password = conn.lock()?.masked();
+                    let sent = ln.take();
+                    send(&sent)?;
+                    if sent.split('\n').any(command::quits) {
+                        return Ok(());
+                    }
+                    if !password {
+                        history.push(sent);
+                    }
+                    show(&ln)?;
+                }
+                Ok(termion::event::Key::Up) => {
+                    if let Some(older) = history.up(&ln.text()) {
+                        ln.set(older);
+                        show(&ln)?;
+                    }
+                }
+                Ok(termion::event::Key::Down) => {
+                    if let Some(newer) = history.down() {
+                        ln.set(newer);
+                        show(&ln)?;
+                    }
+                }
+                Ok(termion::event::Key::Char('\n')) | Ok(termion::event::Key::Alt('\r' | '\n')) => {
+                    ln.insert('\n');
+                    show(&ln)?;
+                }
                 Ok(termion::event::Key::Char(c)) => {
-                    if c == '\n' {
-                        ln.push('\r');
-                        let payload = ln.as_bytes();
-                        let n = tcp.write(&payload)?;
-                        ln.clear();
-                    } else {
-                        let mut s = String::new();
-                        s.push(c);
-
-                        let chars_update = Update::AppendChars(s.clone());
-                        updates_tx.send(chars_update)?;
-
-                        let input_update = Update::Input(s);
-                        updates_tx.send(input_update)?;
-
-                        ln.push(c);
+                    if !conn.lock()?.masked() {
+                        updates_tx.send(Update::AppendChars(c.to_string()))?;
+                    }
+                    ln.insert(c);
+                    show(&ln)?;
+                }
+                Ok(termion::event::Key::Backspace) => {
+                    if ln.backspace() {
+                        show(&ln)?;
+                    }
+                }
+                Ok(termion::event::Key::Delete) => {
+                    if ln.delete() {
+                        show(&ln)?;
+                    }
+                }
+                Ok(termion::event::Key::F(2)) => {
+                    commands_tx.send(command::Command::Ready)?;
+                }
+                Ok(termion::event::Key::F(3)) => {
+                    commands_tx.send(command::Command::Dnd)?;
+                }
+                Ok(termion::event::Key::F(5)) => {
+                    commands_tx.send(command::Command::HistoryBack)?;
+                }
+                Ok(termion::event::Key::F(6)) => {
+                    commands_tx.send(command::Command::HistoryForward)?;
+                }
+                Ok(termion::event::Key::F(7)) => {
+                    updates_tx.send(Update::Zoom(-1))?;
+                }
+                Ok(termion::event::Key::F(8)) => {
+                    updates_tx.send(Update::Zoom(1))?;
+                }
+                // with nothing typed there's no cursor to move, so the
+                // arrows scroll the MOTD's art across instead.
+                Ok(termion::event::Key::Left) if ln.is_empty() => {
+                    updates_tx.send(Update::Across(-MOTD_SCROLL))?;
+                }
+                Ok(termion::event::Key::Right) if ln.is_empty() => {
+                    updates_tx.send(Update::Across(MOTD_SCROLL))?;
+                }
+                Ok(termion::event::Key::Left) => {
+                    if ln.left() {
+                        show(&ln)?;
+                    }
+                }
+                Ok(termion::event::Key::Right) => {
+                    if ln.right() {
+                        show(&ln)?;
                     }
                 }
+                Ok(termion::event::Key::PageUp) => {
+                    updates_tx.send(Update::ScrollUp(1))?;
+                }
+                Ok(termion::event::Key::PageDown) => {
+                    updates_tx.send(Update::ScrollDown(1))?;
+                }
+                Ok(termion::event::Key::F(10)) => {
+                    updates_tx.send(Update::Split(-1))?;
+                }
+                Ok(termion::event::Key::F(11)) => {
+                    updates_tx.send(Update::Split(1))?;
+                }
+                Ok(termion::event::Key::Esc) => {
+                    updates_tx.send(Update::Popup(None))?;
+                }
+                Ok(termion::event::Key::F(9)) => {
+                    commands_tx.send(command::Command::NextGame)?;
+                }
                 Ok(_) => {}
                 Err(e) => {
                     return Err(e.into());
@@ -183,236 +409,1850 @@ fn spawn_input_thread(mut tcp: net::TcpStream, updates_tx: sync::mpsc::Sender<Up
     }))
 }
 
+// hands a finished line of input to the client if it's one of our commands,
+// otherwise to FIBS.
+fn submit(
+    ln: String,
+    conn: &sync::Mutex<connection::Connection>,
+    activity: &sync::Mutex<idle::Activity>,
+    updates_tx: &updates::UpdateSender,
+    commands_tx: &sync::mpsc::Sender<command::Command>,
+) -> Result<()> {
+    activity.lock()?.sent();
+    if let Some(cmd) = command::Command::parse(&ln) {
+        commands_tx.send(cmd)?;
+    } else {
+        send_typed(conn, &ln, updates_tx)?;
+    }
+
+    Ok(())
+}
+
+fn send_fibs(conn: &sync::Mutex<connection::Connection>, ln: &str) -> Result<()> {
+    conn.lock()?.send(ln);
+    Ok(())
+}
+
+// sends what was typed, unless FIBS is sure to refuse it for one of its
+// limits, in which case says why not.
+fn send_typed(conn: &sync::Mutex<connection::Connection>, ln: &str, updates_tx: &updates::UpdateSender) -> Result<()> {
+    let mut conn = conn.lock()?;
+    match conn.blocked(ln) {
+        Some(why) => updates_tx.send(Update::AppendLine(format!("not sent: {}", why)))?,
+        None => conn.send(ln),
+    }
+    Ok(())
+}
+
+// how the TUI draws the FIBS box: its size, which follows the terminal, and
+// the look of what's in it, which follows the settings.
+struct Frame {
+    theme: &'static terminal::Theme,
+    chrome: colors::Chrome,
+    color: bool,
+    // between the borders; what's shown in it is two columns narrower.
+    width: usize,
+    height: u16,
+    zoom: u8,
+    overflow: render::Overflow,
+    labels: render::Labels,
+    clip_numbers: bool,
+    // used when there's color, lowercased player name to color.
+    player_colors: collections::HashMap<String, u8>,
+    who_grading: who::Grading,
+}
+
+impl Frame {
+    fn redraw(&self, rows: &[String]) -> Result<()> {
+        redraw_fibs_buffer(&rows.iter().collect(), self.theme, self.chrome, self.width, self.height)?;
+        Ok(())
+    }
+
+    // the chat in `window` of `fibs_buffer`, and the live board if it has
+    // a pane of its own.
+    fn redraw_live(&self, fibs_buffer: &scrollback::Scrollback, window: scrollback::Window, panes: &split::Split) -> Result<()> {
+        let width = self.width - 2;
+        let chat = fibs_window(fibs_buffer, window, width, self.clip_numbers, self.color.then_some(&self.player_colors), &self.who_grading);
+        self.redraw(&panes.lines(chat, width, self.height as usize, self.zoom, self.overflow, self.labels))
+    }
+
+    fn redraw_history(&self, view: &HistoryView) -> Result<()> {
+        self.redraw(&history_lines(view, self.width - 2, self.height, self.zoom, self.overflow, self.labels))
+    }
+
+    fn redraw_popup(&self, lines: &[String]) -> Result<()> {
+        self.redraw(&popup_rows(lines, self.width - 2, self.height))
+    }
+}
+
 // doesn't do overflow checking, text may escape container...?
-fn redraw_fibs_buffer(fibs_buffer: &Vec<&String>) -> Result<(u16, u16)> {
-    let mut stdout = io::stdout();
-    let view_width = 73;
+fn redraw_fibs_buffer(fibs_buffer: &Vec<&String>, theme: &terminal::Theme, chrome: colors::Chrome, view_width: usize, height: u16) -> Result<(u16, u16)> {
+    let mut stdout = screen::out();
     let mut row: u16 = 3;
     let mut col: u16 = 3;
     let tui_motd = fibs_buffer
-        .into_iter()
+        .iter()
         .fold(String::new(), |mut s, ln| {
-            row = row + 1;
-            col = 3 + ln.len() as u16;
+            row += 1;
+            col = 3 + text::display_width(ln) as u16;
             s.push_str(format!("{}", termion::cursor::Goto(4, row + 1)).as_str());
             s.push_str(ln.as_str());
             s
         });
 
-    write!(stdout, "{}{}{}", termion::cursor::Goto(2, 27), termion::clear::BeforeCursor, termion::cursor::Goto(2, 3))?;
-    write!(stdout, "╔═FIBS{}╗", String::from("═").repeat(view_width - 5))?;
+    // just the box; the banner and score above it, and notifications beside
+    // it, stay put.
+    for row in 3..height + 5 {
+        write!(stdout, "{}{}", termion::cursor::Goto(1, row), " ".repeat(view_width + 3))?;
+    }
+    write!(stdout, "{}", termion::cursor::Goto(2, 3))?;
+    write!(stdout, "{}", chrome.frame(&format!("{}{}FIBS{}{}", theme.top_left, theme.horizontal, theme.horizontal.repeat(view_width - 5), theme.top_right)))?;
 
-    for row in 4..26 {
+    for row in 4..height + 4 {
         write!(stdout, "{}", termion::cursor::Goto(2, row))?;
-        write!(stdout, "║{}║", String::from(" ").repeat(view_width))?;
+        write!(stdout, "{}{}{}", chrome.frame(theme.vertical), String::from(" ").repeat(view_width), chrome.frame(theme.vertical))?;
     }
 
-    write!(stdout, "{}", termion::cursor::Goto(2, 26))?;
-    write!(stdout, "╚{}╝", String::from("═").repeat(view_width))?;
+    write!(stdout, "{}", termion::cursor::Goto(2, height + 4))?;
+    write!(stdout, "{}", chrome.frame(&format!("{}{}{}", theme.bottom_left, theme.horizontal.repeat(view_width), theme.bottom_right)))?;
 
     write!(stdout, "{}{}", termion::cursor::Goto(3, 4), tui_motd)?;
-    io::stdout().flush().unwrap();
+    screen::out().flush().unwrap();
 
-    return Ok((col, row + 1));
+    Ok((col, row + 1))
 }
 
-fn spawn_tui_thread() -> Result<(sync::mpsc::Sender<Update>, thread::JoinHandle<Result<()>>)> {
-    let (updates_tx, updates_rx) = sync::mpsc::channel::<Update>();
+// the rows of the buffer in view, wrapped to `width` and as they should be
+// displayed, the MOTD aside. chat senders are painted in their colors, and WHO lines graded,
+// unless `player_colors` is None.
+fn fibs_window(
+    fibs_buffer: &scrollback::Scrollback,
+    visible_window: scrollback::Window,
+    width: usize,
+    clip_numbers: bool,
+    player_colors: Option<&collections::HashMap<String, u8>>,
+    who_grading: &who::Grading,
+) -> Vec<String> {
+    // only the lines in view are wrapped.
+    let (first, skip) = fibs_buffer.line_at(visible_window.0, width);
+    fibs_buffer
+        .lines()
+        .iter()
+        .enumerate()
+        .skip(first)
+        .flat_map(|(i, ln)| {
+            if fibs_buffer.is_verbatim(i) {
+                // filtered as the rest are, below.
+                let shown = match player_colors {
+                    Some(_) => text::keep_sgr(ln),
+                    None => text::plain(ln),
+                };
+                return text::carry_sgr(vec![text::cut(&shown, fibs_buffer.across(), width)]);
+            }
+            let shown = if clip_numbers { clip::annotate(ln) } else { ln.clone() };
+            // colors the server sent along come through with ours, and
+            // nothing else it could do to the terminal does.
+            let mut rows = match player_colors {
+                Some(_) => text::carry_sgr(text::wrap(&text::keep_sgr(&shown), width)),
+                None => text::wrap(&text::plain(&shown), width),
+            };
+            // and ours go on over the wrapped rows.
+            if let (Some(sender), Some(overrides)) = (classify::sender(ln), player_colors) {
+                rows[0] = colors::paint(&rows[0], &sender, colors::for_player(&sender, overrides));
+            }
+            if let (Some(info), Some(_)) = (who::WhoInfo::parse(ln), player_colors) {
+                let c = who_grading.color(&info);
+                rows = rows.iter().map(|row| colors::paint(row, row, c)).collect();
+            }
+            if ln.starts_with(CLIP_ERROR_LABEL) && player_colors.is_some() {
+                // lightred.
+                rows = rows.iter().map(|row| colors::paint(row, row, 9)).collect();
+            }
+            rows
+        })
+        .skip(skip)
+        .take(visible_window.1)
+        .collect()
+}
 
-    let h = thread::spawn(move || {
-        let mut stdout = io::stdout();
-        let view_width = 73;
+// an earlier board, as shown in place of the FIBS buffer.
+fn history_lines(view: &HistoryView, width: usize, height: u16, zoom: u8, overflow: render::Overflow, labels: render::Labels) -> Vec<String> {
+    let mut lines = vec![
+        format!("-- HISTORY: board {} of {}, F5 older, F6 newer, F7/F8 zoom --", view.index + 1, view.len),
+        String::new(),
+    ];
+    lines.extend(render::board(&view.board, render::fit(zoom, width, (height as usize).saturating_sub(2)), overflow, labels));
+    lines.push(String::new());
+    lines.extend(text::wrap(&view.board.describe(), width));
+    lines.truncate(height as usize);
+    lines
+}
 
-        // termion's cursor_pos() panics....
-        let mut fibs_cursor_pos: (u16, u16) = (4, 4);
-        let mut input_cursor_pos: (u16, u16) = (6, 29);
+// a popup's lines, those too long for `width` wrapped and the rest left as
+// they are so drawings keep their spacing, as many rows as fit in the box.
+fn popup_rows(lines: &[String], width: usize, height: u16) -> Vec<String> {
+    lines
+        .iter()
+        .flat_map(|ln| if ln.chars().count() > width { text::wrap(ln, width) } else { vec![ln.clone()] })
+        .take(height as usize)
+        .collect()
+}
 
-        let mut fibs_buffer: Vec<String> = Vec::new();
-        let mut visible_window: (u8, u8) = (0, 22); // closed range [0, 22]
+// the INPUT box, just below a FIBS box `height` rows tall.
+fn redraw_input_box(theme: &terminal::Theme, chrome: colors::Chrome, view_width: usize, height: u16, label: &str, input: &str) -> Result<()> {
+    let mut stdout = screen::out();
 
-        write!(stdout, "{}{}", termion::clear::All, termion::cursor::Goto(2, 28))?;
-        write!(stdout, "╔═INPUT{}╗", String::from("═").repeat(view_width - 6))?;
+    write!(stdout, "{}", termion::cursor::Goto(2, height + 6))?;
+    write!(stdout, "{}", chrome.frame(&format!("{}{}{}{}{}",
+        theme.top_left,
+        theme.horizontal,
+        label,
+        theme.horizontal.repeat(view_width - 1 - label.len()),
+        theme.top_right)))?;
 
-        write!(stdout, "{}", termion::cursor::Goto(2, 29))?;
-        write!(stdout, "║ > {}║", String::from(" ").repeat(view_width - 3))?;
+    write!(stdout, "{}", termion::cursor::Goto(2, height + 7))?;
+    write!(stdout, "{} {} {}{}", chrome.frame(theme.vertical), chrome.prompt(">"), String::from(" ").repeat(view_width - 3), chrome.frame(theme.vertical))?;
+    write!(stdout, "{}{}", termion::cursor::Goto(6, height + 7), input)?;
 
-        write!(stdout, "{}", termion::cursor::Goto(2, 30))?;
-        write!(stdout, "╚{}╝", String::from("═").repeat(view_width))?;
+    write!(stdout, "{}", termion::cursor::Goto(2, height + 8))?;
+    write!(stdout, "{}", chrome.frame(&format!("{}{}{}", theme.bottom_left, theme.horizontal.repeat(view_width), theme.bottom_right)))?;
 
-        loop {
-            let next = updates_rx.recv()?;
-            match next {
-                Update::MOTD(motd) => {
-                    fibs_buffer = motd
-                        .split("\r\n")
-                        .fold(Vec::<String>::new(), |mut b, s| {
-                            b.push(String::from(s));
-                            b
-                        });
-                    redraw_fibs_buffer(&fibs_buffer.as_slice().iter().collect())?;
-                }
-                Update::AppendChars(s) => {
-                    match fibs_buffer.last_mut() {
-                        Some(ref mut last_ln) => { last_ln.push_str(s.as_str()) }
-                        None => { fibs_buffer.push(s); }
-                    }
-                    let fibs_window = fibs_buffer
-                        .as_slice()
-                        .iter()
-                        .skip(visible_window.0 as usize)
-                        .take((visible_window.1 - visible_window.0) as usize)
-                        .collect();
-                    redraw_fibs_buffer(&fibs_window)?;
-                }
-                Update::AppendLine(s) => {
-                    fibs_buffer.push(s);
-                    visible_window.0 = visible_window.0 + 1;
-                    visible_window.1 = visible_window.1 + 1;
-                    let fibs_window = fibs_buffer
-                        .as_slice()
-                        .iter()
-                        .skip(visible_window.0 as usize)
-                        .take((visible_window.1 - visible_window.0) as usize)
-                        .collect();
-                    redraw_fibs_buffer(&fibs_window)?;
-                }
-                Update::Input(s) => {
-                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
-                    write!(stdout, "{}", s)?;
-                    input_cursor_pos.0 = input_cursor_pos.0 + s.len() as u16;
-                    io::stdout().flush().unwrap();
-                }
-            }
-        }
-    });
+    Ok(())
+}
 
-    Ok((updates_tx, h))
+// the rows a `rows` tall terminal leaves for the FIBS box, after the banner,
+// the INPUT box and the status line.
+fn fibs_box_height(rows: u16) -> u16 {
+    rows.saturating_sub(9).clamp(1, u8::MAX as u16)
 }
 
-fn main() -> Result<()> {
-    let mut stdout = io::stdout().into_raw_mode()?;
-
-    let fibs_hostname = env::vars()
-        .find(|(_envar, val)| val == "FIBS_HOSTNAME")
-        .map(|(_envar, val)| val)
-        .unwrap_or(String::from(DEFAULT_FIBS_SERVER));
-    let fibs_port = env::vars()
-        .find(|(_envar, val)| val == "FIBS_PORT")
-        .and_then(|(_envar, val)| val.parse().ok())
-        .unwrap_or(DEFAULT_FIBS_PORT);
-
-    let fibs_addr = resolvev4(fibs_hostname, fibs_port)?;
-    let tcp = net::TcpStream::connect(fibs_addr)?;
-    let reading_tcp = tcp.try_clone()?;
-    let writing_tcp = tcp.try_clone()?;
+// the columns inside the FIBS and INPUT boxes in a `cols` wide terminal:
+// what's left after the borders and the notifications beside them, no
+// narrower than the boards need and no wider than a chat line can be
+// followed across.
+fn fibs_box_width(cols: u16) -> usize {
+    cols.saturating_sub(3 + NOTIFY_ROOM).clamp(MIN_VIEW_WIDTH, MAX_VIEW_WIDTH) as usize
+}
 
-    let (tcp_tx, tcp_rx) = sync::mpsc::sync_channel::<u8>(4096);
-    let mut state = State {
-        fibs_state: FibsState::MOTD,
-    };
+// where notifications are stacked, just right of a FIBS box `view_width`
+// wide.
+fn notify_column(view_width: usize) -> u16 {
+    view_width as u16 + 5
+}
 
-    let mut buf = vec::Vec::with_capacity(4096);
+const MIN_VIEW_WIDTH: u16 = 73;
+const MAX_VIEW_WIDTH: u16 = 160;
+
+// the least terminal the frame can be drawn in: the FIBS box's width and
+// borders, and a few rows of it.
+const MIN_COLS: u16 = MIN_VIEW_WIDTH + 3;
+const MIN_ROWS: u16 = 12;
+
+fn too_small(cols: u16, rows: u16) -> bool {
+    cols < MIN_COLS || rows < MIN_ROWS
+}
 
-    let mut delta = collections::HashMap::<u8, (u8, collections::HashMap::<u8, u8>)>::new();
+// in place of the frame, which would draw off the edges and garble.
+fn redraw_too_small() -> Result<()> {
+    let mut stdout = screen::out();
+    write!(stdout, "{}{}terminal too small (need at least {}x{})", termion::clear::All, termion::cursor::Goto(1, 1), MIN_COLS, MIN_ROWS)?;
+    stdout.flush()?;
+    Ok(())
+}
 
-    delta.insert(0, (0, collections::HashMap::from([(0x0d, 1)])));
-    delta.insert(1, (0, collections::HashMap::from([(0x0a, 2)])));
+fn redraw_score(score: &Option<String>, color: bool) -> Result<()> {
+    let mut stdout = screen::out();
 
-    // reading motd...
-    delta.insert(2, (2, collections::HashMap::from([(0x0a, 3)])));
+    write!(stdout, "{}{}", termion::cursor::Goto(2, 2), termion::clear::CurrentLine)?;
+    match score {
+        Some(score) if color => write!(stdout, "{}{}{}", termion::style::Bold, score, termion::style::Reset)?,
+        Some(score) => write!(stdout, "{}", score)?,
+        None => {}
+    }
+    Ok(())
+}
 
-    delta.insert(3, (2, collections::HashMap::from([('l' as u8, 4)])));
-    delta.insert(4, (2, collections::HashMap::from([('o' as u8, 5)])));
-    delta.insert(5, (2, collections::HashMap::from([('g' as u8, 6)])));
-    delta.insert(6, (2, collections::HashMap::from([('i' as u8, 7)])));
-    delta.insert(7, (2, collections::HashMap::from([('n' as u8, 8)])));
-    delta.insert(8, (2, collections::HashMap::from([(':' as u8, 9)])));
-    delta.insert(9, (2, collections::HashMap::from([(' ' as u8, 10)])));
+// the status bar, the row under the INPUT box.
+fn redraw_status_bar(status_line: &str, height: u16) -> Result<()> {
+    let mut stdout = screen::out();
+    write!(stdout, "{}{}{}", termion::cursor::Goto(2, height + 9), termion::clear::CurrentLine, status_line)?;
+    Ok(())
+}
 
-    delta.insert(10, (2, collections::HashMap::from([('p' as u8, 11)])));
-    delta.insert(11, (2, collections::HashMap::from([('a' as u8, 12)])));
-    delta.insert(12, (2, collections::HashMap::from([('s' as u8, 13)])));
-    delta.insert(13, (2, collections::HashMap::from([('s' as u8, 14)])));
-    delta.insert(14, (2, collections::HashMap::from([('w' as u8, 15)])));
-    delta.insert(15, (2, collections::HashMap::from([('o' as u8, 16)])));
-    delta.insert(16, (2, collections::HashMap::from([('r' as u8, 17)])));
-    delta.insert(17, (2, collections::HashMap::from([('d' as u8, 18)])));
-    delta.insert(18, (2, collections::HashMap::from([(':' as u8, 19)])));
-    delta.insert(19, (2, collections::HashMap::from([(' ' as u8, 20)])));
+// `notices` stacked beside the FIBS box over the `cleared` there before, or
+// the newest at the end of the status line if the terminal is too narrow.
+fn redraw_notifications(notices: &[(String, bool)], cleared: usize, status_line: &str, cols: u16, view_width: usize, height: u16, color: bool) -> Result<()> {
+    let mut stdout = screen::out();
+    let column = notify_column(view_width);
 
-    let mut s: u8 = 0;
+    if cols < column + NOTIFY_MIN_WIDTH {
+        write!(stdout, "{}{}{}", termion::cursor::Goto(2, height + 9), termion::clear::CurrentLine, status_line)?;
+        if let Some((newest, _)) = notices.last() {
+            write!(stdout, " | {}", newest)?;
+        }
+        return Ok(());
+    }
 
-    // need barriers soon
-    let fibs_handle = spawn_fibs_thread(reading_tcp, tcp_tx.clone())?;
-    let (updates_tx, tui_handle) = spawn_tui_thread()?;
-    let input_handle = spawn_input_thread(writing_tcp, updates_tx.clone())?;
+    let width = (cols - column) as usize;
+    for row in 0..cleared.max(notices.len()) {
+        write!(stdout, "{}{}", termion::cursor::Goto(column, 3 + row as u16), termion::clear::UntilNewline)?;
+    }
+    for (row, (notice, urgent)) in notices.iter().enumerate() {
+        let notice: String = notice.chars().take(width).collect();
+        write!(stdout, "{}", termion::cursor::Goto(column, 3 + row as u16))?;
+        match (color, urgent) {
+            // as urgent banners are.
+            (true, true) => write!(stdout, "{}{}{}{}{}",
+                termion::style::Bold,
+                termion::color::Bg(termion::color::Red),
+                termion::color::Fg(termion::color::White),
+                notice,
+                termion::style::Reset)?,
+            (true, false) => write!(stdout, "{}{}{}", termion::style::Invert, notice, termion::style::Reset)?,
+            (false, _) => write!(stdout, "{}", notice)?,
+        }
+    }
+    Ok(())
+}
 
-    loop {
-        match tcp_rx.try_recv() {
-            Ok(b) => {
-                match state.fibs_state {
-                    FibsState::MOTD => {
-                        // chomp leading whitespace...
-                        if s > 1 {
-                            buf.push(b);
-                        }
+fn redraw_banner(banner: &Option<Banner>, color: bool) -> Result<()> {
+    let mut stdout = screen::out();
+
+    write!(stdout, "{}{}", termion::cursor::Goto(2, 1), termion::clear::CurrentLine)?;
+    match banner {
+        Some(Banner { text, urgent: true }) if color => {
+            write!(stdout, "{}{}{} {} {}",
+                termion::style::Bold,
+                termion::color::Bg(termion::color::Red),
+                termion::color::Fg(termion::color::White),
+                text,
+                termion::style::Reset)?;
+        }
+        Some(Banner { text, urgent: false }) if color => {
+            write!(stdout, "{}{}{}", termion::color::Fg(termion::color::Yellow), text, termion::style::Reset)?;
+        }
+        Some(Banner { text, urgent: true }) => {
+            write!(stdout, "!!! {} !!!", text)?;
+        }
+        Some(Banner { text, urgent: false }) => {
+            write!(stdout, "{}", text)?;
+        }
+        None => {}
+    }
+    stdout.flush()?;
+
+    Ok(())
+}
+
+// updates that only change the FIBS box or the status line, which when frames
+// are capped are drawn a frame's worth at a time, on Update::Frame.
+fn framed(update: &Update) -> bool {
+    matches!(
+        update,
+        Update::MOTD(_)
+            | Update::AppendChars(_)
+            | Update::AppendLine(_)
+            | Update::ClipError(_)
+            | Update::Board(_)
+            | Update::Status(_)
+            | Update::PlayerColor(..)
+            | Update::ClipNumbers(_)
+    )
+}
+
+fn spawn_tui_thread(
+    mut updates_rx: updates::UpdateReceiver,
+    ready: sync::Arc<sync::Barrier>,
+    config: &config::Config,
+    player_colors: collections::HashMap<String, u8>,
+) -> Result<thread::JoinHandle<Result<()>>> {
+    let (mut cols, mut rows) = terminal::capabilities().size.unwrap_or((80, 31));
+    let mut frame = Frame {
+        theme: config.theme,
+        chrome: config.chrome,
+        color: config.color,
+        width: fibs_box_width(cols),
+        height: fibs_box_height(rows),
+        zoom: config.zoom,
+        overflow: config.overflow,
+        labels: config.labels,
+        clip_numbers: config.clip_numbers,
+        player_colors,
+        who_grading: config.who_grading.clone(),
+    };
+    let unwrap = config.unwrap;
+    let split_ratio = config.split;
+    let max_fps = config.max_fps;
 
-                        s = delta
-                            .get(&s)
-                            .and_then(|(default, d)| d.get(&b).or_else(|| Some(default)))
-                            .map(|byte| *byte)
-                            .unwrap_or(0);
+    let h = thread::spawn(move || {
+        updates_rx.cap_frames(max_fps);
 
-                        // hit login prompt...
-                        if s == 10 {
-                            state.fibs_state = FibsState::WaitLogin;
+        // termion's cursor_pos() panics....
+        let mut input_cursor_pos: (u16, u16) = (6, frame.height + 7);
+        // what's shown in and around the INPUT box, for drawing it again.
+        let mut input = String::new();
+        let mut input_label = String::from("INPUT");
+        let mut status_line = String::new();
+        let mut banner: Option<Banner> = None;
+        let mut notifications: Vec<(String, bool)> = Vec::new();
+        let mut score: Option<String> = None;
+
+        let mut fibs_buffer = scrollback::Scrollback::new();
+        // in rows of the wrapped buffer.
+        let mut visible_window = scrollback::bottom(0, frame.height as usize);
+        let mut history: Option<HistoryView> = None;
+        // the live board, over the chat.
+        let mut panes = split::Split::new(split_ratio);
+        let mut popup: Option<Vec<String>> = None;
+        // the paragraph the server is still wrapping, as rejoined so far in
+        // the last line of the buffer, when unwrapping.
+        let mut reflow: Option<String> = None;
+
+        let drawn = (|| -> Result<()> {
+            if too_small(cols, rows) {
+                return redraw_too_small();
+            }
+            write!(screen::out(), "{}", termion::clear::All)?;
+            redraw_input_box(frame.theme, frame.chrome, frame.width, frame.height, &input_label, &input)?;
+            screen::out().flush()?;
+            Ok(())
+        })();
+        // let the input thread go even if that failed, so it isn't left
+        // waiting on us.
+        ready.wait();
+        drawn?;
 
-                            let update = Update::MOTD(String::from_utf8_lossy(buf.as_slice()).into_owned());
-                            updates_tx.send(update)?;
+        loop {
+            let next = updates_rx.recv()?;
+            // what piled up while the terminal was stuck is taken in, then
+            // drawn once on Update::Redraw; what came within a frame of the
+            // last drawn, and goes in the FIBS box or status line, is drawn
+            // on Update::Frame. The bell can't wait for either.
+            let held = updates_rx.catching_up() || (updates_rx.throttled() && framed(&next));
+            screen::hold(held && !matches!(next, Update::Bell));
+            let mut stdout = screen::out();
+            match next {
+                Update::MOTD(motd) => {
+                    fibs_buffer.clear();
+                    for ln in motd.split("\r\n") {
+                        fibs_buffer.push_verbatim(String::from(ln));
+                    }
+                    // down to the prompt at its end.
+                    visible_window = scrollback::bottom(fibs_buffer.rows(frame.width - 2), frame.height as usize);
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                    }
+                }
+                Update::Across(by) => {
+                    fibs_buffer.scroll_across(by, frame.width - 2);
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                        write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                        screen::out().flush().unwrap();
+                    }
+                }
+                update @ (Update::ScrollUp(_) | Update::ScrollDown(_)) => {
+                    visible_window = match update {
+                        Update::ScrollUp(n) => scrollback::up(visible_window, n * frame.height as usize),
+                        Update::ScrollDown(n) => scrollback::down(visible_window, fibs_buffer.rows(frame.width - 2), n * frame.height as usize),
+                        _ => visible_window,
+                    };
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                        write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                        screen::out().flush().unwrap();
+                    }
+                }
+                Update::AppendChars(s) => {
+                    reflow = None;
+                    fibs_buffer.append(&s);
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                    }
+                }
+                Update::AppendLine(s) => {
+                    let before = fibs_buffer.rows(frame.width - 2);
+                    match reflow.take() {
+                        Some(mut joined) => {
+                            joined.push(' ');
+                            joined.push_str(s.trim_start());
+                            fibs_buffer.replace_last(joined.clone());
+                            if text::prewrapped(&s) {
+                                reflow = Some(joined);
+                            }
+                        }
+                        None => {
+                            if unwrap && clip::message_type(&s).is_none() && text::prewrapped(&s) {
+                                reflow = Some(s.clone());
+                            }
+                            fibs_buffer.push(s);
+                        }
+                    }
+                    visible_window = scrollback::followed(visible_window, before, fibs_buffer.rows(frame.width - 2));
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                    }
+                }
+                Update::ClipError(msg) => {
+                    reflow = None;
+                    let before = fibs_buffer.rows(frame.width - 2);
+                    fibs_buffer.push(format!("{}{}", CLIP_ERROR_LABEL, msg));
+                    visible_window = scrollback::followed(visible_window, before, fibs_buffer.rows(frame.width - 2));
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                    }
+                }
+                Update::Input(s, cursor) => {
+                    // over whatever was there, which may have been longer.
+                    let blank = (frame.width - 4).saturating_sub(s.chars().count());
+                    write!(stdout, "{}{}{}", termion::cursor::Goto(6, input_cursor_pos.1), s, " ".repeat(blank))?;
+                    input_cursor_pos.0 = 6 + cursor as u16;
+                    input = s;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Board(board) => {
+                    reflow = None;
+                    // in a pane of its own, the chat's window isn't touched.
+                    if panes.on() {
+                        panes.board = Some(board);
+                    } else {
+                        let before = fibs_buffer.rows(frame.width - 2);
+                        fibs_buffer.push(board.to_string());
+                        visible_window = scrollback::followed(visible_window, before, fibs_buffer.rows(frame.width - 2));
+                    }
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                    }
+                }
+                Update::Clear => {
+                    reflow = None;
+                    fibs_buffer.clear();
+                    visible_window = scrollback::bottom(0, frame.height as usize);
+                    history = None;
+                    panes.board = None;
+                    frame.redraw(&[])?;
+                }
+                Update::History(view) => {
+                    history = view;
+                    if popup.is_some() {
+                        continue;
+                    }
+                    // a board doesn't squeeze into a box that small.
+                    if too_small(cols, rows) {
+                        redraw_too_small()?;
+                        continue;
+                    }
+                    match &history {
+                        Some(view) => {
+                            frame.redraw_history(view)?;
+                        }
+                        None => {
+                            frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                        }
+                    }
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Popup(None) if popup.is_none() => {}
+                Update::Popup(lines) => {
+                    popup = lines;
+                    if too_small(cols, rows) {
+                        redraw_too_small()?;
+                        continue;
+                    }
+                    match (&popup, &history) {
+                        (Some(lines), _) => {
+                            frame.redraw_popup(lines)?;
+                        }
+                        (None, Some(view)) => {
+                            frame.redraw_history(view)?;
+                        }
+                        (None, None) => {
+                            frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                        }
+                    }
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::PlayerColor(player, c) => {
+                    frame.player_colors.insert(player, c);
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                    }
+                }
+                Update::ClipNumbers(on) => {
+                    frame.clip_numbers = on;
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                    }
+                }
+                Update::Pending(n) => {
+                    input_label = if n > 0 { format!("INPUT ({} held until login)", n) } else { String::from("INPUT") };
+                    redraw_input_box(frame.theme, frame.chrome, frame.width, frame.height, &input_label, &input)?;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Status(status) => {
+                    status_line = format!(" {} | F2 ready, F3 dnd", status);
+                    redraw_status_bar(&status_line, frame.height)?;
+                    redraw_notifications(&notifications, notifications.len(), &status_line, cols, frame.width, frame.height, frame.color)?;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Notifications(notices) => {
+                    let cleared = notifications.len();
+                    notifications = notices;
+                    redraw_notifications(&notifications, cleared, &status_line, cols, frame.width, frame.height, frame.color)?;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Bell => {
+                    write!(stdout, "\x07")?;
+                    screen::out().flush().unwrap();
+                }
+                update @ (Update::Resize(..) | Update::Redraw) => {
+                    if let Update::Resize(new_cols, new_rows) = update {
+                        (cols, rows) = (new_cols, new_rows);
+                    }
+                    frame.width = fibs_box_width(cols);
+                    frame.height = fibs_box_height(rows);
+                    visible_window = scrollback::pinned(visible_window, fibs_buffer.rows(frame.width - 2), frame.height as usize);
+                    input_cursor_pos.1 = frame.height + 7;
+                    if too_small(cols, rows) {
+                        redraw_too_small()?;
+                        continue;
+                    }
 
-                            buf.clear();
+                    write!(stdout, "{}", termion::clear::All)?;
+                    redraw_banner(&banner, frame.color)?;
+                    redraw_score(&score, frame.color)?;
+                    match (&popup, &history) {
+                        (Some(lines), _) => frame.redraw_popup(lines)?,
+                        (None, Some(view)) => frame.redraw_history(view)?,
+                        (None, None) => {
+                            frame.redraw_live(&fibs_buffer, visible_window, &panes)?
                         }
+                    };
+                    redraw_input_box(frame.theme, frame.chrome, frame.width, frame.height, &input_label, &input)?;
+                    redraw_status_bar(&status_line, frame.height)?;
+                    redraw_notifications(&notifications, 0, &status_line, cols, frame.width, frame.height, frame.color)?;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Frame => {
+                    if too_small(cols, rows) {
+                        continue;
+                    }
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                    }
+                    redraw_status_bar(&status_line, frame.height)?;
+                    redraw_notifications(&notifications, notifications.len(), &status_line, cols, frame.width, frame.height, frame.color)?;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Zoom(change) => {
+                    frame.zoom = frame.zoom.saturating_add_signed(change).clamp(render::MIN_ZOOM, render::MAX_ZOOM);
+                    if too_small(cols, rows) {
+                        redraw_too_small()?;
+                        continue;
                     }
-                    FibsState::WaitLogin => {
-                        s = delta
-                            .get(&s)
-                            .and_then(|(default, d)| d.get(&b).or_else(|| Some(default)))
-                            .map(|byte| *byte)
-                            .unwrap_or(0);
-
-                        // hit password prompt...
-                        if s == 20 {
-                            state.fibs_state = FibsState::WaitPassword;
-                            let update = Update::AppendLine(String::from("password: "));
-                            updates_tx.send(update)?;
-                            buf.clear();
+                    match (&popup, &history) {
+                        (Some(_), _) => continue,
+                        (None, Some(view)) => {
+                            frame.redraw_history(view)?;
+                        }
+                        (None, None) => {
+                            frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
                         }
                     }
-                    FibsState::WaitPassword => {
-                        break;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Split(change) => {
+                    panes.adjust(change as f32 * split::STEP);
+                    if history.is_none() && popup.is_none() {
+                        frame.redraw_live(&fibs_buffer, visible_window, &panes)?;
+                        write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                        screen::out().flush().unwrap();
                     }
                 }
+                Update::Score(s) => {
+                    score = s;
+                    redraw_score(&score, frame.color)?;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
+                Update::Quit => return Ok(()),
+                Update::Banner(b) => {
+                    banner = b;
+                    redraw_banner(&banner, frame.color)?;
+                    write!(stdout, "{}", termion::cursor::Goto(input_cursor_pos.0, input_cursor_pos.1))?;
+                    screen::out().flush().unwrap();
+                }
             }
-            Err(sync::mpsc::TryRecvError::Empty) => {
-                continue;
+            // whatever was just drawn went off the edges; cover it up until
+            // the terminal's big enough again.
+            if too_small(cols, rows) {
+                redraw_too_small()?;
             }
-            Err(e @ sync::mpsc::TryRecvError::Disconnected) => { break; }
         }
-    }
+    });
 
-    tcp.shutdown(net::Shutdown::Both)?;
-    stdout.suspend_raw_mode()?;
+    Ok(h)
+}
 
-    fibs_handle.join().unwrap_or_else(|_| {
-        write!(stdout, "fibs thread panicked")?;
-        stdout.flush()?;
-        Ok(())
-    })?;
+// e.g. "You 3 - 1 bob (to 5), pips 142 - 150 ▲6": the race as `trend` has
+// it for this board, with how far our lead in it last grew or shrank.
+fn score_header(board: &board::Board, trend: &mut pips::Trend, theme: &terminal::Theme) -> String {
+    let length = if board.match_length == UNLIMITED_MATCH {
+        String::from("unlimited")
+    } else {
+        format!("to {}", board.match_length)
+    };
+    let ((ours, theirs), change) = trend.next(board);
+    let arrow = match change {
+        0 => String::new(),
+        n if n > 0 => format!(" {}{}", theme.gaining, n),
+        n => format!(" {}{}", theme.losing, -n),
+    };
+    format!("{} {} - {} {} ({}), pips {} - {}{}",
+        board.player, board.player_score, board.opponent_score, board.opponent, length, ours, theirs, arrow)
+}
 
-    tui_handle.join().unwrap_or_else(|_| {
-        write!(stdout, "tui thread panicked")?;
-        stdout.flush()?;
-        Ok(())
+fn set_score(state: &mut State, score: Option<String>, updates_tx: &updates::UpdateSender) -> Result<()> {
+    if score != state.score {
+        state.score = score.clone();
+        updates_tx.send(Update::Score(score))?;
+    }
+    Ok(())
+}
+
+fn notify(state: &mut State, text: String, urgent: bool, updates_tx: &updates::UpdateSender) -> Result<()> {
+    if state.notifications.push(text, urgent, time::Instant::now()) {
+        updates_tx.send(Update::Notifications(state.notifications.lines()))?;
+    }
+    Ok(())
+}
+
+// how a reconnect went, shown and sounded as FIBS_RECONNECT_NOTIFY says.
+fn reconnect_notice(state: &mut State, config: &config::Config, text: String, failed: bool, updates_tx: &updates::UpdateSender) -> Result<()> {
+    if config.reconnect_notify.visual {
+        notify(state, text, failed, updates_tx)?;
+    }
+    if config.reconnect_notify.bell {
+        updates_tx.send(Update::Bell)?;
+    }
+    Ok(())
+}
+
+// the match that `ln` says just ended, to the webhook.
+fn post_result(state: &State, config: &config::Config, url: &str, ln: &str, updates_tx: &updates::UpdateSender) -> Result<()> {
+    let (Some((winner, match_length, won, lost)), Some(board)) = (classify::match_result(ln), &state.board) else {
+        return Ok(());
+    };
+    let player = config.user.clone().unwrap_or_else(|| String::from("You"));
+    let ours = winner == "You";
+    let result = webhook::MatchResult {
+        winner: if ours { player.clone() } else { winner },
+        player,
+        opponent: board.opponent.clone(),
+        match_length,
+        score: if ours { (won, lost) } else { (lost, won) },
+        rating: state.status.rating,
+    };
+    if let Err(e) = webhook::post(url, &result, updates_tx) {
+        updates_tx.send(Update::AppendLine(format!("couldn't post the match result: {}", e)))?;
+    }
+    Ok(())
+}
+
+// where the connection's at, and how many are online, on the status line
+// whenever either changes.
+fn show_connection(state: &mut State, updates_tx: &updates::UpdateSender) -> Result<()> {
+    let connection = match (state.login.stage(), state.disconnected_at) {
+        (_, Some(_)) if state.reconnect.is_some() => "reconnecting",
+        (_, Some(_)) => "disconnected",
+        (login::Stage::Greeting, None) => "connecting",
+        (login::Stage::Name | login::Stage::Password, None) => "logging in",
+        (login::Stage::In, None) => "logged in",
+    };
+    let online = (!state.who.is_empty()).then_some(state.who.len());
+    if state.status.connection != Some(connection) || state.status.online != online {
+        state.status.connection = Some(connection);
+        state.status.online = online;
+        updates_tx.send(Update::Status(state.status.clone()))?;
+    }
+    Ok(())
+}
+
+// the status line's game indicator, as of the latest routing or switch.
+fn show_games(state: &mut State, updates_tx: &updates::UpdateSender) -> Result<()> {
+    let games = state.games.indicator();
+    if games != state.status.games {
+        state.status.games = games;
+        updates_tx.send(Update::Status(state.status.clone()))?;
+    }
+    Ok(())
+}
+
+fn handle_line(
+    state: &mut State,
+    config: &config::Config,
+    mut ln: String,
+    conn: &sync::Mutex<connection::Connection>,
+    updates_tx: &updates::UpdateSender,
+) -> Result<()> {
+    conn.lock()?.received(&ln);
+    // the line's shown anyway, but is easily lost among the rest.
+    if limits::rejection(&ln) {
+        notify(state, format!("FIBS limit: {}", ln.trim_start_matches("** ")), true, updates_tx)?;
+    }
+    match clip::parse(&ln) {
+        Some(clip::ClipMessage::WhoInfo(info)) => {
+            state.who.insert(info.name.clone(), info);
+        }
+        Some(clip::ClipMessage::Logout { name, .. }) => {
+            state.who.remove(&name);
+        }
+        _ => {}
+    }
+    if ln.starts_with("board:") {
+        if let Ok((board, warning)) = board::Board::parse(&ln) {
+            if let Some(warning) = warning {
+                updates_tx.send(Update::AppendLine(format!("{}: {}", warning, ln)))?;
+            }
+            // another game's board is only filed away until it's switched to.
+            let shown = state.games.route(&board);
+            show_games(state, updates_tx)?;
+            if !shown {
+                return Ok(());
+            }
+            let caption = state.board
+                .as_ref()
+                .filter(|_| config.describe_moves)
+                .and_then(|previous| moves::describe_move(previous, &board));
+
+            // a new game (or opponent) starts a new history.
+            let same_game = state.history.back().map(|last| {
+                last.player == board.player
+                    && last.opponent == board.opponent
+                    && last.player_score == board.player_score
+                    && last.opponent_score == board.opponent_score
+            });
+            if same_game != Some(true) {
+                state.history.clear();
+                state.race.reset();
+                if state.viewing.take().is_some() {
+                    updates_tx.send(Update::History(None))?;
+                }
+            }
+            if state.history.len() == HISTORY_LIMIT {
+                state.history.pop_front();
+                state.viewing = state.viewing.map(|i| i.saturating_sub(1));
+            }
+            state.history.push_back(board.clone());
+
+            state.money = board.match_length == UNLIMITED_MATCH;
+            state.last_board_at = Some(time::Instant::now());
+            state.watching = board.player != "You";
+            // the cube decision is over once a board comes without it.
+            if !board.was_doubled && state.status.hints.take().is_some() {
+                updates_tx.send(Update::Status(state.status.clone()))?;
+            }
+
+            let score = score_header(&board, &mut state.race, config.theme);
+            set_score(state, Some(score), updates_tx)?;
+            state.board = Some(board.clone());
+            updates_tx.send(Update::Board(board))?;
+            if let Some(caption) = caption {
+                updates_tx.send(Update::AppendLine(caption))?;
+            }
+            return Ok(());
+        }
+    }
+
+    let kind = classify::classify(&ln);
+    for handler in state.handlers.iter() {
+        match handler(&ln, &kind) {
+            hooks::Action::Pass => {}
+            hooks::Action::Suppress => return Ok(()),
+            hooks::Action::Replace(replacement) => ln = replacement,
+            hooks::Action::Send(cmd) => send_fibs(conn, &cmd)?,
+        }
+    }
+
+    match kind {
+        classify::LineKind::Chat(kind, sender) => {
+            if state.quiet || state.muted.contains(&sender.to_lowercase()) {
+                return Ok(());
+            }
+            if kind == classify::ChatKind::Tell {
+                conn.lock()?.heard_from(&sender);
+                if state.tells.len() == config.tells {
+                    state.tells.pop_front();
+                }
+                notify(state, format!("tell from {}", sender), false, updates_tx)?;
+                if config.tells > 0 {
+                    let message = classify::message(&ln).unwrap_or_default();
+                    state.tells.push_back((sender, String::from(message), time::Instant::now()));
+                }
+            }
+        }
+        classify::LineKind::Invite(player) => {
+            if state.status.dnd {
+                send_fibs(conn, &format!("tell {} {}", player, config.dnd_message))?;
+                updates_tx.send(Update::AppendLine(format!("declined an invitation from {} (do not disturb)", player)))?;
+                return Ok(());
+            }
+        }
+        classify::LineKind::Ready(ready) => {
+            state.status.ready = Some(ready);
+            updates_tx.send(Update::Status(state.status.clone()))?;
+        }
+        classify::LineKind::MatchStart(_, money) => {
+            state.money = money;
+        }
+        classify::LineKind::Watching(player, opponent, length) => {
+            state.watching = true;
+            state.games.watch(&player);
+            // until the first board, with the score, comes.
+            if let Some(opponent) = opponent {
+                let length = length.map(|n| format!(" (to {})", n)).unwrap_or_default();
+                set_score(state, Some(format!("watching {} - {}{}", player, opponent, length)), updates_tx)?;
+            }
+        }
+        classify::LineKind::Double(_) => {
+            state.status.hints = Some(String::from(if state.money {
+                "doubled: accept, reject or beaver"
+            } else {
+                "doubled: accept or reject"
+            }));
+            updates_tx.send(Update::Status(state.status.clone()))?;
+        }
+        classify::LineKind::Beaver(_) => {
+            state.status.hints = Some(String::from("beavered: raccoon, or play on"));
+            updates_tx.send(Update::Status(state.status.clone()))?;
+        }
+        classify::LineKind::Raccoon(_) => {
+            state.status.hints = None;
+            updates_tx.send(Update::Status(state.status.clone()))?;
+        }
+        classify::LineKind::Shutdown(secs) => {
+            let text = match secs {
+                Some(secs) if secs < 60 => format!("FIBS shutting down in {} seconds", secs),
+                Some(secs) => format!("FIBS shutting down in {} minutes", secs / 60),
+                None => String::from("FIBS shutting down"),
+            };
+            let urgent = secs.map(|secs| secs <= SHUTDOWN_IMMINENT_SECS).unwrap_or(true);
+            updates_tx.send(Update::Banner(Some(Banner { text, urgent })))?;
+        }
+        classify::LineKind::ShutdownCancelled => {
+            updates_tx.send(Update::Banner(None))?;
+        }
+        classify::LineKind::Error(msg) => {
+            let ours = state.login_command
+                .as_ref()
+                .filter(|(_, at)| at.elapsed() < LOGIN_COMMAND_SPACING * 2);
+            if let Some((cmd, _)) = ours {
+                updates_tx.send(Update::AppendLine(format!("on-login command {:?} failed: {}", cmd, msg)))?;
+            }
+        }
+        classify::LineKind::ClipError(msg) => {
+            updates_tx.send(Update::ClipError(msg))?;
+            return Ok(());
+        }
+        classify::LineKind::Away(player, message) => {
+            // put it with the tell it answers, rather than as a bare server line.
+            let told = conn.lock()?.last_tell() == Some(player.to_lowercase().as_str());
+            if told {
+                updates_tx.send(Update::AppendLine(format!("{} is away and may not see your tell: {}", player, message)))?;
+                return Ok(());
+            }
+        }
+        classify::LineKind::Delivered(ref player) | classify::LineKind::Saved(ref player) => {
+            // the server only says who, so it's taken to answer the tell
+            // we sent last.
+            let told = conn.lock()?.last_tell() == Some(player.to_lowercase().as_str());
+            if told {
+                let ln = if matches!(kind, classify::LineKind::Delivered(_)) {
+                    format!("delivered to {}", player)
+                } else {
+                    format!("stored for {} (offline); they'll see it when they next log in", player)
+                };
+                updates_tx.send(Update::AppendLine(ln))?;
+                return Ok(());
+            }
+        }
+        classify::LineKind::Standing(rating, experience) => {
+            if let (Some(was), Some(now)) = (state.status.rating, rating) {
+                if (now - was).abs() >= 0.005 {
+                    notify(state, format!("rating {:.2} ({:+.2})", now, now - was), false, updates_tx)?;
+                }
+            }
+            // keep what we knew if this report was missing a part.
+            state.status.rating = rating.or(state.status.rating);
+            state.status.experience = experience.or(state.status.experience);
+            updates_tx.send(Update::Status(state.status.clone()))?;
+        }
+        classify::LineKind::Setting(name, value) if name == "boardstyle" => {
+            // only boardstyle 3 boards are ours to draw and follow; the others
+            // come as the server's own pictures, and what we had goes stale.
+            if value.parse() != Ok(BOARDSTYLE) {
+                state.board = None;
+                state.history.clear();
+                state.race.reset();
+                set_score(state, None, updates_tx)?;
+                updates_tx.send(Update::AppendLine(ln))?;
+                updates_tx.send(Update::AppendLine(format!(
+                    "boards are drawn by the server now; set boardstyle {} to have them followed here", BOARDSTYLE
+                )))?;
+                return Ok(());
+            }
+        }
+        classify::LineKind::MatchEnd => {
+            if let (Some(url), false) = (&config.webhook, state.watching) {
+                post_result(state, config, url, &ln, updates_tx)?;
+            }
+            set_score(state, None, updates_tx)?;
+        }
+        classify::LineKind::Setting(..) | classify::LineKind::Login(_) | classify::LineKind::Other => {}
+    }
+
+    updates_tx.send(Update::AppendLine(ln))?;
+    Ok(())
+}
+
+// the server has let us in: what was typed before now goes out, and the
+// on-login commands after it.
+fn logged_in(
+    state: &mut State,
+    config: &config::Config,
+    conn: &sync::Mutex<connection::Connection>,
+    updates_tx: &updates::UpdateSender,
+) -> Result<()> {
+    {
+        let mut conn = conn.lock()?;
+        if conn.pending() > 0 {
+            updates_tx.send(Update::AppendLine(
+                format!("sending {} line(s) typed before login", conn.pending())
+            ))?;
+        }
+        conn.flush()?;
+        // boards in any other style are only pictures to us.
+        conn.send(&format!("set boardstyle {}", BOARDSTYLE));
+    }
+    state.login_commands = config.on_login.iter().cloned().collect();
+    if state.reconnected {
+        state.reconnected = false;
+        let text = format!("reconnected to {}", state.status.server.clone().unwrap_or_default());
+        reconnect_notice(state, config, text, false, updates_tx)?;
+    }
+    Ok(())
+}
+
+// what's said and sent for `step` of logging in.
+fn login_step(
+    state: &mut State,
+    config: &config::Config,
+    step: login::Step,
+    recorder: &mut Option<replay::Recorder>,
+    conn: &sync::Mutex<connection::Connection>,
+    updates_tx: &updates::UpdateSender,
+) -> Result<()> {
+    match step {
+        login::Step::Greeted(greeting) => {
+            conn.lock()?.prompted();
+
+            let credentials = config.reconnect_credentials().filter(|_| state.reconnected);
+            state.relogin = credentials.is_some();
+            let update = match credentials {
+                Some((user, _)) if state.resuming => Update::AppendLine(format!("reconnected to FIBS, logging in as {}", user)),
+                None if state.resuming => Update::AppendLine(String::from("reconnected to FIBS, login: ")),
+                _ => Update::MOTD(login::motd(&greeting)),
+            };
+            updates_tx.send(update)?;
+            // below the MOTD, which would clear them away.
+            if !state.reconnected {
+                for warning in &config.warnings {
+                    updates_tx.send(Update::AppendLine(warning.clone()))?;
+                }
+            }
+            if let Some((user, _)) = credentials {
+                conn.lock()?.send(user);
+            }
+        }
+        login::Step::AskedPassword => {
+            conn.lock()?.prompted_for_password();
+            match config.reconnect_credentials().filter(|_| state.relogin) {
+                Some((_, password)) => conn.lock()?.send(password),
+                None => updates_tx.send(Update::AppendLine(String::from("password: ")))?,
+            }
+        }
+        login::Step::AskedAgain => {
+            // turned down; over to whoever's at the keyboard.
+            state.relogin = false;
+            conn.lock()?.prompted();
+            updates_tx.send(Update::AppendLine(String::from("login: ")))?;
+        }
+        login::Step::Outcome(login::Outcome::Incorrect, _) => {
+            updates_tx.send(Update::AppendLine(String::from("login incorrect; log in again at the prompt")))?;
+        }
+        login::Step::Outcome(login::Outcome::Welcome(name), ln) => {
+            let who = name.as_ref().map(|n| format!(" as {}", n)).unwrap_or_default();
+            updates_tx.send(Update::AppendLine(format!("logged in{}", who)))?;
+            state.status.user = name;
+            logged_in(state, config, conn, updates_tx)?;
+            if let Some(recorder) = recorder {
+                recorder.line(&ln)?;
+            }
+            handle_line(state, config, ln, conn, updates_tx)?;
+        }
+        login::Step::Line(ln) => updates_tx.send(Update::AppendLine(ln))?,
+    }
+    Ok(())
+}
+
+// sends the next on-login command once the last has had time to be answered,
+// so a long list doesn't flood the server.
+fn next_login_command(state: &mut State, conn: &sync::Mutex<connection::Connection>) -> Result<()> {
+    let due = state.login_command
+        .as_ref()
+        .map(|(_, at)| at.elapsed() >= LOGIN_COMMAND_SPACING)
+        .unwrap_or(true);
+    if !due {
+        return Ok(());
+    }
+
+    if let Some(cmd) = state.login_commands.pop_front() {
+        send_fibs(conn, &cmd)?;
+        state.login_command = Some((cmd, time::Instant::now()));
+    }
+    Ok(())
+}
+
+// the panic key: everything it's set to do, at once, or as much of it undone
+// as can be.
+fn go_safe(
+    state: &mut State,
+    config: &config::Config,
+    safe: bool,
+    conn: &sync::Mutex<connection::Connection>,
+    updates_tx: &updates::UpdateSender,
+) -> Result<()> {
+    let actions = config.panic;
+    state.safe = safe;
+    if actions.clear && safe {
+        updates_tx.send(Update::Clear)?;
+    }
+    if actions.away {
+        if safe {
+            send_fibs(conn, &format!("away {}", config.away_message))?;
+        } else {
+            send_fibs(conn, "back")?;
+        }
+        state.auto_away = false;
+    }
+    if actions.dnd {
+        state.status.dnd = safe;
+    }
+    if actions.quiet {
+        state.quiet = safe;
+    }
+
+    state.status.hints = safe.then(|| String::from("safe: press the panic key again to undo"));
+    updates_tx.send(Update::Status(state.status.clone()))?;
+    Ok(())
+}
+
+fn copy_or_show(what: &str, text: String, updates_tx: &updates::UpdateSender) -> Result<()> {
+    match clipboard::copy(&text) {
+        Ok(()) => {
+            updates_tx.send(Update::AppendLine(format!("{} copied to clipboard", what)))?;
+        }
+        Err(e) => {
+            updates_tx.send(Update::AppendLine(format!("{}; copy it from here:", e)))?;
+            updates_tx.send(Update::AppendLine(text))?;
+        }
+    }
+
+    Ok(())
+}
+
+// marks us away once idle for `after`, unless we're busy with a game the
+// config exempts, and back again as soon as there's activity.
+fn check_idle(
+    state: &mut State,
+    config: &config::Config,
+    after: time::Duration,
+    activity: &sync::Mutex<idle::Activity>,
+    conn: &sync::Mutex<connection::Connection>,
+    updates_tx: &updates::UpdateSender,
+) -> Result<()> {
+    if !state.login.logged_in() {
+        return Ok(());
+    }
+
+    let now = time::Instant::now();
+    let idle = activity.lock()?.idle_for(config.idle_reset, now) >= after;
+    let in_game = state.last_board_at
+        .map(|t| now.saturating_duration_since(t) < after)
+        .unwrap_or(false);
+    let exempt = in_game && if state.watching { config.idle_exempt.watching } else { config.idle_exempt.playing };
+
+    if idle && !exempt && !state.auto_away {
+        send_fibs(conn, &format!("away {}", config.away_message))?;
+        state.auto_away = true;
+        updates_tx.send(Update::AppendLine(format!("marked away after {} minutes idle", after.as_secs() / 60)))?;
+    } else if !idle && state.auto_away {
+        send_fibs(conn, "back")?;
+        state.auto_away = false;
+    }
+
+    Ok(())
+}
+
+// what's kept up to date by the clock rather than by what comes in: lines
+// held back for login, on-login commands, unanswered requests, notifications,
+// idleness and the connection on the status line.
+fn tick(
+    state: &mut State,
+    config: &config::Config,
+    conn: &sync::Mutex<connection::Connection>,
+    activity: &sync::Mutex<idle::Activity>,
+    updates_tx: &updates::UpdateSender,
+) -> Result<()> {
+    show_connection(state, updates_tx)?;
+
+    let pending = conn.lock()?.pending();
+    if pending != state.pending {
+        state.pending = pending;
+        updates_tx.send(Update::Pending(pending))?;
+    }
+
+    if state.login.logged_in() {
+        next_login_command(state, conn)?;
+    }
+
+    if let Some(timeout) = config.command_timeout {
+        let unanswered = conn.lock()?.unanswered(timeout, time::Instant::now());
+        for request in unanswered {
+            notify(state, format!("no response to {}", request), false, updates_tx)?;
+        }
+    }
+
+    if state.notifications.expire(time::Instant::now()) {
+        updates_tx.send(Update::Notifications(state.notifications.lines()))?;
+    }
+
+    if let Some(after) = config.auto_away {
+        check_idle(state, config, after, activity, conn, updates_tx)?;
+    }
+    Ok(())
+}
+
+fn send_history(state: &State, updates_tx: &updates::UpdateSender) -> Result<()> {
+    let view = state.viewing.and_then(|i| {
+        state.history.get(i).map(|board| HistoryView {
+            board: board.clone(),
+            index: i,
+            len: state.history.len(),
+        })
+    });
+    updates_tx.send(Update::History(view))
+}
+
+fn run_command(
+    state: &mut State,
+    config: &config::Config,
+    cmd: command::Command,
+    conn: &sync::Mutex<connection::Connection>,
+    updates_tx: &updates::UpdateSender,
+) -> Result<()> {
+    match (cmd, &state.board) {
+        (command::Command::CopyBoard, Some(board)) => {
+            copy_or_show("board", board.to_string(), updates_tx)?;
+        }
+        (command::Command::CopyGnubgId, Some(board)) => {
+            copy_or_show("position ID", gnubg::to_gnubg_id(board), updates_tx)?;
+        }
+        (command::Command::CopyBoard, None) | (command::Command::CopyGnubgId, None) => {
+            updates_tx.send(Update::AppendLine(String::from("no board to copy yet")))?;
+        }
+        // handled by the main loop, which owns the connection.
+        (command::Command::Reconnect, _) | (command::Command::Quit, _) => {}
+        (command::Command::Mute(player), _) => {
+            state.muted.insert(player.to_lowercase());
+            updates_tx.send(Update::AppendLine(format!("muted {}", player)))?;
+        }
+        (command::Command::Unmute(player), _) => {
+            if state.muted.remove(&player.to_lowercase()) {
+                updates_tx.send(Update::AppendLine(format!("unmuted {}", player)))?;
+            } else {
+                updates_tx.send(Update::AppendLine(format!("{} wasn't muted", player)))?;
+            }
+        }
+        // the status line changes once FIBS confirms, not before.
+        (command::Command::Ready, _) => {
+            send_fibs(conn, "toggle ready")?;
+        }
+        (command::Command::Dnd, _) => {
+            state.status.dnd = !state.status.dnd;
+            updates_tx.send(Update::Status(state.status.clone()))?;
+        }
+        (command::Command::HistoryBack, _) => {
+            let last = state.history.len().saturating_sub(1);
+            state.viewing = match state.viewing {
+                Some(i) => Some(i.saturating_sub(1)),
+                None if state.history.len() > 1 => Some(last - 1),
+                None => None,
+            };
+            send_history(state, updates_tx)?;
+        }
+        (command::Command::HistoryForward, _) => {
+            // stepping forward onto the newest board is back to live.
+            state.viewing = state.viewing
+                .map(|i| i + 1)
+                .filter(|i| *i + 1 < state.history.len());
+            send_history(state, updates_tx)?;
+        }
+        (command::Command::NextGame, _) => {
+            match state.games.next().cloned() {
+                Some(board) => {
+                    state.history.clear();
+                    state.race.reset();
+                    state.history.push_back(board.clone());
+                    if state.viewing.take().is_some() {
+                        updates_tx.send(Update::History(None))?;
+                    }
+                    state.money = board.match_length == UNLIMITED_MATCH;
+                    state.watching = board.player != "You";
+                    let score = score_header(&board, &mut state.race, config.theme);
+                    set_score(state, Some(score), updates_tx)?;
+                    state.board = Some(board.clone());
+                    updates_tx.send(Update::Board(board))?;
+                    show_games(state, updates_tx)?;
+                }
+                None => updates_tx.send(Update::AppendLine(String::from("no games to switch to")))?,
+            }
+        }
+        (command::Command::Color(player, name), _) => {
+            match colors::by_name(&name) {
+                Some(c) => {
+                    state.player_colors.insert(player.to_lowercase(), c);
+                    colors::save(&state.player_colors)?;
+                    updates_tx.send(Update::PlayerColor(player.to_lowercase(), c))?;
+                    updates_tx.send(Update::AppendLine(format!("{} is now {}", player, colors::NAMES[c as usize])))?;
+                }
+                None => {
+                    updates_tx.send(Update::AppendLine(
+                        format!("unknown color {:?}; try one of {}", name, colors::NAMES.join(", "))
+                    ))?;
+                }
+            }
+        }
+        (command::Command::Trace(None), _) => {
+            updates_tx.send(Update::AppendLine(format!("tracing {} to {}", trace::describe(), config.trace_file.display())))?;
+        }
+        (command::Command::Trace(Some((what, on))), _) => {
+            let categories = if what == "all" {
+                Ok(trace::Category::ALL.to_vec())
+            } else {
+                what.parse().map(|c| vec![c])
+            };
+            match categories {
+                Ok(categories) => {
+                    if on {
+                        trace_to_file(config)?;
+                    }
+                    for c in categories {
+                        trace::set(c, on);
+                    }
+                    updates_tx.send(Update::AppendLine(format!("tracing {}", trace::describe())))?;
+                }
+                Err(e) => updates_tx.send(Update::AppendLine(e.to_string()))?,
+            }
+        }
+        (command::Command::ClipNumbers, _) => {
+            state.clip_numbers = !state.clip_numbers;
+            updates_tx.send(Update::ClipNumbers(state.clip_numbers))?;
+        }
+        (command::Command::Mutes, _) => {
+            let mut muted: Vec<&str> = state.muted.iter().map(String::as_str).collect();
+            muted.sort_unstable();
+            let ln = if muted.is_empty() {
+                String::from("nobody is muted")
+            } else {
+                format!("muted: {}", muted.join(", "))
+            };
+            updates_tx.send(Update::AppendLine(ln))?;
+        }
+        (command::Command::Resolve, _) => {
+            let (servers, peer) = (config.servers.clone(), conn.lock()?.peer());
+            let updates_tx = updates_tx.clone();
+            // the lookups can take a while; the rest of the client needn't
+            // wait on them.
+            thread::spawn(move || -> Result<()> {
+                for (hostname, port) in servers {
+                    match network::resolve_all(&hostname, port) {
+                        Ok(addrs) => {
+                            updates_tx.send(Update::AppendLine(format!("{}:{} resolves to:", hostname, port)))?;
+                            for addr in addrs {
+                                let connected = if Some(addr) == peer { " (connected)" } else { "" };
+                                updates_tx.send(Update::AppendLine(format!("  {}{}", addr, connected)))?;
+                            }
+                        }
+                        Err(e) => updates_tx.send(Update::AppendLine(format!("{}:{}: {}", hostname, port, e)))?,
+                    }
+                }
+                if let Some(peer) = peer {
+                    updates_tx.send(Update::AppendLine(format!("connected to {}", peer)))?;
+                }
+                Ok(())
+            });
+        }
+        (command::Command::Rank, _) => {
+            let ln = state.status.standing().unwrap_or_else(|| String::from("no rating from the server yet"));
+            updates_tx.send(Update::AppendLine(ln))?;
+        }
+        (command::Command::Tell(player, message), _) => {
+            send_typed(conn, &format!("tell {} {}", player, message), updates_tx)?;
+        }
+        (command::Command::Reply(message), _) => {
+            let player = conn.lock()?.reply_to().map(String::from);
+            match player {
+                Some(player) => send_typed(conn, &format!("tell {} {}", player, message), updates_tx)?,
+                None => updates_tx.send(Update::AppendLine(String::from("nobody to reply to yet")))?,
+            }
+        }
+        (command::Command::Tells(None), _) if state.tells.is_empty() => {
+            updates_tx.send(Update::AppendLine(String::from("no tells yet")))?;
+        }
+        (command::Command::Tells(None), _) => {
+            let mut lines = vec![
+                String::from("-- TELLS: newest first, /tells <n> to reply to one, Esc to close --"),
+                String::new(),
+            ];
+            // wrapped to the box when shown.
+            lines.extend(state.tells.iter().rev().enumerate().map(|(i, (sender, message, at))| {
+                format!("{:>2}. {} ({}): {}", i + 1, sender, ago(at.elapsed()), message)
+            }));
+            updates_tx.send(Update::Popup(Some(lines)))?;
+        }
+        (command::Command::Tells(Some(n)), _) => {
+            let sender = n.checked_sub(1)
+                .and_then(|i| state.tells.iter().rev().nth(i))
+                .map(|(sender, _, _)| sender.clone());
+            match sender {
+                Some(sender) => {
+                    conn.lock()?.heard_from(&sender);
+                    updates_tx.send(Update::Popup(None))?;
+                    updates_tx.send(Update::AppendLine(format!("/r now replies to {}", sender)))?;
+                }
+                None => updates_tx.send(Update::AppendLine(format!("no tell {}; /tells lists them", n)))?,
+            }
+        }
+        (command::Command::DemoBoard, _) => {
+            let mut lines = vec![String::from("-- DEMO: the opening position, Esc to close --"), String::new()];
+            lines.extend(demo_board(config, MIN_VIEW_WIDTH as usize - 2, 20));
+            updates_tx.send(Update::Popup(Some(lines)))?;
+        }
+        (command::Command::ExportWho(path, format), _) => {
+            let ln = match who::Format::pick(format.as_deref(), &path) {
+                Some(format) => {
+                    let players: Vec<&who::WhoInfo> = state.who.values().collect();
+                    match fs::write(&path, who::export(&players, format)) {
+                        Ok(()) => format!("exported {} player(s) to {}", players.len(), path),
+                        Err(e) => format!("couldn't export the WHO list to {}: {}", path, e),
+                    }
+                }
+                None => String::from("/exportwho <file> [csv|json]"),
+            };
+            updates_tx.send(Update::AppendLine(ln))?;
+        }
+        (command::Command::Panic, _) => {
+            go_safe(state, config, !state.safe, conn, updates_tx)?;
+        }
+        (command::Command::Unknown(s), _) => {
+            updates_tx.send(Update::AppendLine(format!("unknown command: /{}", s)))?;
+        }
+    }
+
+    Ok(())
+}
+
+// the opening position as the configured zoom and overflow draw it, for
+// trying settings out without a server: the board fitted to `width`, so it
+// fits any box that wide, and its description to be wrapped as it's shown.
+fn demo_board(config: &config::Config, width: usize, height: usize) -> Vec<String> {
+    let opening = board::Board::opening();
+    let mut lines = render::board(&opening, render::fit(config.zoom, width, height), config.overflow, config.labels);
+    lines.push(String::new());
+    lines.push(opening.describe());
+    lines
+}
+
+// e.g. "just now", "5m ago", "2h ago".
+fn ago(elapsed: time::Duration) -> String {
+    match elapsed.as_secs() {
+        0..=59 => String::from("just now"),
+        secs @ 60..=3599 => format!("{}m ago", secs / 60),
+        secs => format!("{}h ago", secs / 3600),
+    }
+}
+
+// opens the trace file the first time anything is traced.
+fn trace_to_file(config: &config::Config) -> Result<()> {
+    if !trace::has_sink() {
+        let file = fs::OpenOptions::new().create(true).append(true).open(&config.trace_file)?;
+        trace::to(Box::new(file), config.trace_format.parse()?);
+    }
+    Ok(())
+}
+
+// how long to wait before our `attempt`th try at reconnecting; the first
+// is straight away.
+fn reconnect_delay(attempt: u32) -> time::Duration {
+    match attempt {
+        0 | 1 => time::Duration::ZERO,
+        n => RECONNECT_BACKOFF.saturating_mul(1 << (n - 2).min(16)).min(RECONNECT_BACKOFF_CAP),
+    }
+}
+
+fn main() -> Result<()> {
+    let config = config::Config::from_env();
+    // better now than the first time something's traced, mid-game.
+    config.trace_format.parse::<trace::Format>()?;
+
+    if env::args().skip(1).any(|a| a == "--check") {
+        print!("{}", terminal::capabilities());
+        println!();
+        println!("using:   {} boxes, color {}, mouse {}",
+            config.theme.name,
+            if config.color { "on" } else { "off" },
+            if config.mouse { "on" } else { "off" });
+        return Ok(());
+    }
+    if let Some(recording) = &config.replay {
+        return replay::run(&config, recording);
+    }
+    if env::args().skip(1).any(|a| a == "--demo-board") {
+        for ln in demo_board(&config, MIN_VIEW_WIDTH as usize - 2, usize::MAX) {
+            println!("{}", ln);
+        }
+        return Ok(());
+    }
+
+    if !config.trace.is_empty() {
+        trace_to_file(&config)?;
+        for c in &config.trace {
+            trace::set(*c, true);
+        }
+    }
+
+    if config.monitor {
+        return monitor::run(&config, network::connect_any(&config.servers, 0, config.connect_timeout)?.0);
+    }
+
+    // connected before raw mode, so a server that never answers leaves the
+    // terminal as it was, with the error below.
+    let connected = network::connect_any(&config.servers, 0, config.connect_timeout)?;
+    run(&config, connected, io::stdin())
+}
+
+// the session with the server `connected` to, the TCP stream and which of
+// the configured servers it is, until it's over. the accessible front-end
+// reads its lines from `stdin`.
+fn run<R: io::Read + Send + 'static>(config: &config::Config, connected: (net::TcpStream, usize), stdin: R) -> Result<()> {
+    let servers = &config.servers;
+    let (mut tcp, mut server) = connected;
+    // set before the clones below, so they share it; see network::keepalive.
+    if let Some(idle) = config.keepalive {
+        network::keepalive(&tcp, idle)?;
+    }
+
+    // from here on the terminal is put back however main ends.
+    let raw = if config.accessible {
+        None
+    } else {
+        Some(terminal::RawModeGuard::new()?)
+    };
+
+    let reading_tcp = tcp.try_clone()?;
+    let conn = sync::Arc::new(sync::Mutex::new(connection::Connection::new(tcp.try_clone()?)));
+
+    let (tcp_tx, mut tcp_rx) = sync::mpsc::sync_channel::<u8>(4096);
+    let (commands_tx, commands_rx) = sync::mpsc::channel::<command::Command>();
+    let mut state = State {
+        login: login::Login::new(),
+        board: None,
+        disconnected_at: None,
+        reconnect: None,
+        reconnected: false,
+        resuming: false,
+        relogin: false,
+        muted: config.mutes.iter().map(|p| p.to_lowercase()).collect(),
+        status: Status {
+            server: Some(format!("{}:{}", servers[server].0, servers[server].1)),
+            ..Status::default()
+        },
+        history: collections::VecDeque::new(),
+        viewing: None,
+        race: pips::Trend::new(),
+        money: false,
+        last_board_at: None,
+        watching: false,
+        games: games::Games::new(config.watch_limit),
+        auto_away: false,
+        clip_numbers: config.clip_numbers,
+        player_colors: colors::load(),
+        pending: 0,
+        login_commands: collections::VecDeque::new(),
+        login_command: None,
+        safe: false,
+        quiet: false,
+        score: None,
+        notifications: notify::Notifications::new(config.notify_timeout, config.notify_max),
+        tells: collections::VecDeque::new(),
+        who: collections::BTreeMap::new(),
+        handlers: hooks::handlers(),
+    };
+    let activity = sync::Arc::new(sync::Mutex::new(idle::Activity::new()));
+
+    // the line coming in, once we're logged in.
+    let mut buf = vec::Vec::with_capacity(4096);
+
+    let mut fibs_handle = network::spawn_fibs_thread(reading_tcp, tcp_tx)?;
+    let (updates_tx, updates_rx) = updates::channel(config.update_queue, config.update_policy);
+    let (tui_handle, input_handle) = if config.accessible {
+        let tui_handle = plain::spawn_plain_thread(updates_rx, config.clip_numbers)?;
+        let input_handle = plain::spawn_line_input_thread(stdin, conn.clone(), activity.clone(), updates_tx.clone(), commands_tx)?;
+        (tui_handle, input_handle)
+    } else {
+        let tui_ready = sync::Arc::new(sync::Barrier::new(2));
+        let tui_handle = spawn_tui_thread(updates_rx, tui_ready.clone(), config, state.player_colors.clone())?;
+        winch::spawn(updates_tx.clone())?;
+        let input_handle = spawn_input_thread(
+            keys::Stdin::default(),
+            conn.clone(),
+            activity.clone(),
+            updates_tx.clone(),
+            commands_tx,
+            tui_ready,
+            config,
+        )?;
+        (tui_handle, input_handle)
+    };
+    updates_tx.send(Update::Status(state.status.clone()))?;
+
+    let mut recorder = config.record.as_deref().map(replay::Recorder::create).transpose()?;
+    // the connection dropped and the policy is to let the client go with it.
+    let mut hung_up = false;
+    // the server closed on us before we got to log in, and what it said.
+    let mut refused: Option<Vec<String>> = None;
+    // asked to quit, so everything winds down rather than going with the
+    // process.
+    let mut quit = false;
+
+    let mut next_tick = time::Instant::now();
+
+    loop {
+        let now = time::Instant::now();
+        if now >= next_tick {
+            tick(&mut state, config, &conn, &activity, &updates_tx)?;
+            next_tick = now + TICK;
+        }
+        let due = state.reconnect.map(|(_, at)| at <= time::Instant::now()).unwrap_or(false);
+        let cmd = if due { Ok(command::Command::Reconnect) } else { commands_rx.try_recv() };
+        match cmd {
+            Ok(command::Command::Quit) => {
+                quit = true;
+                break;
+            }
+            Ok(command::Command::Reconnect) => {
+                // one of our own attempts, or /reconnect starting over.
+                let attempt = state.reconnect.take().map(|(n, _)| n);
+                if let Some(n) = attempt {
+                    updates_tx.send(Update::AppendLine(format!("Reconnecting (attempt {} of {})…", n, config.reconnect_attempts)))?;
+                }
+
+                // within the window we pick up where we left off; past it, start clean.
+                let stale = state.disconnected_at
+                    .map(|t| t.elapsed() > config.reconnect_window)
+                    .unwrap_or(false);
+                if stale {
+                    state.board = None;
+                    set_score(&mut state, None, &updates_tx)?;
+                    updates_tx.send(Update::Clear)?;
+                    updates_tx.send(Update::Banner(None))?;
+                }
+
+                // once round the servers: keys and ticks wait while we
+                // try, and the backoff between attempts is our own.
+                let connected = match network::connect_round(servers, server, config.connect_timeout) {
+                    Ok(connected) => connected,
+                    Err(e) => {
+                        let next = attempt.map(|n| n + 1).filter(|n| *n <= config.reconnect_attempts);
+                        let ln = match next {
+                            Some(n) => {
+                                let delay = reconnect_delay(n);
+                                state.reconnect = Some((n, time::Instant::now() + delay));
+                                format!("{}; trying again in {}s", e, delay.as_secs())
+                            }
+                            None => format!("{}; /reconnect to try again", e),
+                        };
+                        updates_tx.send(Update::AppendLine(ln))?;
+                        if attempt.is_some() && next.is_none() {
+                            let text = format!("couldn't reconnect after {} attempts", config.reconnect_attempts);
+                            reconnect_notice(&mut state, config, text, true, &updates_tx)?;
+                        }
+                        continue;
+                    }
+                };
+                tcp.shutdown(net::Shutdown::Both).ok();
+                let old_handle = fibs_handle;
+
+                (tcp, server) = connected;
+                if let Some(idle) = config.keepalive {
+                    network::keepalive(&tcp, idle)?;
+                }
+                state.status.server = Some(format!("{}:{}", servers[server].0, servers[server].1));
+                updates_tx.send(Update::Status(state.status.clone()))?;
+                conn.lock()?.replace(tcp.try_clone()?);
+                let (tcp_tx, new_tcp_rx) = sync::mpsc::sync_channel::<u8>(4096);
+                tcp_rx = new_tcp_rx;
+                fibs_handle = network::spawn_fibs_thread(tcp.try_clone()?, tcp_tx)?;
+                old_handle.join().ok();
+
+                state.login.reset();
+                state.reconnected = true;
+                state.resuming = !stale;
+                state.disconnected_at = None;
+                buf.clear();
+            }
+            Ok(cmd) => {
+                run_command(&mut state, config, cmd, &conn, &updates_tx)?;
+            }
+            Err(sync::mpsc::TryRecvError::Empty) => {}
+            // the input's run out, as at the end of a piped stdin, without
+            // a /quit; that's the user done all the same.
+            Err(sync::mpsc::TryRecvError::Disconnected) => {
+                quit = true;
+                break;
+            }
+        }
+
+        match tcp_rx.recv_timeout(TICK) {
+            Ok(b) if state.login.logged_in() => {
+                if b == 0x0a {
+                    let ln = String::from_utf8_lossy(buf.as_slice());
+                    let ln = ln.trim_end_matches('\r');
+                    let ln = String::from(if config.trim { text::trim_trailing(ln) } else { ln });
+                    if let Some(recorder) = &mut recorder {
+                        recorder.line(&ln)?;
+                    }
+                    handle_line(&mut state, config, ln, &conn, &updates_tx)?;
+                    buf.clear();
+                } else {
+                    buf.push(b);
+                }
+            }
+            Ok(b) => {
+                if let Some(step) = state.login.feed(b, time::Instant::now()) {
+                    login_step(&mut state, config, step, &mut recorder, &conn, &updates_tx)?;
+                }
+            }
+            Err(sync::mpsc::RecvTimeoutError::Timeout) => {
+                // prompts only count once the server stops and waits for us.
+                if let Some(step) = state.login.settled(time::Instant::now()) {
+                    login_step(&mut state, config, step, &mut recorder, &conn, &updates_tx)?;
+                }
+            }
+            Err(sync::mpsc::RecvTimeoutError::Disconnected) => {
+                // nothing to wait on until we're reconnected, or a command
+                // comes.
+                thread::sleep(TICK);
+                if state.disconnected_at.is_none() {
+                    state.disconnected_at = Some(time::Instant::now());
+                    if !state.login.logged_in() {
+                        // turned away before we could log in; straight back
+                        // in would most likely be turned away again.
+                        let reason = login::closed_during_login(&state.login.received());
+                        if !state.resuming {
+                            refused = Some(reason);
+                            break;
+                        }
+                        for ln in reason {
+                            updates_tx.send(Update::AppendLine(ln))?;
+                        }
+                        updates_tx.send(Update::AppendLine(String::from("/reconnect to try again")))?;
+                        continue;
+                    }
+                    // a match of our own is in progress while there's a score
+                    // up for a game we're not just watching.
+                    let in_match = state.score.is_some() && !state.watching;
+                    if config.reconnect == config::Reconnect::InMatch && !in_match {
+                        hung_up = true;
+                        break;
+                    }
+                    let ln = if config.reconnect_attempts > 0 {
+                        state.reconnect = Some((1, time::Instant::now()));
+                        "disconnected from FIBS, reconnecting; anything typed meanwhile is sent after login"
+                    } else {
+                        "disconnected from FIBS; /reconnect to try again"
+                    };
+                    updates_tx.send(Update::AppendLine(String::from(ln)))?;
+                }
+            }
+        }
+    }
+
+    tcp.shutdown(net::Shutdown::Both).ok();
+    let mut stdout = io::stdout();
+    // the front-end drawing what's left before it goes, not after the
+    // terminal's been put back. It may already be gone.
+    // joined, or the handle to join later.
+    let tui = if quit {
+        updates_tx.send(Update::Quit).ok();
+        Ok(tui_handle.join())
+    } else {
+        Err(tui_handle)
+    };
+    if let Some(raw) = &raw {
+        raw.suspend()?;
+        write!(stdout, "{}{}", termion::clear::All, termion::cursor::Goto(1, 1))?;
+        if let (config::OnExit::Summary, Some(board)) = (config.on_exit, &state.board) {
+            writeln!(stdout, "{}", board.describe())?;
+        }
+        stdout.flush()?;
+    }
+    if let Some(reason) = &refused {
+        for ln in reason {
+            writeln!(stdout, "{}", ln)?;
+        }
+        stdout.flush()?;
+    } else if hung_up {
+        writeln!(stdout, "disconnected from FIBS")?;
+        stdout.flush()?;
+    }
+
+    fibs_handle.join().unwrap_or_else(|_| {
+        write!(stdout, "fibs thread panicked")?;
+        stdout.flush()?;
+        Ok(())
+    })?;
+
+    // the input and TUI threads only finish when the user is done; they go
+    // with the process instead.
+    if hung_up || refused.is_some() {
+        return Ok(());
+    }
+
+    tui.unwrap_or_else(thread::JoinHandle::join).unwrap_or_else(|_| {
+        write!(stdout, "tui thread panicked")?;
+        stdout.flush()?;
+        Ok(())
     })?;
 
     input_handle.join().unwrap_or_else(|_| {
@@ -423,3 +2263,238 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use termion::event::Key;
+
+    #[test]
+    fn reconnect_backoff() {
+        let secs: Vec<u64> = (1..=8).map(|n| reconnect_delay(n).as_secs()).collect();
+        assert_eq!(secs, vec![0, 1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(reconnect_delay(100), RECONNECT_BACKOFF_CAP);
+    }
+
+    #[test]
+    fn frame_fits() {
+        assert!(!too_small(80, 24));
+        assert!(!too_small(MIN_COLS, MIN_ROWS));
+        assert!(too_small(MIN_COLS - 1, 24));
+        assert!(too_small(80, MIN_ROWS - 1));
+        assert_eq!(fibs_box_height(MIN_ROWS), 3);
+
+        // a box squeezed to a row still has a history to not draw.
+        let view = HistoryView { board: board::Board::opening(), index: 0, len: 1 };
+        let lines = history_lines(&view, fibs_box_width(MIN_COLS) - 2, fibs_box_height(1), render::MIN_ZOOM, render::Overflow::Count, render::Labels::Standard);
+        assert_eq!(lines.len(), 1);
+    }
+
+    #[test]
+    fn status_bar_shows_the_connection() {
+        let mut status = Status {
+            connection: Some("connecting"),
+            server: Some(String::from("fibs.com:4321")),
+            ..Status::default()
+        };
+        assert_eq!(status.to_string(), "connecting | fibs.com:4321 | ready? | dnd off");
+
+        status.connection = Some("logged in");
+        status.user = Some(String::from("alice"));
+        status.online = Some(112);
+        status.ready = Some(true);
+        assert_eq!(status.to_string(), "logged in | alice@fibs.com:4321 | 112 online | ready | dnd off");
+    }
+
+    #[test]
+    fn frame_laid_out_by_size() {
+        // at the least, and until there's room for notifications too.
+        assert_eq!(fibs_box_width(MIN_COLS), 73);
+        assert_eq!(fibs_box_width(80), 73);
+        assert_eq!(fibs_box_width(108), 73);
+        assert_eq!(fibs_box_width(120), 85);
+        assert_eq!(fibs_box_width(500), MAX_VIEW_WIDTH as usize);
+        assert_eq!(fibs_box_width(0), 73);
+
+        for cols in [90, 120, 200, 500] {
+            let column = notify_column(fibs_box_width(cols));
+            assert!(column + NOTIFY_MIN_WIDTH <= cols, "{} columns", cols);
+        }
+        // just past the box's right border, where they always were.
+        assert_eq!(notify_column(fibs_box_width(80)), 78);
+
+        assert_eq!(fibs_box_height(24), 15);
+        assert_eq!(fibs_box_height(60), 51);
+    }
+
+    #[test]
+    fn only_the_box_waits_for_a_frame() {
+        assert!(framed(&Update::AppendLine(String::from("alice says: hi"))));
+        assert!(framed(&Update::Status(Status::default())));
+        // drawn as they come, and not over the whole screen.
+        assert!(!framed(&Update::Input(String::from("hi"), 2)));
+        assert!(!framed(&Update::Score(None)));
+        assert!(!framed(&Update::Resize(80, 24)));
+    }
+
+    #[test]
+    fn motd_escapes_filtered() {
+        let mut buffer = scrollback::Scrollback::new();
+        buffer.push_verbatim(String::from("\x1b[2J\x1b]0;FIBS\x07\x1b[1mWelcome\x07"));
+        let window = scrollback::bottom(buffer.rows(71), 20);
+        let colors = collections::HashMap::new();
+
+        assert_eq!(fibs_window(&buffer, window, 71, false, None, &who::Grading::default()), vec!["Welcome"]);
+        assert_eq!(fibs_window(&buffer, window, 71, false, Some(&colors), &who::Grading::default()), vec!["\x1b[1mWelcome\x1b[0m"]);
+    }
+
+    #[test]
+    fn window_trails_the_buffer() {
+        let height = 20;
+        let mut buffer = scrollback::Scrollback::new();
+        let mut window = scrollback::bottom(0, height);
+        for i in 0..300usize {
+            let before = buffer.rows(71);
+            buffer.push(format!("line {}", i));
+            window = scrollback::followed(window, before, buffer.rows(71));
+
+            let shown = fibs_window(&buffer, window, 71, false, None, &who::Grading::default());
+            let first = (i + 1).saturating_sub(height);
+            let trailing: Vec<String> = (first..=i).map(|n| format!("line {}", n)).collect();
+            assert_eq!(shown, trailing);
+        }
+    }
+
+    // without a clipboard to copy to, the board's shown to copy by hand.
+    #[cfg(not(feature = "clipboard"))]
+    #[test]
+    fn copyboard_shown_without_a_clipboard() {
+        let (updates_tx, mut updates_rx) = updates::channel(None, updates::QueuePolicy::Block);
+        let board = board::Board::opening();
+        copy_or_show("board", board.to_string(), &updates_tx).unwrap();
+
+        assert!(matches!(updates_rx.recv(), Ok(Update::AppendLine(ln)) if ln.ends_with("copy it from here:")));
+        assert!(matches!(updates_rx.recv(), Ok(Update::AppendLine(ln)) if ln == board.to_string() && ln.starts_with("board:")));
+    }
+
+    #[test]
+    fn popups_wrapped_to_the_box() {
+        let lines = vec![String::from("  |  drawn  |"), "word ".repeat(20)];
+        let rows = popup_rows(&lines, 40, 30);
+        assert_eq!(rows[0], "  |  drawn  |");
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|row| row.chars().count() <= 40));
+        assert_eq!(popup_rows(&lines, 40, 2).len(), 2);
+        // the whole of it in a box wide enough.
+        assert_eq!(popup_rows(&lines, fibs_box_width(200) - 2, 30).len(), 2);
+    }
+
+    #[test]
+    fn scripted_keys() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tcp = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let conn = sync::Arc::new(sync::Mutex::new(connection::Connection::new(tcp)));
+        let activity = sync::Arc::new(sync::Mutex::new(idle::Activity::new()));
+        let (updates_tx, _updates_rx) = updates::channel(None, updates::QueuePolicy::Block);
+        let (commands_tx, commands_rx) = sync::mpsc::channel();
+
+        let mut script = vec![Key::F(2)];
+        script.extend("/dnd\nwho\n".chars().map(Key::Char));
+        let keys = keys::Scripted::new(script);
+        let ready = sync::Arc::new(sync::Barrier::new(1));
+        spawn_input_thread(keys, conn.clone(), activity, updates_tx, commands_tx, ready, &config::Config::from_lookup(&|_| None))
+            .unwrap()
+            .join()
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(commands_rx.try_recv(), Ok(command::Command::Ready)));
+        assert!(matches!(commands_rx.try_recv(), Ok(command::Command::Dnd)));
+        // not logged in, so "who" waits.
+        assert_eq!(conn.lock().unwrap().pending(), 1);
+    }
+
+    #[test]
+    fn ctrl_c_quits() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tcp = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let conn = sync::Arc::new(sync::Mutex::new(connection::Connection::new(tcp)));
+        let activity = sync::Arc::new(sync::Mutex::new(idle::Activity::new()));
+        let (updates_tx, _updates_rx) = updates::channel(None, updates::QueuePolicy::Block);
+        let (commands_tx, commands_rx) = sync::mpsc::channel();
+
+        // nothing after Ctrl-C is read.
+        let keys = keys::Scripted::new(vec![Key::Ctrl('c'), Key::F(2)]);
+        let ready = sync::Arc::new(sync::Barrier::new(1));
+        spawn_input_thread(keys, conn, activity, updates_tx, commands_tx, ready, &config::Config::from_lookup(&|_| None))
+            .unwrap()
+            .join()
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(commands_rx.try_recv(), Ok(command::Command::Quit)));
+        assert!(commands_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn session_over_when_input_runs_out() {
+        let (port, server) = fibsterm::mock::serve(fibsterm::mock::Chunks::Whole, None, |mut fibs| {
+            fibs.send("login: ");
+            // until the client hangs up.
+            fibs.line();
+        });
+        let config = config::Config::from_lookup(&|name| (name == "FIBS_ACCESSIBLE").then(|| String::from("1")));
+        let tcp = net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+
+        // stdin at its end, as after Ctrl-D at a line prompt, with no /quit.
+        let (done_tx, done_rx) = sync::mpsc::channel();
+        thread::spawn(move || done_tx.send(run(&config, (tcp, 0), io::empty()).is_ok()));
+        assert_eq!(done_rx.recv_timeout(time::Duration::from_secs(10)), Ok(true));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn clip_login_at_the_login_prompt() {
+        let (stdin, mut typed) = std::os::unix::net::UnixStream::pair().unwrap();
+        let (port, server) = fibsterm::mock::serve(fibsterm::mock::Chunks::Every(3), Some(fibsterm::mock::SHORT_DELAY), move |mut fibs| {
+            fibs.send("\r\nWelcome to FIBS\r\nlogin: ");
+            // typed once the prompt's been shown.
+            thread::sleep(time::Duration::from_millis(500));
+            typed.write_all(b"login fibsterm 1008 alice secret\n").unwrap();
+            assert_eq!(fibs.line(), "login fibsterm 1008 alice secret");
+            // no password prompt: straight to the welcome.
+            fibs.send("\r\n1 alice 1041253132 192.168.1.30\r\n3\r\n+--------------+\r\n4\r\n");
+            assert_eq!(fibs.line(), format!("set boardstyle {}", BOARDSTYLE));
+            drop(typed);
+            fibs.line();
+        });
+        let config = config::Config::from_lookup(&|name| (name == "FIBS_ACCESSIBLE").then(|| String::from("1")));
+        let tcp = net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+
+        let (done_tx, done_rx) = sync::mpsc::channel();
+        thread::spawn(move || done_tx.send(run(&config, (tcp, 0), stdin).is_ok()));
+        assert_eq!(done_rx.recv_timeout(time::Duration::from_secs(10)), Ok(true));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn keys_wait_for_the_tui() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tcp = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let conn = sync::Arc::new(sync::Mutex::new(connection::Connection::new(tcp)));
+        let activity = sync::Arc::new(sync::Mutex::new(idle::Activity::new()));
+        let (updates_tx, _updates_rx) = updates::channel(None, updates::QueuePolicy::Block);
+        let (commands_tx, commands_rx) = sync::mpsc::channel();
+        let ready = sync::Arc::new(sync::Barrier::new(2));
+
+        let keys = keys::Scripted::new(vec![Key::F(2)]);
+        let input = spawn_input_thread(keys, conn, activity, updates_tx, commands_tx, ready.clone(), &config::Config::from_lookup(&|_| None)).unwrap();
+
+        thread::sleep(time::Duration::from_millis(50));
+        assert!(commands_rx.try_recv().is_err());
+
+        ready.wait();
+        input.join().unwrap().unwrap();
+        assert!(matches!(commands_rx.try_recv(), Ok(command::Command::Ready)));
+    }
+}
@@ -0,0 +1,85 @@
+//! Spotting a few fixed byte strings in a stream read a byte at a time, for
+//! prompts and the like, without caring where the reads split them.
+
+use std::collections;
+
+/// Watches a stream for any of its targets, each with a label to say which
+/// it was. A target that overlaps itself or another, as "lolog" does
+/// "log", is still found.
+pub struct PromptMatcher<T> {
+    targets: Vec<(&'static [u8], T)>,
+    // the last bytes fed, as many as the longest target.
+    recent: collections::VecDeque<u8>,
+    longest: usize,
+}
+
+impl<T: Copy> PromptMatcher<T> {
+    pub fn new(targets: Vec<(&'static [u8], T)>) -> PromptMatcher<T> {
+        let longest = targets.iter().map(|(t, _)| t.len()).max().unwrap_or(0);
+        PromptMatcher { targets, recent: collections::VecDeque::with_capacity(longest), longest }
+    }
+
+    /// Takes the next byte, returning the label and length of the target it
+    /// completes, if any; the first given, if it completes more than one.
+    pub fn feed(&mut self, b: u8) -> Option<(T, usize)> {
+        if self.longest == 0 {
+            return None;
+        }
+        if self.recent.len() == self.longest {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(b);
+
+        self.targets
+            .iter()
+            .find(|(target, _)| {
+                target.len() <= self.recent.len() && self.recent.iter().skip(self.recent.len() - target.len()).eq(target.iter())
+            })
+            .map(|(target, label)| (*label, target.len()))
+    }
+
+    /// Forgets what's been fed, so nothing before carries into a match.
+    pub fn reset(&mut self) {
+        self.recent.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(matcher: &mut PromptMatcher<u8>, s: &str) -> Vec<u8> {
+        s.bytes().filter_map(|b| matcher.feed(b)).map(|(label, _)| label).collect()
+    }
+
+    #[test]
+    fn finds_each_target() {
+        let mut matcher = PromptMatcher::new(vec![(b"login: ".as_slice(), 1), (b"password: ".as_slice(), 2)]);
+
+        assert_eq!(feed_all(&mut matcher, "Welcome\r\nlogin: "), vec![1]);
+        assert_eq!(feed_all(&mut matcher, "alice\r\npassword: "), vec![2]);
+        assert_eq!(feed_all(&mut matcher, "login:password "), vec![]);
+    }
+
+    #[test]
+    fn interleaved_partial_matches() {
+        let mut matcher = PromptMatcher::new(vec![(b"login: ".as_slice(), 1), (b"password: ".as_slice(), 2)]);
+
+        // each false start is also the start of the real thing.
+        assert_eq!(feed_all(&mut matcher, "logilogin: "), vec![1]);
+        assert_eq!(feed_all(&mut matcher, "passpaslopassword: "), vec![2]);
+        assert_eq!(feed_all(&mut matcher, "login: password: "), vec![1, 2]);
+    }
+
+    #[test]
+    fn split_between_feeds_and_reset() {
+        let mut matcher = PromptMatcher::new(vec![(b"login: ".as_slice(), 1)]);
+
+        assert_eq!(feed_all(&mut matcher, "lo"), vec![]);
+        assert_eq!(feed_all(&mut matcher, "gin: "), vec![1]);
+
+        feed_all(&mut matcher, "log");
+        matcher.reset();
+        assert_eq!(feed_all(&mut matcher, "in: "), vec![]);
+    }
+}
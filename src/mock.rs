@@ -0,0 +1,267 @@
+//! A scripted FIBS for tests, which can break what it sends into pieces.
+//!
+//! Reads off a socket come in whatever pieces the network made of the
+//! stream, so a CLIP line, a UTF-8 character or a login prompt can be split
+//! anywhere, and some bugs only show with one particular split. `serve`
+//! plays the server on a local port, and everything it sends goes out as
+//! `Chunks` says, flushed piece by piece with `delay` between pieces so
+//! they arrive as separate reads:
+//!
+//! ```no_run
+//! use fibsterm::{client, mock};
+//!
+//! let (port, server) = mock::serve(mock::Chunks::Every(1), Some(mock::SHORT_DELAY), |mut fibs| {
+//!     fibs.send("login: ");
+//!     assert_eq!(fibs.line(), "alice");
+//!     fibs.send("password: ");
+//!     assert_eq!(fibs.line(), "secret");
+//!     fibs.send("\r\n** User alice authenticated.\r\n12 bob héllo\r\n");
+//! });
+//! let (_client, events) = client::Client::connect("127.0.0.1", port, "alice", "secret").unwrap();
+//! // ... what came through `events` ...
+//! server.join().unwrap();
+//! ```
+//!
+//! Try a test against `Chunks::Every(1)`, the worst case for anything that
+//! carries state between reads, and a few seeds of `Chunks::Random`. The
+//! delay matters where timing does, as for the login prompts, which only
+//! count once the server has been quiet for a quarter of a second: keep it
+//! well under that. Without a socket, `ChunkedReader` hands any `Read` over
+//! in the same pieces.
+//!
+//! Only built for tests, or with the `mock` feature: add fibsterm to
+//! `[dev-dependencies]` with it, so it stays out of release builds.
+
+use std::{io, net, thread, time};
+use std::io::prelude::*;
+
+use crate::telnet;
+
+/// Long enough between pieces that each is read on its own, short enough
+/// that nothing waiting on the server to go quiet thinks it has.
+pub const SHORT_DELAY: time::Duration = time::Duration::from_millis(5);
+
+/// How a stream is broken up.
+#[derive(Clone, Debug)]
+pub enum Chunks {
+    /// As it's given.
+    Whole,
+    /// Into pieces this long.
+    Every(usize),
+    /// Into pieces of these lengths, over and over.
+    Sizes(Vec<usize>),
+    /// Into pieces of 1 to `max` bytes, the same for the same `seed`.
+    Random { seed: u64, max: usize },
+}
+
+// the length of each piece in turn.
+struct Splitter {
+    chunks: Chunks,
+    next: usize,
+    state: u64,
+}
+
+impl Splitter {
+    fn new(chunks: Chunks) -> Splitter {
+        let state = match chunks {
+            // xorshift never leaves 0.
+            Chunks::Random { seed, .. } => seed.max(1),
+            _ => 0,
+        };
+        Splitter { chunks, next: 0, state }
+    }
+
+    // the next piece's length, of `left` bytes still to go.
+    fn take(&mut self, left: usize) -> usize {
+        let n = match &self.chunks {
+            Chunks::Whole => left,
+            Chunks::Every(n) => *n,
+            Chunks::Sizes(sizes) if sizes.is_empty() => left,
+            Chunks::Sizes(sizes) => {
+                let n = sizes[self.next % sizes.len()];
+                self.next += 1;
+                n
+            }
+            Chunks::Random { max, .. } => {
+                self.state ^= self.state << 13;
+                self.state ^= self.state >> 7;
+                self.state ^= self.state << 17;
+                1 + (self.state % (*max).max(1) as u64) as usize
+            }
+        };
+        n.clamp(1, left.max(1))
+    }
+}
+
+/// Writes to `inner` a piece at a time, flushing each and waiting `delay`
+/// before the next.
+pub struct ChunkedWriter<W> {
+    inner: W,
+    splitter: Splitter,
+    delay: Option<time::Duration>,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    pub fn new(inner: W, chunks: Chunks, delay: Option<time::Duration>) -> ChunkedWriter<W> {
+        ChunkedWriter { inner, splitter: Splitter::new(chunks), delay }
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    // one piece per call; `write_all` goes round for the rest.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let n = self.inner.write(&buf[..self.splitter.take(buf.len())])?;
+        self.inner.flush()?;
+        if let Some(delay) = self.delay {
+            thread::sleep(delay);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Reads from `inner` no more than a piece at a time.
+pub struct ChunkedReader<R> {
+    inner: R,
+    splitter: Splitter,
+}
+
+impl<R: Read> ChunkedReader<R> {
+    pub fn new(inner: R, chunks: Chunks) -> ChunkedReader<R> {
+        ChunkedReader { inner, splitter: Splitter::new(chunks) }
+    }
+}
+
+impl<R: Read> Read for ChunkedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let n = self.splitter.take(buf.len());
+        self.inner.read(&mut buf[..n])
+    }
+}
+
+/// The server's end of a connection from the client under test.
+pub struct Fibs {
+    tcp: ChunkedWriter<net::TcpStream>,
+    telnet: telnet::Filter,
+}
+
+impl Fibs {
+    /// Sends `s` to the client, in pieces.
+    pub fn send(&mut self, s: &str) {
+        self.send_bytes(s.as_bytes());
+    }
+
+    pub fn send_bytes(&mut self, bytes: &[u8]) {
+        self.tcp.write_all(bytes).unwrap();
+    }
+
+    /// The next line from the client, up to its carriage return, without
+    /// any answers to telnet negotiation sent.
+    pub fn line(&mut self) -> String {
+        let tcp = self.tcp.get_mut();
+        let mut ln = Vec::new();
+        let mut b = [0];
+        while tcp.read(&mut b).unwrap() == 1 && b[0] != b'\r' {
+            let mut bytes = b.to_vec();
+            self.telnet.strip(&mut bytes);
+            ln.extend(bytes);
+        }
+        String::from_utf8(ln).unwrap()
+    }
+}
+
+/// Listens on a local port, returned, for one connection, and plays FIBS
+/// to it with `script`, sending in `chunks` with `delay` between them. The
+/// connection closes when `script` returns; join the handle to have its
+/// assertions count.
+pub fn serve<F>(chunks: Chunks, delay: Option<time::Duration>, script: F) -> (u16, thread::JoinHandle<()>)
+where
+    F: FnOnce(Fibs) + Send + 'static,
+{
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || {
+        let (tcp, _) = listener.accept().unwrap();
+        // each piece its own segment, not held back to join the next.
+        tcp.set_nodelay(true).unwrap();
+        script(Fibs { tcp: ChunkedWriter::new(tcp, chunks, delay), telnet: telnet::Filter::new() });
+    });
+    (port, server)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the pieces `chunks` makes of `len` bytes.
+    fn pieces(chunks: Chunks, len: usize) -> Vec<usize> {
+        let mut splitter = Splitter::new(chunks);
+        let mut left = len;
+        let mut pieces = Vec::new();
+        while left > 0 {
+            let n = splitter.take(left);
+            pieces.push(n);
+            left -= n;
+        }
+        pieces
+    }
+
+    #[test]
+    fn splits() {
+        assert_eq!(pieces(Chunks::Whole, 10), vec![10]);
+        assert_eq!(pieces(Chunks::Every(4), 10), vec![4, 4, 2]);
+        assert_eq!(pieces(Chunks::Sizes(vec![1, 3]), 10), vec![1, 3, 1, 3, 1, 1]);
+
+        let random = pieces(Chunks::Random { seed: 7, max: 5 }, 100);
+        assert_eq!(random.iter().sum::<usize>(), 100);
+        assert!(random.iter().all(|n| (1..=5).contains(n)));
+        assert_eq!(random, pieces(Chunks::Random { seed: 7, max: 5 }, 100));
+    }
+
+    #[test]
+    fn written_and_read_in_pieces() {
+        let mut writer = ChunkedWriter::new(Vec::new(), Chunks::Every(3), None);
+        assert_eq!(writer.write(b"abcdefg").unwrap(), 3);
+        writer.write_all(b"defg").unwrap();
+        assert_eq!(writer.get_mut(), b"abcdefg");
+
+        let mut reader = ChunkedReader::new(&b"abcdefg"[..], Chunks::Sizes(vec![2, 5]));
+        let mut buf = [0; 16];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 5);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn server_sends_in_pieces() {
+        let (port, server) = serve(Chunks::Every(2), Some(SHORT_DELAY), |mut fibs| {
+            fibs.send("login: ");
+            assert_eq!(fibs.line(), "alice");
+        });
+
+        let mut tcp = net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut buf = [0; 16];
+        let mut reads: Vec<Vec<u8>> = Vec::new();
+        while reads.concat().len() < 7 {
+            let n = tcp.read(&mut buf).unwrap();
+            reads.push(buf[..n].to_vec());
+        }
+        assert_eq!(reads.concat(), b"login: ");
+        assert!(reads.iter().all(|r| r.len() <= 2));
+        tcp.write_all(b"alice\r").unwrap();
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use std::{io, net};
+use std::io::prelude::*;
+
+use crate::classify::{ChatKind, LineKind};
+use crate::{config, Error, Result};
+use fibsterm::client::{Client, Event};
+
+/// `--monitor`: logs in with the configured credentials and prints only
+/// what wants our attention. Nothing is read from the keyboard.
+pub fn run(config: &config::Config, tcp: net::TcpStream) -> Result<()> {
+    let (user, password) = match (&config.user, &config.password) {
+        (Some(user), Some(password)) => (user, password),
+        _ => return Err(Error::ConfigError(String::from("--monitor needs FIBS_USER and FIBS_PASSWORD"))),
+    };
+    let friends: Vec<String> = config.friends.iter().map(|p| p.to_lowercase()).collect();
+
+    let (_client, events) = Client::start(tcp, user, password)?;
+    loop {
+        match events.recv()? {
+            Event::LoggedIn => println!("monitoring FIBS as {}", user),
+            Event::LoginFailed => println!("login incorrect; check FIBS_USER and FIBS_PASSWORD"),
+            Event::Line(ln, kind) => notify(&ln, kind, &friends)?,
+            Event::Board(_) => {}
+            Event::Disconnected => {
+                println!("disconnected from FIBS");
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn notify(ln: &str, kind: LineKind, friends: &[String]) -> Result<()> {
+    match kind {
+        LineKind::Chat(ChatKind::Tell, _) | LineKind::Invite(_) => {
+            println!("{}", ln);
+        }
+        LineKind::Login(player) if friends.contains(&player.to_lowercase()) => {
+            println!("{} logged in", player);
+        }
+        _ => return Ok(()),
+    }
+    io::stdout().flush()?;
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use std::cmp;
+
+use crate::board::Board;
+
+const BAR: usize = 25;
+const OFF: usize = 0;
+
+// one side's checkers seen from that side: points 1..24, the bar at 25 and
+// borne off checkers at 0.
+fn side(board: &Board, ours: bool) -> [u8; 26] {
+    let mut counts = [0; 26];
+
+    counts[1..25].copy_from_slice(&board.checkers(ours));
+    if ours {
+        counts[BAR] = board.player_bar;
+        counts[OFF] = board.player_home;
+    } else {
+        counts[BAR] = board.opponent_bar;
+        counts[OFF] = board.opponent_home;
+    }
+
+    counts
+}
+
+// tries every way of playing the remaining dice until the mover's checkers
+// end up as they are in `after`, leaving the steps taken in `steps`.
+fn search(counts: &mut [u8; 26], after: &[u8; 26], dice: &[u8], steps: &mut Vec<(usize, usize)>) -> bool {
+    if counts == after {
+        return true;
+    }
+
+    for (i, die) in dice.iter().enumerate() {
+        // the same die value twice in a row gains nothing new.
+        if dice[..i].contains(die) {
+            continue;
+        }
+        let rest: Vec<u8> = dice.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, d)| *d).collect();
+
+        for from in (1..=BAR).rev() {
+            if counts[from] == 0 || (counts[BAR] > 0 && from != BAR) {
+                continue;
+            }
+            let to = from.saturating_sub(*die as usize);
+
+            counts[from] -= 1;
+            counts[to] += 1;
+            steps.push((from, to));
+
+            if search(counts, after, &rest, steps) {
+                return true;
+            }
+
+            steps.pop();
+            counts[to] -= 1;
+            counts[from] += 1;
+        }
+    }
+
+    false
+}
+
+fn point_name(point: usize) -> String {
+    match point {
+        BAR => String::from("bar"),
+        OFF => String::from("off"),
+        p => p.to_string(),
+    }
+}
+
+/// Works out the move played between two consecutive boards, in the usual
+/// notation, e.g. "bob moved 13/7* 8/7".
+///
+/// Returns `None` if the boards don't follow on from each other by a move.
+pub fn describe_move(before: &Board, after: &Board) -> Option<String> {
+    let ours = before.turn == before.color;
+    let (dice, mover) = if ours {
+        (before.player_dice, String::from("You"))
+    } else {
+        (before.opponent_dice, before.opponent.clone())
+    };
+    if dice.0 == 0 || dice.1 == 0 {
+        return None;
+    }
+
+    let dice = if dice.0 == dice.1 {
+        vec![dice.0; 4]
+    } else {
+        vec![dice.0, dice.1]
+    };
+
+    let mut counts = side(before, ours);
+    let target = side(after, ours);
+    if counts == target {
+        return None;
+    }
+
+    let mut steps = Vec::new();
+    if !search(&mut counts, &target, &dice, &mut steps) {
+        return None;
+    }
+
+    steps.sort_by_key(|step| cmp::Reverse(step.0));
+
+    // blots the opponent had before that aren't there any more were hit.
+    let victim_before = side(before, !ours);
+    let victim_after = side(after, !ours);
+    let mut hit = [false; 26];
+    for (_, to) in steps.iter() {
+        if (1..BAR).contains(to) {
+            let theirs = 25 - to;
+            hit[*to] = victim_before[theirs] == 1 && victim_after[theirs] == 0;
+        }
+    }
+
+    let mut played: Vec<String> = Vec::new();
+    for (from, to) in steps.iter() {
+        let mut s = format!("{}/{}", point_name(*from), point_name(*to));
+        if hit[*to] {
+            s.push('*');
+            hit[*to] = false;
+        }
+        played.push(s);
+    }
+
+    Some(format!("{} moved {}", mover, played.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static START: &str = "board:You:bob:3:0:0:\
+        0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+        1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0";
+
+    fn board(s: &str) -> Board {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn opening_move() {
+        let before = board(START);
+        let mut after = before.clone();
+        after.points[8] -= 1;
+        after.points[6] -= 1;
+        after.points[5] += 2;
+        after.turn = -1;
+
+        assert_eq!(describe_move(&before, &after), Some(String::from("You moved 8/5 6/5")));
+    }
+
+    #[test]
+    fn hit() {
+        let mut before = board(START);
+        before.points[1] = -1;
+        before.points[3] = -1;
+        let mut after = before.clone();
+        after.points[6] -= 1;
+        after.points[3] = 1;
+        after.points[8] -= 1;
+        after.points[7] += 1;
+        after.opponent_bar = 1;
+
+        assert_eq!(describe_move(&before, &after), Some(String::from("You moved 8/7 6/3*")));
+    }
+
+    #[test]
+    fn bear_off() {
+        let mut before = board(START);
+        before.points = [0; 26];
+        before.points[2] = 1;
+        before.points[1] = 1;
+        before.player_home = 13;
+        let mut after = before.clone();
+        after.points[2] = 0;
+        after.points[1] = 0;
+        after.player_home = 15;
+
+        assert_eq!(describe_move(&before, &after), Some(String::from("You moved 2/off 1/off")));
+    }
+
+    #[test]
+    fn unchanged_board() {
+        let before = board(START);
+        assert_eq!(describe_move(&before, &before.clone()), None);
+    }
+}
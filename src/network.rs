@@ -0,0 +1,336 @@
+use core::ptr;
+
+use std::{ffi, io, mem, net, sync, thread, time};
+use std::os::unix::io::AsRawFd;
+use std::io::prelude::*;
+
+use crate::{telnet, trace, Error, Result};
+
+pub static DEFAULT_FIBS_SERVER: &str = "fibs.com";
+pub const DEFAULT_FIBS_PORT: u16 = 4321;
+/// How long a server gets to accept the connection.
+pub const DEFAULT_CONNECT_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+// how many times to go round the server list, and how long to wait before
+// the second time, doubling after.
+const FAILOVER_ROUNDS: u32 = 3;
+const FAILOVER_BACKOFF: time::Duration = time::Duration::from_secs(2);
+
+// the addresses in a getaddrinfo list, in its order, skipping any family
+// but IPv4 and IPv6.
+unsafe fn addresses(mut cursor: *const libc::addrinfo) -> Vec<net::SocketAddr> {
+    let mut addrs = Vec::new();
+    while !cursor.is_null() {
+        let ai = &*cursor;
+        match ai.ai_family {
+            _ if ai.ai_addr.is_null() => {}
+            libc::AF_INET => {
+                let res_addr = &*(ai.ai_addr as *const libc::sockaddr_in);
+                addrs.push(net::SocketAddr::V4(net::SocketAddrV4::new(
+                        net::Ipv4Addr::from(u32::from_be(res_addr.sin_addr.s_addr)),
+                        u16::from_be(res_addr.sin_port),
+                )));
+            }
+            libc::AF_INET6 => {
+                let res_addr = &*(ai.ai_addr as *const libc::sockaddr_in6);
+                addrs.push(net::SocketAddr::V6(net::SocketAddrV6::new(
+                        net::Ipv6Addr::from(res_addr.sin6_addr.s6_addr),
+                        u16::from_be(res_addr.sin6_port),
+                        u32::from_be(res_addr.sin6_flowinfo),
+                        res_addr.sin6_scope_id,
+                )));
+            }
+            _ => {}
+        }
+        cursor = ai.ai_next;
+    }
+    addrs
+}
+
+// everything getaddrinfo has for `hostname`.
+fn lookup(hostname: &str, port: u16) -> Result<Vec<net::SocketAddr>> {
+    let c_hostname = ffi::CString::new(hostname)?;
+    let c_port = ffi::CString::new(port.to_string())?;
+    let hints = libc::addrinfo {
+        ai_flags: 0,
+        ai_family: libc::AF_UNSPEC,
+        ai_socktype: libc::SOCK_STREAM,
+        ai_protocol: 0,
+        ai_addrlen: 0,
+        ai_addr: ptr::null_mut(),
+        ai_canonname: ptr::null_mut(),
+        ai_next: ptr::null_mut(),
+    };
+    let mut cursor: *mut libc::addrinfo = ptr::null_mut();
+    unsafe {
+        match libc::getaddrinfo(c_hostname.as_ptr(), c_port.as_ptr(), &hints, &mut cursor) {
+            0 => {
+                let addrs = addresses(cursor);
+                trace::trace(trace::Category::Net, || format!("resolved {}: {:?}", hostname, addrs));
+                // copied out; the list is ours to free. On failure there's none.
+                libc::freeaddrinfo(cursor);
+                Ok(addrs)
+            }
+            e => {
+                let err: Error = e.into();
+                trace::trace(trace::Category::Net, || format!("resolving {}: {}", hostname, err));
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Where to find `hostname`, over IPv4 or IPv6: the first address
+/// getaddrinfo gives, which puts them in the order the system prefers.
+pub fn resolve(hostname: String, port: u16) -> Result<net::SocketAddr> {
+    lookup(&hostname, port)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::GAIError(format!("{} has no IPv4 or IPv6 address", hostname)))
+}
+
+/// Every address of `hostname`, IPv4 and IPv6, in the order `resolve`
+/// would try them.
+pub fn resolve_all(hostname: &str, port: u16) -> Result<Vec<net::SocketAddr>> {
+    lookup(hostname, port)
+}
+
+/// As `resolve`, for IPv4 only.
+pub fn resolvev4(hostname: String, port: u16) -> Result<net::SocketAddrV4> {
+    lookup(&hostname, port)?
+        .into_iter()
+        .find_map(|addr| match addr {
+            net::SocketAddr::V4(addr) => Some(addr),
+            net::SocketAddr::V6(_) => None,
+        })
+        .ok_or_else(|| Error::GAIError(format!("{} has no IPv4 address", hostname)))
+}
+
+/// Connects to `hostname` at the first of its addresses that answers, in
+/// the order `resolve_all` gives them, so an IPv6 address that can't be
+/// reached falls back to IPv4.
+pub fn connect(hostname: &str, port: u16, timeout: time::Duration) -> Result<net::TcpStream> {
+    connect_first(hostname, &resolve_all(hostname, port)?, timeout)
+}
+
+// the first of `hostname`'s `addrs` to take a connection, or the error from
+// the last.
+fn connect_first(hostname: &str, addrs: &[net::SocketAddr], timeout: time::Duration) -> Result<net::TcpStream> {
+    let mut last = None;
+    for fibs_addr in addrs {
+        let tcp = net::TcpStream::connect_timeout(fibs_addr, timeout);
+        trace::trace(trace::Category::Net, || match &tcp {
+            Ok(_) => format!("connected to {}", fibs_addr),
+            Err(e) => format!("connecting to {}: {}", fibs_addr, e),
+        });
+        match tcp {
+            Ok(tcp) => return Ok(tcp),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                last = Some(Error::IOError(format!("no answer from {} in {}s", fibs_addr, timeout.as_secs_f32())));
+            }
+            Err(e) => last = Some(e.into()),
+        }
+    }
+    Err(last.unwrap_or_else(|| Error::GAIError(format!("{} has no IPv4 or IPv6 address", hostname))))
+}
+
+// connects to the first of `servers` that answers, going round the list from
+// `start` and waiting longer after each time round. Returns which one it was.
+pub fn connect_any(servers: &[(String, u16)], start: usize, timeout: time::Duration) -> Result<(net::TcpStream, usize)> {
+    let mut result = connect_round(servers, start, timeout);
+    for round in 1..FAILOVER_ROUNDS {
+        if result.is_ok() {
+            break;
+        }
+        thread::sleep(FAILOVER_BACKOFF * 2u32.pow(round - 1));
+        result = connect_round(servers, start, timeout);
+    }
+    result
+}
+
+/// As `connect_any`, once round the list and no more, for a caller that
+/// has its own way of waiting between tries.
+pub fn connect_round(servers: &[(String, u16)], start: usize, timeout: time::Duration) -> Result<(net::TcpStream, usize)> {
+    let mut failures = Vec::new();
+    for i in (0..servers.len()).map(|i| (start + i) % servers.len()) {
+        let (hostname, port) = &servers[i];
+        match connect(hostname, *port, timeout) {
+            Ok(tcp) => return Ok((tcp, i)),
+            Err(e) => failures.push(format!("{}:{} ({})", hostname, port, e)),
+        }
+    }
+
+    Err(Error::IOError(format!("couldn't reach any server: {}", failures.join(", "))))
+}
+
+fn setsockopt(fd: libc::c_int, level: libc::c_int, name: libc::c_int, value: libc::c_int) -> Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd, level, name,
+            &value as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Has the kernel probe a connection that's gone quiet for `idle`, every
+/// `idle` after, so a NAT gateway that has quietly dropped it between games
+/// shows up as a read error rather than a socket left half-open for good.
+/// Where the idle time and interval can't be set, the system's apply.
+pub fn keepalive(tcp: &net::TcpStream, idle: time::Duration) -> Result<()> {
+    let fd = tcp.as_raw_fd();
+    setsockopt(fd, libc::SOL_SOCKET, libc::SO_KEEPALIVE, 1)?;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let secs = idle.as_secs().clamp(1, libc::c_int::MAX as u64) as libc::c_int;
+        setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_KEEPIDLE, secs)?;
+        setsockopt(fd, libc::IPPROTO_TCP, libc::TCP_KEEPINTVL, secs)?;
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = idle;
+    Ok(())
+}
+
+pub fn spawn_fibs_thread(mut tcp: net::TcpStream, tx: sync::mpsc::SyncSender<u8>) -> Result<thread::JoinHandle<Result<()>>> {
+    Ok(thread::spawn(move || -> Result<()> {
+        let mut buf = [0; 4096];
+        let mut telnet = telnet::Filter::new();
+
+        loop {
+            let n = tcp.read(&mut buf)?;
+            if n == 0 {
+                trace::trace(trace::Category::Net, || String::from("closed by the server"));
+                return Ok(());
+            }
+            trace::trace(trace::Category::Net, || format!("read {} bytes: {:?}", n, String::from_utf8_lossy(&buf[..n])));
+
+            let mut bytes = buf[..n].to_vec();
+            let replies = telnet.strip(&mut bytes);
+            if !replies.is_empty() {
+                tcp.write_all(&replies)?;
+            }
+            for b in bytes {
+                tx.send(b)?;
+            };
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_to_ipv4() {
+        // localhost is often dual-stack, with ::1 listed first.
+        let addr = resolvev4(String::from("localhost"), 4321).unwrap();
+        assert_eq!(addr, net::SocketAddrV4::new(net::Ipv4Addr::LOCALHOST, 4321));
+    }
+
+    #[test]
+    fn resolves_every_address() {
+        let addrs = resolve_all("localhost", 4321).unwrap();
+        assert!(addrs.contains(&net::SocketAddr::from((net::Ipv4Addr::LOCALHOST, 4321))));
+        assert_eq!(addrs[0], resolve(String::from("localhost"), 4321).unwrap());
+    }
+
+    #[test]
+    fn resolves_ipv6() {
+        let addr = resolve(String::from("::1"), 4321).unwrap();
+        assert_eq!(addr, net::SocketAddr::from((net::Ipv6Addr::LOCALHOST, 4321)));
+        assert!(resolvev4(String::from("::1"), 4321).is_err());
+    }
+
+    #[test]
+    fn both_families_in_order() {
+        unsafe {
+            let mut v4: libc::sockaddr_in = std::mem::zeroed();
+            v4.sin_family = libc::AF_INET as libc::sa_family_t;
+            v4.sin_port = 4321u16.to_be();
+            v4.sin_addr.s_addr = u32::from(net::Ipv4Addr::new(192, 0, 2, 1)).to_be();
+            let mut v6: libc::sockaddr_in6 = std::mem::zeroed();
+            v6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            v6.sin6_port = 4321u16.to_be();
+            v6.sin6_addr.s6_addr = "2001:db8::1".parse::<net::Ipv6Addr>().unwrap().octets();
+
+            let mut second: libc::addrinfo = std::mem::zeroed();
+            second.ai_family = libc::AF_INET;
+            second.ai_addr = &mut v4 as *mut _ as *mut libc::sockaddr;
+            let mut first: libc::addrinfo = std::mem::zeroed();
+            first.ai_family = libc::AF_INET6;
+            first.ai_addr = &mut v6 as *mut _ as *mut libc::sockaddr;
+            first.ai_next = &mut second;
+
+            assert_eq!(addresses(&first), vec![
+                "[2001:db8::1]:4321".parse::<net::SocketAddr>().unwrap(),
+                "192.0.2.1:4321".parse::<net::SocketAddr>().unwrap(),
+            ]);
+        }
+    }
+
+    #[test]
+    fn connecting_times_out() {
+        // TEST-NET-1: reserved, so nothing there ever answers.
+        let started = time::Instant::now();
+        assert!(connect("192.0.2.1", 4321, time::Duration::from_millis(300)).is_err());
+        assert!(started.elapsed() < time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn falls_back_to_the_next_address() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        // nothing listening there any more.
+        let refused = net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let timeout = time::Duration::from_millis(300);
+        let tcp = connect_first("localhost", &[refused, listener.local_addr().unwrap()], timeout).unwrap();
+        assert_eq!(tcp.peer_addr().unwrap(), listener.local_addr().unwrap());
+        assert!(matches!(connect_first("localhost", &[refused], timeout), Err(Error::IOError(_))));
+        assert!(matches!(connect_first("localhost", &[], timeout), Err(Error::GAIError(_))));
+    }
+
+    #[test]
+    fn once_round_the_servers() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let refused = net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let servers = vec![(String::from("127.0.0.1"), refused), (String::from("127.0.0.1"), listener.local_addr().unwrap().port())];
+        let timeout = time::Duration::from_millis(300);
+
+        assert_eq!(connect_round(&servers, 0, timeout).unwrap().1, 1);
+        // no waiting to go round again.
+        let started = time::Instant::now();
+        assert!(connect_round(&servers[..1], 0, timeout).is_err());
+        assert!(started.elapsed() < FAILOVER_BACKOFF);
+    }
+
+    #[test]
+    fn keeps_alive() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tcp = net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        keepalive(&tcp, time::Duration::from_secs(60)).unwrap();
+
+        let getsockopt = |level, name| {
+            let mut value: libc::c_int = 0;
+            let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+            let ret = unsafe {
+                libc::getsockopt(tcp.as_raw_fd(), level, name, &mut value as *mut libc::c_int as *mut libc::c_void, &mut len)
+            };
+            assert_eq!(ret, 0);
+            value
+        };
+        assert_ne!(getsockopt(libc::SOL_SOCKET, libc::SO_KEEPALIVE), 0);
+        #[cfg(target_os = "linux")]
+        assert_eq!(getsockopt(libc::IPPROTO_TCP, libc::TCP_KEEPIDLE), 60);
+    }
+
+    #[test]
+    fn resolves_again_after_freeing() {
+        for _ in 0..100 {
+            assert!(resolvev4(String::from("localhost"), 4321).is_ok());
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{collections, time};
+
+/// Short-lived notices, stacked beside the FIBS box: a tell came in, our
+/// rating changed. Each goes after `timeout`, and past `max` at once the
+/// oldest makes way. Urgent ones are shown as errors are.
+pub struct Notifications {
+    shown: collections::VecDeque<(String, bool, time::Instant)>,
+    timeout: time::Duration,
+    max: usize,
+}
+
+impl Notifications {
+    pub fn new(timeout: time::Duration, max: usize) -> Notifications {
+        Notifications { shown: collections::VecDeque::new(), timeout, max }
+    }
+
+    /// Adds a notice, saying whether there's anything new to show.
+    pub fn push(&mut self, text: String, urgent: bool, now: time::Instant) -> bool {
+        if self.max == 0 {
+            return false;
+        }
+        if self.shown.len() == self.max {
+            self.shown.pop_front();
+        }
+        self.shown.push_back((text, urgent, now));
+        true
+    }
+
+    /// Drops the notices that have been up long enough, saying whether any
+    /// went.
+    pub fn expire(&mut self, now: time::Instant) -> bool {
+        let before = self.shown.len();
+        self.shown.retain(|(_, _, at)| now.duration_since(*at) < self.timeout);
+        self.shown.len() != before
+    }
+
+    /// Oldest first, as stacked top to bottom, each with whether it's
+    /// urgent.
+    pub fn lines(&self) -> Vec<(String, bool)> {
+        self.shown.iter().map(|(text, urgent, _)| (text.clone(), *urgent)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notices_stack_and_expire() {
+        let now = time::Instant::now();
+        let second = time::Duration::from_secs(1);
+        let mut notifications = Notifications::new(5 * second, 2);
+
+        notifications.push(String::from("tell from alice"), false, now);
+        notifications.push(String::from("tell from bob"), false, now + second);
+        notifications.push(String::from("couldn't reconnect"), true, now + 2 * second);
+        assert_eq!(notifications.lines(), vec![
+            (String::from("tell from bob"), false),
+            (String::from("couldn't reconnect"), true),
+        ]);
+
+        assert!(!notifications.expire(now + 5 * second));
+        assert!(notifications.expire(now + 6 * second));
+        assert_eq!(notifications.lines(), vec![(String::from("couldn't reconnect"), true)]);
+    }
+
+    #[test]
+    fn none_allowed() {
+        let mut notifications = Notifications::new(time::Duration::from_secs(5), 0);
+        assert!(!notifications.push(String::from("tell from alice"), false, time::Instant::now()));
+        assert!(notifications.lines().is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+//! The race: each side's pip count, and which way the difference between
+//! them is going as a game goes on.
+
+use crate::board::Board;
+
+/// Whether we're gaining or losing ground in the race over a game's boards.
+/// Boards that leave the counts as they were, as for a roll, carry the last
+/// change over rather than losing it.
+#[derive(Default)]
+pub struct Trend {
+    // ours and theirs, on the last board.
+    last: Option<(u32, u32)>,
+    // how far our lead grew, or shrank if negative, the last time the
+    // counts changed.
+    change: i32,
+}
+
+impl Trend {
+    pub fn new() -> Trend {
+        Trend::default()
+    }
+
+    /// Takes the game's next board: its pip counts, ours then theirs, and
+    /// how our lead has changed since they were last different.
+    pub fn next(&mut self, board: &Board) -> ((u32, u32), i32) {
+        let pips = (board.pips(true), board.pips(false));
+        match self.last {
+            Some(last) if last != pips => self.change = lead(pips) - lead(last),
+            Some(_) => {}
+            None => self.change = 0,
+        }
+        self.last = Some(pips);
+        (pips, self.change)
+    }
+
+    /// Starts again, for a new game.
+    pub fn reset(&mut self) {
+        *self = Trend::default();
+    }
+}
+
+// how far we're ahead in the race: fewer pips to go is ahead.
+fn lead((ours, theirs): (u32, u32)) -> i32 {
+    theirs as i32 - ours as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(points: &str) -> Board {
+        Board::parse(&format!("board:You:bob:3:0:0:{}:1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0", points)).unwrap().0
+    }
+
+    #[test]
+    fn gaining_and_losing_ground() {
+        let mut trend = Trend::new();
+        assert_eq!(trend.next(&Board::opening()), ((167, 167), 0));
+
+        // we play 31: 8/5 6/5.
+        let ours = board("0:-2:0:0:0:2:4:0:2:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0");
+        assert_eq!(trend.next(&ours), ((163, 167), 4));
+        // they roll: nothing moves, and we're still up on the last change.
+        assert_eq!(trend.next(&ours), ((163, 167), 4));
+
+        // they run a back checker 6 pips; their 24 point is our 1.
+        let theirs = board("0:-1:0:0:0:2:4:-1:2:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0");
+        assert_eq!(trend.next(&theirs), ((163, 161), -6));
+
+        trend.reset();
+        assert_eq!(trend.next(&theirs), ((163, 161), 0));
+    }
+}
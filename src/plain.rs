@@ -0,0 +1,97 @@
+use std::{io, sync, thread};
+use std::io::prelude::*;
+
+use crate::{clip, command, connection, idle, submit, updates, Banner, Result, Update, CLIP_ERROR_LABEL};
+
+// the accessible front-end: no boxes, no cursor addressing, just one line
+// after another on stdout so screen readers can follow along.
+pub fn spawn_plain_thread(
+    mut updates_rx: updates::UpdateReceiver,
+    mut clip_numbers: bool,
+) -> Result<thread::JoinHandle<Result<()>>> {
+    let h = thread::spawn(move || {
+        let mut stdout = io::stdout();
+
+        loop {
+            match updates_rx.recv()? {
+                Update::MOTD(motd) => {
+                    for ln in motd.split("\r\n") {
+                        writeln!(stdout, "{}", ln)?;
+                    }
+                }
+                Update::AppendLine(ln) if clip_numbers => {
+                    writeln!(stdout, "{}", clip::annotate(&ln))?;
+                }
+                Update::AppendLine(ln) => {
+                    writeln!(stdout, "{}", ln)?;
+                }
+                // a screen reader has already read the line behind each one.
+                Update::Notifications(_) => {}
+                Update::Bell => {
+                    write!(stdout, "\x07")?;
+                }
+                Update::ClipError(msg) => {
+                    writeln!(stdout, "{}{}", CLIP_ERROR_LABEL, msg)?;
+                }
+                Update::ClipNumbers(on) => {
+                    clip_numbers = on;
+                }
+                Update::PlayerColor(..) => {}
+                Update::Pending(n) if n > 0 => {
+                    writeln!(stdout, "{} line(s) held until login", n)?;
+                }
+                Update::Board(board) => {
+                    writeln!(stdout, "{}", board.describe())?;
+                }
+                Update::History(Some(view)) => {
+                    writeln!(stdout, "History, board {} of {}: {}", view.index + 1, view.len, view.board.describe())?;
+                }
+                Update::Popup(Some(lines)) => {
+                    for ln in lines {
+                        writeln!(stdout, "{}", ln)?;
+                    }
+                }
+                Update::History(None) => {
+                    writeln!(stdout, "Back to the live board.")?;
+                }
+                Update::Score(Some(score)) => {
+                    writeln!(stdout, "Score: {}", score)?;
+                }
+                Update::Status(status) => {
+                    writeln!(stdout, "Status: {}", status)?;
+                }
+                Update::Quit => return Ok(()),
+                Update::Banner(Some(Banner { text, urgent })) => {
+                    let label = if urgent { "Urgent" } else { "Notice" };
+                    writeln!(stdout, "{}: {}", label, text)?;
+                }
+                // the terminal echoes typed input itself in line mode.
+                Update::AppendChars(_) | Update::Input(..) | Update::Banner(None) | Update::Pending(_) | Update::Resize(..) | Update::Redraw | Update::Frame | Update::Score(None) | Update::Zoom(_) | Update::Split(_) | Update::Across(_) | Update::ScrollUp(_) | Update::ScrollDown(_) | Update::Popup(None) | Update::Clear => {}
+            }
+            stdout.flush()?;
+        }
+    });
+
+    Ok(h)
+}
+
+pub fn spawn_line_input_thread<R: io::Read + Send + 'static>(
+    input: R,
+    conn: sync::Arc<sync::Mutex<connection::Connection>>,
+    activity: sync::Arc<sync::Mutex<idle::Activity>>,
+    updates_tx: updates::UpdateSender,
+    commands_tx: sync::mpsc::Sender<command::Command>,
+) -> Result<thread::JoinHandle<Result<()>>> {
+    Ok(thread::spawn(move || -> Result<()> {
+        for ln in io::BufReader::new(input).lines() {
+            let ln = ln?;
+            let quit = command::quits(&ln);
+            submit(ln, &conn, &activity, &updates_tx, &commands_tx)?;
+            if quit {
+                break;
+            }
+        }
+
+        Ok(())
+    }))
+}
@@ -0,0 +1,289 @@
+use crate::board::Board;
+
+/// The smallest and largest zoom a board can be drawn at.
+pub const MIN_ZOOM: u8 = 1;
+pub const MAX_ZOOM: u8 = 4;
+
+// a point is this many columns wide, and its stack this many rows tall,
+// before it shows a count instead of more checkers.
+const fn cell_width(zoom: u8) -> usize {
+    2 + zoom as usize
+}
+
+const fn stack_height(zoom: u8) -> usize {
+    4 + zoom as usize
+}
+
+/// Columns and rows the board takes at `zoom`.
+pub const fn size(zoom: u8) -> (usize, usize) {
+    (13 * cell_width(zoom) + 2, 2 * stack_height(zoom) + 6)
+}
+
+/// The largest zoom whose board fits in `width` by `height`, at least the
+/// smallest.
+pub fn fit(zoom: u8, width: usize, height: usize) -> u8 {
+    (MIN_ZOOM..=zoom.min(MAX_ZOOM))
+        .rev()
+        .find(|z| {
+            let (w, h) = size(*z);
+            w <= width && h <= height
+        })
+        .unwrap_or(MIN_ZOOM)
+}
+
+/// How a stack taller than can be drawn on its point is shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+    /// The top checker replaced by how many there are.
+    Count,
+    /// The top checker replaced by how many more there are than drawn,
+    /// e.g. "+3".
+    Plus,
+    /// Every stack scaled down by the same amount until the tallest fits,
+    /// without numbers.
+    Compress,
+}
+
+/// How the points are numbered above and below the board.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Labels {
+    /// 1 to 24 from our home board, the usual numbering for the side at
+    /// the bottom.
+    Standard,
+    /// 1 to 24 from the home board of whoever is on roll, so the labels
+    /// match the moves being made.
+    Relative,
+    /// No numbers; the rows are left blank so the board keeps its size.
+    Off,
+}
+
+fn centered(s: &str, width: usize) -> String {
+    format!("{:^width$}", s, width = width)
+}
+
+// what's drawn `row` checkers up a stack of `n`: a checker, or a number in
+// place of the last one if the stack is taller than it can be drawn. Either
+// number fits the narrowest cell.
+fn checker(n: u8, row: usize, height: usize, c: &str, overflow: Overflow) -> String {
+    if row + 1 == height && n as usize > height {
+        match overflow {
+            Overflow::Plus => format!("+{}", n as usize - (height - 1)),
+            _ => n.to_string(),
+        }
+    } else if row < n as usize {
+        String::from(c)
+    } else {
+        String::new()
+    }
+}
+
+/// The position from our side: our home board bottom right, our checkers O
+/// and the opponent's X.
+pub fn board(board: &Board, zoom: u8, overflow: Overflow, labels: Labels) -> Vec<String> {
+    let w = cell_width(zoom);
+    let h = stack_height(zoom);
+    let ours = board.checkers(true);
+    let theirs = board.checkers(false);
+    let tallest = ours.iter().chain(theirs.iter()).copied().max().unwrap_or(0) as usize;
+
+    // what's on our point `p`, whoever's it is.
+    let cell = |p: usize, row: usize| {
+        let (n, c) = if ours[p - 1] > 0 { (ours[p - 1], "O") } else { (theirs[24 - p], "X") };
+        let n = match overflow {
+            Overflow::Compress if tallest > h => (n as usize * h).div_ceil(tallest) as u8,
+            _ => n,
+        };
+        centered(&checker(n, row, h, c, overflow), w)
+    };
+    let half = |points: &[usize], row: usize| {
+        let (left, right) = points.split_at(6);
+        format!("|{}{}{}|",
+            left.iter().map(|p| cell(*p, row)).collect::<String>(),
+            centered("|", w),
+            right.iter().map(|p| cell(*p, row)).collect::<String>())
+    };
+    // the opponent's numbering is ours the other way round.
+    let theirs_on_roll = board.turn != 0 && board.turn != board.color;
+    let label = |p: usize| match labels {
+        Labels::Standard => p.to_string(),
+        Labels::Relative if theirs_on_roll => (25 - p).to_string(),
+        Labels::Relative => p.to_string(),
+        Labels::Off => String::new(),
+    };
+    let labels = |points: &[usize]| {
+        let (left, right) = points.split_at(6);
+        format!(" {}{}{} ",
+            left.iter().map(|p| centered(&label(*p), w)).collect::<String>(),
+            " ".repeat(w),
+            right.iter().map(|p| centered(&label(*p), w)).collect::<String>())
+    };
+
+    let top: Vec<usize> = (13..=24).collect();
+    let bottom: Vec<usize> = (1..=12).rev().collect();
+    let border = format!("+{}+", "-".repeat(13 * w));
+
+    let mut lines = vec![labels(&top), border.clone()];
+    lines.extend((0..h).map(|row| half(&top, row)));
+    lines.push(format!("|{}|", centered(
+        &format!("bar O {} X {}, off O {} X {}", board.player_bar, board.opponent_bar, board.player_home, board.opponent_home),
+        13 * w,
+    )));
+    lines.extend((0..h).rev().map(|row| half(&bottom, row)));
+    lines.push(border);
+    lines.push(labels(&bottom));
+    lines.push(centered(&format!("{}, {}", dice(board), cube(board)), 13 * w + 2));
+
+    lines
+}
+
+// whose turn it is and what they rolled, with us O and the opponent X.
+fn dice(board: &Board) -> String {
+    let (side, (a, b)) = match board.turn {
+        0 => return String::from("game over"),
+        t if t == board.color => ("O", board.player_dice),
+        _ => ("X", board.opponent_dice),
+    };
+    match a {
+        0 => format!("{} to roll", side),
+        _ => format!("{} rolled {}-{}", side, a, b),
+    }
+}
+
+// the cube's value, and who has it once it's been turned: whoever alone may
+// double next.
+fn cube(board: &Board) -> String {
+    match (board.player_may_double, board.opponent_may_double) {
+        (true, false) => format!("cube {}, O's", board.cube),
+        (false, true) => format!("cube {}, X's", board.cube),
+        _ => format!("cube {}", board.cube),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static START: &str = "board:You:bob:3:0:0:\
+        0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+        1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0";
+
+    // the opening, us to roll, at the smallest zoom.
+    static SNAPSHOT: [&str; 16] = [
+        " 13 14 15 16 17 18    19 20 21 22 23 24  ",
+        "+---------------------------------------+",
+        "| O           X     |  X              O |",
+        "| O           X     |  X              O |",
+        "| O           X     |  X                |",
+        "| O                 |  X                |",
+        "| O                 |  X                |",
+        "|       bar O 0 X 0, off O 0 X 0        |",
+        "| X                 |  O                |",
+        "| X                 |  O                |",
+        "| X           O     |  O                |",
+        "| X           O     |  O              X |",
+        "| X           O     |  O              X |",
+        "+---------------------------------------+",
+        " 12 11 10  9  8  7     6  5  4  3  2  1  ",
+        "            O to roll, cube 1            ",
+    ];
+
+    #[test]
+    fn zoom_scales_the_whole_board() {
+        let start: Board = START.parse().unwrap();
+
+        for zoom in MIN_ZOOM..=MAX_ZOOM {
+            let lines = board(&start, zoom, Overflow::Count, Labels::Standard);
+            let (width, height) = size(zoom);
+            assert_eq!(lines.len(), height);
+            assert!(lines.iter().all(|ln| ln.chars().count() == width), "zoom {}", zoom);
+        }
+    }
+
+    #[test]
+    fn opening_position() {
+        let lines = board(&Board::opening(), MIN_ZOOM, Overflow::Count, Labels::Standard);
+        assert_eq!(lines, SNAPSHOT);
+    }
+
+    #[test]
+    fn dice_and_cube() {
+        let mut position: Board = START.parse().unwrap();
+        assert_eq!(board(&position, MIN_ZOOM, Overflow::Count, Labels::Standard).last().unwrap().trim(), "O rolled 3-1, cube 1");
+
+        position.turn = -position.color;
+        position.opponent_dice = (6, 6);
+        position.cube = 2;
+        position.player_may_double = false;
+        assert_eq!(board(&position, MIN_ZOOM, Overflow::Count, Labels::Standard).last().unwrap().trim(), "X rolled 6-6, cube 2, X's");
+    }
+
+    #[test]
+    fn tall_stacks_show_a_count() {
+        let mut position: Board = START.parse().unwrap();
+        let six = position.index(6, true);
+        position.points[six] = 7 * position.color;
+        let lines = board(&position, MIN_ZOOM, Overflow::Count, Labels::Standard);
+
+        // the top of the bottom half: bob's five on our 12 point, and our 6
+        // point too tall to draw.
+        assert_eq!(lines[8], "| X                 |  7                |");
+        assert_eq!(lines[9], "| X                 |  O                |");
+    }
+
+    #[test]
+    fn overflow_styles() {
+        let mut position: Board = START.parse().unwrap();
+        let six = position.index(6, true);
+        position.points[six] = 15 * position.color;
+
+        let plus = board(&position, MIN_ZOOM, Overflow::Plus, Labels::Standard);
+        assert_eq!(plus[8], "| X                 | +11               |");
+
+        // fifteen checkers into five rows: bob's five on our 12 point take
+        // two of them.
+        let compressed = board(&position, MIN_ZOOM, Overflow::Compress, Labels::Standard);
+        assert_eq!(compressed[8], "|                   |  O                |");
+        assert_eq!(compressed[11], "| X                 |  O                |");
+
+        for zoom in MIN_ZOOM..=MAX_ZOOM {
+            for overflow in [Overflow::Count, Overflow::Plus, Overflow::Compress] {
+                let (width, _) = size(zoom);
+                let lines = board(&position, zoom, overflow, Labels::Standard);
+                assert!(lines.iter().all(|ln| ln.chars().count() == width), "zoom {} {:?}", zoom, overflow);
+            }
+        }
+    }
+
+    #[test]
+    fn point_labels() {
+        let mut position: Board = START.parse().unwrap();
+        let standard = board(&position, MIN_ZOOM, Overflow::Count, Labels::Standard);
+        assert_eq!(standard[0], " 13 14 15 16 17 18    19 20 21 22 23 24  ");
+
+        // our roll, so our numbers either way.
+        assert_eq!(board(&position, MIN_ZOOM, Overflow::Count, Labels::Relative), standard);
+        position.turn = -position.color;
+        let relative = board(&position, MIN_ZOOM, Overflow::Count, Labels::Relative);
+        assert_eq!(relative[0], " 12 11 10  9  8  7     6  5  4  3  2  1  ");
+        // the labels under the board, above the dice.
+        assert_eq!(relative[relative.len() - 2], " 13 14 15 16 17 18    19 20 21 22 23 24  ");
+
+        let off = board(&position, MIN_ZOOM, Overflow::Count, Labels::Off);
+        assert!(off[0].trim().is_empty());
+        assert_eq!(off.len(), standard.len());
+
+        for zoom in MIN_ZOOM..=MAX_ZOOM {
+            for labels in [Labels::Standard, Labels::Relative, Labels::Off] {
+                let (width, _) = size(zoom);
+                assert!(board(&position, zoom, Overflow::Count, labels).iter().all(|ln| ln.chars().count() == width));
+            }
+        }
+    }
+
+    #[test]
+    fn fit_clamps_to_the_space() {
+        assert_eq!(fit(MAX_ZOOM, 71, 22), 3);
+        assert_eq!(fit(2, 71, 22), 2);
+        assert_eq!(fit(MAX_ZOOM, 10, 10), MIN_ZOOM);
+    }
+}
@@ -0,0 +1,275 @@
+use std::{fs, io, path, sync, thread, time};
+use std::io::prelude::*;
+
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+
+use crate::{board, config, render, text, Error, Result};
+
+// how often playback checks whether the next line is due.
+const TICK: time::Duration = time::Duration::from_millis(50);
+
+// lines of the recording shown under the board.
+const CONTEXT_LINES: usize = 8;
+
+/// Writes every line received from FIBS to `FIBS_RECORD`, each after the
+/// milliseconds since recording started, for `--replay` to play back.
+pub struct Recorder {
+    file: fs::File,
+    started: time::Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &path::Path) -> Result<Recorder> {
+        let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder { file, started: time::Instant::now() })
+    }
+
+    pub fn line(&mut self, ln: &str) -> Result<()> {
+        writeln!(self.file, "{} {}", self.started.elapsed().as_millis(), ln)?;
+        Ok(())
+    }
+}
+
+/// A recording being played back, and how far into it we are.
+///
+/// Only what the lines say up to the position counts, so stepping back is
+/// just looking back from there for the last board; there's no state to
+/// unwind.
+pub struct Scrubber {
+    // milliseconds in, and the line as received.
+    lines: Vec<(u64, String)>,
+    // where the boards are among the lines.
+    boards: Vec<usize>,
+    // how many lines have been played.
+    position: usize,
+}
+
+impl Scrubber {
+    /// Reads a recording. Lines without a time, as in a plain capture, take
+    /// the time of the line before.
+    pub fn parse(recording: &str) -> Scrubber {
+        let mut at = 0;
+        let lines: Vec<(u64, String)> = recording
+            .lines()
+            .map(|ln| {
+                let timed = ln.split_once(' ').and_then(|(ms, rest)| Some((ms.parse().ok()?, rest)));
+                match timed {
+                    Some((ms, rest)) => {
+                        at = ms;
+                        (ms, String::from(rest))
+                    }
+                    None => (at, String::from(ln)),
+                }
+            })
+            .collect();
+        let boards = lines.iter().enumerate().filter(|(_, (_, ln))| ln.starts_with("board:")).map(|(i, _)| i).collect();
+
+        Scrubber { lines, boards, position: 0 }
+    }
+
+    /// Moves `n` lines on, or back if negative.
+    pub fn step(&mut self, n: isize) {
+        self.position = self.position.saturating_add_signed(n).min(self.lines.len());
+    }
+
+    /// Moves on to just after the next board, or back to just after the one
+    /// before the last shown.
+    pub fn step_board(&mut self, forward: bool) {
+        let shown = self.boards.iter().take_while(|i| **i < self.position).count();
+        let target = if forward { self.boards.get(shown) } else { shown.checked_sub(2).and_then(|k| self.boards.get(k)) };
+        self.position = target.map(|i| i + 1).unwrap_or(if forward { self.lines.len() } else { 0 });
+    }
+
+    /// Moves to the first line at or after `ms` in.
+    pub fn seek(&mut self, ms: u64) {
+        self.position = self.lines.iter().take_while(|(at, _)| *at < ms).count();
+    }
+
+    /// When the next line is due, if there is one.
+    pub fn next_at(&self) -> Option<u64> {
+        self.lines.get(self.position).map(|(at, _)| *at)
+    }
+
+    pub fn at(&self) -> u64 {
+        self.position.checked_sub(1).map(|i| self.lines[i].0).unwrap_or(0)
+    }
+
+    /// The latest board played.
+    pub fn board(&self) -> Option<board::Board> {
+        self.boards
+            .iter()
+            .rev()
+            .filter(|i| **i < self.position)
+            .find_map(|i| board::Board::parse(&self.lines[*i].1).ok())
+            .map(|(board, _)| board)
+    }
+
+    /// The last `n` lines played, boards aside.
+    pub fn recent(&self, n: usize) -> Vec<&str> {
+        let mut recent: Vec<&str> = self.lines[..self.position]
+            .iter()
+            .rev()
+            .map(|(_, ln)| ln.as_str())
+            .filter(|ln| !ln.starts_with("board:"))
+            .take(n)
+            .collect();
+        recent.reverse();
+        recent
+    }
+
+    /// e.g. "01:23 of 12:00, line 120 of 3000, board 14 of 80".
+    pub fn describe(&self) -> String {
+        let clock = |ms: u64| format!("{:02}:{:02}", ms / 60_000, ms / 1000 % 60);
+        let end = self.lines.last().map(|(at, _)| *at).unwrap_or(0);
+        let board = self.boards.iter().take_while(|i| **i < self.position).count();
+        format!("{} of {}, line {} of {}, board {} of {}",
+            clock(self.at()), clock(end), self.position, self.lines.len(), board, self.boards.len())
+    }
+}
+
+/// `--replay <file>`: plays a recording back, with the keys driving it
+/// rather than going anywhere. Space pauses and resumes, Left and Right step
+/// a line, Up and Down a board, a number of seconds and Enter jumps there,
+/// and q or Esc leaves.
+pub fn run(config: &config::Config, recording: &path::Path) -> Result<()> {
+    let mut scrubber = Scrubber::parse(&fs::read_to_string(recording)?);
+    let mut stdout = io::stdout().into_raw_mode()?;
+
+    let (keys_tx, keys_rx) = sync::mpsc::channel();
+    thread::spawn(move || {
+        for k in io::stdin().keys() {
+            if keys_tx.send(k).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut playing = true;
+    // where playback would have got to, in milliseconds of the recording.
+    let mut clock = 0;
+    let mut jump = String::new();
+    let mut dirty = true;
+
+    loop {
+        match keys_rx.try_recv() {
+            Ok(Ok(termion::event::Key::Char('q'))) | Ok(Ok(termion::event::Key::Esc)) => break,
+            Ok(Ok(termion::event::Key::Char(' '))) => playing = !playing,
+            Ok(Ok(termion::event::Key::Right)) => scrubber.step(1),
+            Ok(Ok(termion::event::Key::Left)) => scrubber.step(-1),
+            Ok(Ok(termion::event::Key::Down)) => scrubber.step_board(true),
+            Ok(Ok(termion::event::Key::Up)) => scrubber.step_board(false),
+            Ok(Ok(termion::event::Key::Char(c))) if c.is_ascii_digit() => jump.push(c),
+            Ok(Ok(termion::event::Key::Char('\n'))) => {
+                if let Ok(secs) = jump.parse::<u64>() {
+                    scrubber.seek(secs * 1000);
+                }
+                jump.clear();
+            }
+            Ok(Ok(termion::event::Key::Backspace)) => {
+                jump.pop();
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(e.into()),
+            Err(sync::mpsc::TryRecvError::Empty) => {
+                if playing {
+                    clock += TICK.as_millis() as u64;
+                    while scrubber.next_at().map(|at| at <= clock).unwrap_or(false) {
+                        scrubber.step(1);
+                        dirty = true;
+                    }
+                }
+                if dirty {
+                    draw(&mut stdout, config, &scrubber, playing, &jump)?;
+                    dirty = false;
+                }
+                thread::sleep(TICK);
+                continue;
+            }
+            Err(sync::mpsc::TryRecvError::Disconnected) => {
+                return Err(Error::SyncError(String::from("input thread disconnected")));
+            }
+        }
+        // a key moved us; play on from there.
+        clock = scrubber.at();
+        dirty = true;
+    }
+
+    write!(stdout, "{}{}", termion::clear::All, termion::cursor::Goto(1, 1))?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn draw(stdout: &mut impl Write, config: &config::Config, scrubber: &Scrubber, playing: bool, jump: &str) -> Result<()> {
+    let (cols, rows) = termion::terminal_size().unwrap_or((80, 24));
+    let width = cols as usize;
+    let board_rows = (rows as usize).saturating_sub(CONTEXT_LINES + 2);
+
+    let mut lines = match scrubber.board() {
+        Some(board) => render::board(&board, render::fit(config.zoom, width, board_rows), config.overflow, config.labels),
+        None => vec![String::from("no board yet")],
+    };
+    lines.push(String::new());
+    lines.extend(scrubber.recent(CONTEXT_LINES).iter().flat_map(|ln| text::wrap(ln, width)));
+    lines.push(format!("REPLAY {} | {}{} | space {}, ←/→ line, ↑/↓ board, <secs>⏎ jump, q quit",
+        scrubber.describe(),
+        if playing { "playing" } else { "paused" },
+        if jump.is_empty() { String::new() } else { format!(", jump to {}s", jump) },
+        if playing { "pause" } else { "play" }));
+
+    write!(stdout, "{}", termion::clear::All)?;
+    for (i, ln) in lines.iter().take(rows as usize).enumerate() {
+        write!(stdout, "{}{}", termion::cursor::Goto(1, i as u16 + 1), ln)?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static START: &str = "board:You:bob:3:0:0:\
+        0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+        1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0";
+
+    fn recording() -> String {
+        let moved = START.replacen(":3:1:", ":0:0:", 1);
+        format!("0 Starting a new game with bob.\n1000 {}\n1500 12 bob gl\n4000 {}\nbob moves 13-10 13-11\n", START, moved)
+    }
+
+    #[test]
+    fn steps_by_line_and_board() {
+        let mut scrubber = Scrubber::parse(&recording());
+        assert_eq!(scrubber.board(), None);
+
+        scrubber.step_board(true);
+        assert_eq!(scrubber.describe(), "00:01 of 00:04, line 2 of 5, board 1 of 2");
+        assert_eq!(scrubber.board().map(|b| b.player_dice), Some((3, 1)));
+
+        scrubber.step_board(true);
+        assert_eq!(scrubber.board().map(|b| b.player_dice), Some((0, 0)));
+
+        // back from the second board is to the first, not the second's start.
+        scrubber.step_board(false);
+        assert_eq!(scrubber.board().map(|b| b.player_dice), Some((3, 1)));
+
+        scrubber.step(1);
+        assert_eq!(scrubber.recent(1), vec!["12 bob gl"]);
+        scrubber.step(-10);
+        assert_eq!(scrubber.describe(), "00:00 of 00:04, line 0 of 5, board 0 of 2");
+    }
+
+    #[test]
+    fn seeks_and_untimed_lines() {
+        let mut scrubber = Scrubber::parse(&recording());
+
+        scrubber.seek(1500);
+        assert_eq!(scrubber.next_at(), Some(1500));
+        scrubber.seek(4000);
+        scrubber.step(2);
+        // the plain line took the time before it.
+        assert_eq!(scrubber.at(), 4000);
+        assert_eq!(scrubber.recent(1), vec!["bob moves 13-10 13-11"]);
+    }
+}
@@ -0,0 +1,42 @@
+//! Where the TUI draws: the terminal, or nowhere while it takes in a
+//! backlog it's only going to draw over once it's through.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static HELD: AtomicBool = AtomicBool::new(false);
+
+/// Stops drawing, or starts again.
+pub fn hold(held: bool) {
+    HELD.store(held, Ordering::Relaxed);
+}
+
+/// The terminal, unless drawing is held.
+pub fn out() -> Screen {
+    if HELD.load(Ordering::Relaxed) {
+        Screen::Held
+    } else {
+        Screen::Terminal(io::stdout())
+    }
+}
+
+pub enum Screen {
+    Terminal(io::Stdout),
+    Held,
+}
+
+impl io::Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Screen::Terminal(stdout) => stdout.write(buf),
+            Screen::Held => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Screen::Terminal(stdout) => stdout.flush(),
+            Screen::Held => Ok(()),
+        }
+    }
+}
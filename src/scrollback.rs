@@ -0,0 +1,338 @@
+use std::cell;
+
+use crate::text;
+
+/// Everything shown in the FIBS box, one entry per line as the server sent
+/// it. Wrapping to the box is left to whoever draws it, so a long line stays
+/// one line for anything that reads the buffer back.
+///
+/// The MOTD, which always starts the buffer, is the exception: its lines are
+/// shown just as sent, a row each, and scrolled across rather than wrapped
+/// so the art in it survives.
+#[derive(Default)]
+pub struct Scrollback {
+    lines: Vec<String>,
+    // how many lines from the first are shown as sent.
+    verbatim: usize,
+    // how far those are scrolled across, in columns.
+    across: usize,
+    // the rows the lines take up, counted once each rather than wrapped
+    // over again for every line that comes in.
+    counted: cell::RefCell<Counted>,
+}
+
+// for `width`, the rows up to the end of each line counted so far.
+#[derive(Default)]
+struct Counted {
+    width: usize,
+    ends: Vec<usize>,
+}
+
+impl Scrollback {
+    pub fn new() -> Scrollback {
+        Scrollback::default()
+    }
+
+    pub fn push(&mut self, ln: String) {
+        self.lines.push(ln);
+    }
+
+    /// Adds a line to be shown as sent. Only the lines the buffer starts
+    /// with can be; after any other, this is `push`.
+    pub fn push_verbatim(&mut self, ln: String) {
+        if self.verbatim == self.lines.len() {
+            self.verbatim += 1;
+        }
+        self.lines.push(ln);
+    }
+
+    pub fn is_verbatim(&self, i: usize) -> bool {
+        i < self.verbatim
+    }
+
+    /// Scrolls the verbatim lines `by` columns right, or left if negative,
+    /// no further than shows the end of the widest in `width`.
+    pub fn scroll_across(&mut self, by: isize, width: usize) {
+        let widest = self.lines[..self.verbatim].iter().map(|ln| text::display_width(&text::cut(&text::keep_sgr(ln), 0, usize::MAX))).max().unwrap_or(0);
+        self.across = self.across.saturating_add_signed(by).min(widest.saturating_sub(width));
+    }
+
+    pub fn across(&self) -> usize {
+        self.across
+    }
+
+    /// Adds to the last line, for a line still being typed or received.
+    pub fn append(&mut self, s: &str) {
+        self.recount_last();
+        match self.lines.last_mut() {
+            Some(last) => last.push_str(s),
+            None => self.lines.push(String::from(s)),
+        }
+    }
+
+    pub fn replace_last(&mut self, ln: String) {
+        self.recount_last();
+        self.lines.pop();
+        self.lines.push(ln);
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.verbatim = 0;
+        self.across = 0;
+        self.counted.take();
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// How many rows of `width` the whole buffer takes up.
+    pub fn rows(&self, width: usize) -> usize {
+        self.count(width).ends.last().copied().unwrap_or(0)
+    }
+
+    /// The line that row `row` of the buffer wrapped to `width` is in, and
+    /// how many of that line's rows come before it.
+    pub fn line_at(&self, row: usize, width: usize) -> (usize, usize) {
+        let counted = self.count(width);
+        let i = counted.ends.partition_point(|end| *end <= row);
+        let start = i.checked_sub(1).map(|i| counted.ends[i]).unwrap_or(0);
+        (i, row.saturating_sub(start))
+    }
+
+    // the rows counted at `width` through the last line, counting only
+    // those lines that haven't been yet.
+    fn count(&self, width: usize) -> cell::Ref<'_, Counted> {
+        {
+            let mut counted = self.counted.borrow_mut();
+            if counted.width != width {
+                *counted = Counted { width, ends: Vec::new() };
+            }
+            for i in counted.ends.len()..self.lines.len() {
+                let end = counted.ends.last().copied().unwrap_or(0) + self.rows_of(i, width);
+                counted.ends.push(end);
+            }
+        }
+        self.counted.borrow()
+    }
+
+    // the last line's about to change; it's counted again when next asked.
+    fn recount_last(&mut self) {
+        let ends = &mut self.counted.get_mut().ends;
+        ends.truncate(self.lines.len().saturating_sub(1));
+    }
+
+    /// How many rows of `width` the line at `i` takes up.
+    pub fn rows_of(&self, i: usize, width: usize) -> usize {
+        match self.lines.get(i) {
+            Some(_) if self.is_verbatim(i) => 1,
+            Some(ln) => text::wrap(&text::keep_sgr(ln), width).len(),
+            None => 0,
+        }
+    }
+}
+
+/// The rows of the wrapped buffer in view: the first, and how many from
+/// there, which is the FIBS box's height.
+pub type Window = (usize, usize);
+
+/// The furthest a window `height` rows tall goes down a buffer of `rows`
+/// rows: the newest at the bottom of it, or from the top while they don't
+/// yet fill it.
+pub fn bottom(rows: usize, height: usize) -> Window {
+    (rows.saturating_sub(height), height)
+}
+
+/// `window` resized to `height` rows over a buffer of `rows`, keeping its
+/// bottom row where it was so that, at the end, the newest stay in view.
+pub fn pinned(window: Window, rows: usize, height: usize) -> Window {
+    let end = (window.0 + window.1).min(rows);
+    (end.saturating_sub(height).min(bottom(rows, height).0), height)
+}
+
+/// `window` after rows come onto the end of a buffer, `before` rows long
+/// and `after` now: moved on with them if it was showing the end, and left
+/// where it is if it's been scrolled back from there, so that what's being
+/// read back through isn't pulled out from under the reader by live play.
+pub fn followed(window: Window, before: usize, after: usize) -> Window {
+    if window.0 + window.1 < before {
+        return window;
+    }
+    bottom(after, window.1)
+}
+
+/// `window` scrolled back `by` rows, no further than the top.
+pub fn up(window: Window, by: usize) -> Window {
+    (window.0.saturating_sub(by), window.1)
+}
+
+/// `window` scrolled on `by` rows over a buffer of `rows`, no further than
+/// the newest.
+pub fn down(window: Window, rows: usize, by: usize) -> Window {
+    ((window.0 + by).min(bottom(rows, window.1).0), window.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn long_tell() -> String {
+        String::from("bob tells: that was a great match, we should play again tomorrow if you have the time")
+    }
+
+    #[test]
+    fn wrapped_line_stays_one_line() {
+        let mut scrollback = Scrollback::new();
+        scrollback.push(long_tell());
+
+        assert_eq!(scrollback.rows_of(0, 40), 3);
+        assert_eq!(scrollback.lines(), &[long_tell()]);
+    }
+
+    #[test]
+    fn search_across_a_wrap() {
+        let mut scrollback = Scrollback::new();
+        scrollback.push(String::from("alice says: hi"));
+        scrollback.push(long_tell());
+
+        // "we should" is split between the first and second rows at 40 columns.
+        let found = scrollback.lines().iter().position(|ln| ln.contains("we should"));
+        assert_eq!(found, Some(1));
+    }
+
+    #[test]
+    fn motd_is_neither_wrapped_nor_trimmed() {
+        let art = "  _____ ___ ____  ____    _____ ___ ____  ____    _____ ___ ____  ____    ";
+        let mut scrollback = Scrollback::new();
+        scrollback.push_verbatim(String::from(art));
+        scrollback.push_verbatim(String::from("login: "));
+        scrollback.push(long_tell());
+        scrollback.push_verbatim(String::from("not the MOTD"));
+
+        assert_eq!(scrollback.rows_of(0, 40), 1);
+        assert_eq!(scrollback.lines()[1], "login: ");
+        assert!(!scrollback.is_verbatim(3));
+
+        scrollback.scroll_across(8, 40);
+        assert_eq!(scrollback.across(), 8);
+        scrollback.scroll_across(100, 40);
+        assert_eq!(scrollback.across(), art.len() - 40);
+        scrollback.scroll_across(-100, 40);
+        assert_eq!(scrollback.across(), 0);
+    }
+
+    #[test]
+    fn scrolled_back_stays_put_as_a_board_comes_in() {
+        let mut scrollback = Scrollback::new();
+        for i in 0..40 {
+            scrollback.push(format!("line {}", i));
+        }
+        let before = scrollback.rows(40);
+        scrollback.push(String::from("board:You:bob:3:0:0:0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0"));
+        let after = scrollback.rows(40);
+
+        assert_eq!(followed((5, 20), before, after), (5, 20));
+        // at the end, it keeps up.
+        assert_eq!(followed((20, 20), before, after), (after - 20, 20));
+    }
+
+    #[test]
+    fn rows_counted_as_lines_change() {
+        let mut scrollback = Scrollback::new();
+        scrollback.push(String::from("alice says: hi"));
+        scrollback.push(long_tell());
+        assert_eq!(scrollback.rows(40), 4);
+        assert_eq!(scrollback.line_at(0, 40), (0, 0));
+        assert_eq!(scrollback.line_at(2, 40), (1, 1));
+        assert_eq!(scrollback.line_at(4, 40), (2, 0));
+
+        scrollback.append(" and the day after");
+        assert_eq!(scrollback.rows(40), 4);
+        scrollback.replace_last(String::from("bob tells: bye"));
+        assert_eq!(scrollback.rows(40), 2);
+        assert_eq!(scrollback.rows(10), 4);
+
+        scrollback.clear();
+        assert_eq!(scrollback.rows(40), 0);
+    }
+
+    #[test]
+    fn long_sessions_stay_quick() {
+        // as the FIBS box takes each line in: what's the bottom now, and
+        // where does it start.
+        let mut scrollback = Scrollback::new();
+        let mut take_in = |lines: std::ops::Range<usize>| {
+            let started = std::time::Instant::now();
+            for i in lines {
+                let before = scrollback.rows(71);
+                scrollback.push(format!("{} {}", long_tell(), i));
+                let window = followed(bottom(before, 20), before, scrollback.rows(71));
+                scrollback.line_at(window.0, 71);
+            }
+            started.elapsed()
+        };
+
+        // wrapped all over again for every line, the last thousand would
+        // take ten times the first.
+        let first = take_in(0..1000);
+        take_in(1000..5000);
+        let last = take_in(5000..6000);
+        assert!(last < first * 3, "{:?} for the first thousand lines, {:?} for the last", first, last);
+    }
+
+    #[test]
+    fn filling_from_the_top() {
+        assert_eq!(bottom(0, 30), (0, 30));
+        assert_eq!(followed((0, 30), 10, 11), (0, 30));
+        assert_eq!(followed((0, 30), 30, 31), (1, 30));
+    }
+
+    #[test]
+    fn paging_stops_at_either_end() {
+        let window = bottom(100, 30);
+        assert_eq!(window, (70, 30));
+        assert_eq!(up(window, 30), (40, 30));
+        assert_eq!(up(up(up(window, 30), 30), 30), (0, 30));
+        assert_eq!(down(up(window, 30), 100, 30), (70, 30));
+        assert_eq!(down((60, 30), 100, 30), (70, 30));
+
+        // nothing to scroll through yet.
+        assert_eq!(up(bottom(10, 30), 30), (0, 30));
+        assert_eq!(down(bottom(10, 30), 10, 30), (0, 30));
+    }
+
+    #[test]
+    fn scrolled_back_until_paged_down_again() {
+        let window = up(bottom(100, 30), 30);
+        assert_eq!(followed(window, 100, 101), window);
+        let window = down(window, 101, 30);
+        assert_eq!(window, (70, 30));
+        // not quite at the end yet: the line that came in is still below.
+        assert_eq!(followed(window, 101, 102), window);
+        let window = down(window, 102, 30);
+        assert_eq!(followed(window, 102, 103), (73, 30));
+    }
+
+    #[test]
+    fn growing_shows_more_rows() {
+        let lines: Vec<String> = (0..40).map(|i| format!("line {}", i)).collect();
+        let window = pinned((18, 22), 40, 30);
+
+        assert_eq!(window, (10, 30));
+        assert_eq!(lines.iter().skip(window.0).take(window.1).count(), 30);
+        assert_eq!(lines[window.0 + window.1 - 1], "line 39");
+    }
+
+    #[test]
+    fn shrinking_shows_fewer_rows() {
+        assert_eq!(pinned((18, 22), 40, 10), (30, 10));
+        // scrolled back, the same bottom row.
+        assert_eq!(pinned((5, 20), 40, 10), (15, 10));
+    }
+
+    #[test]
+    fn growing_past_the_top() {
+        assert_eq!(pinned((0, 22), 22, 30), (0, 30));
+    }
+}
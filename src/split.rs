@@ -0,0 +1,126 @@
+use crate::{board, render};
+
+/// The least of the FIBS box the board can be given: the smallest zoom's
+/// height. Less than this and the board isn't drawn at all.
+pub const MIN_BOARD_ROWS: usize = render::size(render::MIN_ZOOM).1;
+
+/// The least left for chat under the board.
+pub const MIN_CHAT_ROWS: usize = 3;
+
+/// How far F10 and F11 move the split.
+pub const STEP: f32 = 0.05;
+
+// the range F10 and F11 keep the ratio in; `FIBS_SPLIT=0` is the only way to
+// have no board pane.
+const MIN_RATIO: f32 = 0.1;
+const MAX_RATIO: f32 = 0.9;
+
+/// The FIBS box split between the live board, on top, and chat below it.
+/// The board's share is kept as a fraction of the box, so it stays about
+/// the same as the terminal is resized.
+pub struct Split {
+    pub ratio: f32,
+    pub board: Option<board::Board>,
+}
+
+impl Split {
+    pub fn new(ratio: f32) -> Split {
+        Split { ratio, board: None }
+    }
+
+    /// Whether boards go in a pane of their own rather than the buffer.
+    pub fn on(&self) -> bool {
+        self.ratio > 0.0
+    }
+
+    /// Grows the board's share by `change`, or shrinks it if negative. Does
+    /// nothing when the split is off.
+    pub fn adjust(&mut self, change: f32) {
+        if self.on() {
+            self.ratio = (self.ratio + change).clamp(MIN_RATIO, MAX_RATIO);
+        }
+    }
+
+    /// The box's `height` rows of chat, with the board drawn over the top
+    /// of them when there's one to show and room for it.
+    pub fn lines(
+        &self,
+        chat: Vec<String>,
+        width: usize,
+        height: usize,
+        zoom: u8,
+        overflow: render::Overflow,
+        labels: render::Labels,
+    ) -> Vec<String> {
+        let board = match &self.board {
+            Some(board) if self.on() => board,
+            _ => return chat,
+        };
+        let rows = board_rows(height, self.ratio);
+        if rows == 0 {
+            return chat;
+        }
+
+        let mut lines = render::board(board, render::fit(zoom, width, rows), overflow, labels);
+        lines.resize(rows, String::new());
+        // the newest chat, as much as fits.
+        let chat_rows = height - rows;
+        lines.extend(chat.into_iter().rev().take(chat_rows).rev());
+        lines
+    }
+}
+
+/// The rows of a box `height` tall that `ratio` gives the board, within the
+/// minimums for each pane, or 0 if the box can't fit both.
+pub fn board_rows(height: usize, ratio: f32) -> usize {
+    if ratio <= 0.0 || height < MIN_BOARD_ROWS + MIN_CHAT_ROWS {
+        return 0;
+    }
+    ((height as f32 * ratio).round() as usize).clamp(MIN_BOARD_ROWS, height - MIN_CHAT_ROWS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_follow_the_ratio_within_the_minimums() {
+        assert_eq!(board_rows(40, 0.5), 20);
+        assert_eq!(board_rows(40, 0.9), 36);
+        assert_eq!(board_rows(40, 0.1), MIN_BOARD_ROWS);
+        assert_eq!(board_rows(20, 0.9), 20 - MIN_CHAT_ROWS);
+        assert_eq!(board_rows(17, 0.5), 0);
+        assert_eq!(board_rows(40, 0.0), 0);
+    }
+
+    #[test]
+    fn board_over_the_newest_chat() {
+        let (board, _) = board::Board::parse("board:You:bob:3:0:0:\
+            0:-2:0:0:0:0:5:0:3:0:0:0:-5:5:0:0:0:-3:0:-5:0:0:0:0:2:0:\
+            1:3:1:0:0:1:1:1:0:1:-1:0:25:0:0:0:0:2:0:0:0").unwrap();
+        let chat: Vec<String> = (0..30).map(|i| format!("line {}", i)).collect();
+        let mut split = Split::new(0.5);
+
+        let lines = split.lines(chat.clone(), 71, 30, 4, render::Overflow::Count, render::Labels::Standard);
+        assert_eq!(lines, chat);
+
+        split.board = Some(board);
+        let lines = split.lines(chat, 71, 30, 4, render::Overflow::Count, render::Labels::Standard);
+        assert_eq!(lines.len(), 30);
+        assert_eq!(lines[MIN_BOARD_ROWS], format!("line {}", MIN_BOARD_ROWS));
+        assert_eq!(lines[29], "line 29");
+    }
+
+    #[test]
+    fn adjusting_stays_in_range() {
+        let mut split = Split::new(0.5);
+        for _ in 0..20 {
+            split.adjust(STEP);
+        }
+        assert_eq!(split.ratio, MAX_RATIO);
+
+        let mut off = Split::new(0.0);
+        off.adjust(STEP);
+        assert!(!off.on());
+    }
+}
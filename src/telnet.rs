@@ -0,0 +1,149 @@
+//! FIBS speaks telnet, and some servers open with option negotiation. We
+//! want none of the options, so every offer is turned down and the commands
+//! are taken out of the stream before the login detector or the FIBS box
+//! see them.
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Data,
+    // just had an IAC.
+    Command,
+    // WILL, WONT, DO or DONT, waiting on the option.
+    Option(u8),
+    // inside SB ... IAC SE, and just had an IAC in it.
+    Sub,
+    SubCommand,
+}
+
+/// Strips telnet commands from the bytes read, a read at a time; a command
+/// split between two reads is picked up where it left off.
+pub struct Filter {
+    state: State,
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter::new()
+    }
+}
+
+impl Filter {
+    pub fn new() -> Filter {
+        Filter { state: State::Data }
+    }
+
+    /// Takes the commands out of `bytes`, returning the answers to send
+    /// back: DONT for each WILL, and WONT for each DO.
+    pub fn strip(&mut self, bytes: &mut Vec<u8>) -> Vec<u8> {
+        let mut replies = Vec::new();
+        let mut kept = 0;
+
+        for i in 0..bytes.len() {
+            let b = bytes[i];
+            self.state = match (self.state, b) {
+                (State::Data, IAC) => State::Command,
+                (State::Data, _) => {
+                    bytes[kept] = b;
+                    kept += 1;
+                    State::Data
+                }
+                // a doubled IAC is a 255 in the data.
+                (State::Command, IAC) => {
+                    bytes[kept] = b;
+                    kept += 1;
+                    State::Data
+                }
+                (State::Command, WILL | WONT | DO | DONT) => State::Option(b),
+                (State::Command, SB) => State::Sub,
+                // NOP, GA and the like: nothing to do.
+                (State::Command, _) => State::Data,
+                (State::Option(verb), option) => {
+                    match verb {
+                        WILL => replies.extend_from_slice(&[IAC, DONT, option]),
+                        DO => replies.extend_from_slice(&[IAC, WONT, option]),
+                        _ => {}
+                    }
+                    State::Data
+                }
+                (State::Sub, IAC) => State::SubCommand,
+                (State::Sub, _) => State::Sub,
+                (State::SubCommand, SE) => State::Data,
+                (State::SubCommand, _) => State::Sub,
+            };
+        }
+
+        bytes.truncate(kept);
+        replies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    use crate::mock;
+
+    #[test]
+    fn negotiation_is_stripped_and_refused() {
+        let mut filter = Filter::new();
+        let mut bytes = vec![IAC, WILL, 1, b'h', IAC, DO, 24, b'i', IAC, DONT, 3, b'\r', b'\n'];
+
+        let replies = filter.strip(&mut bytes);
+        assert_eq!(bytes, b"hi\r\n");
+        assert_eq!(replies, vec![IAC, DONT, 1, IAC, WONT, 24]);
+    }
+
+    #[test]
+    fn subnegotiation_and_escaped_iac() {
+        let mut filter = Filter::new();
+        let mut bytes = vec![b'a', IAC, SB, 24, 1, IAC, SE, b'b', IAC, IAC, b'c'];
+
+        assert!(filter.strip(&mut bytes).is_empty());
+        assert_eq!(bytes, vec![b'a', b'b', IAC, b'c']);
+    }
+
+    #[test]
+    fn commands_split_across_reads() {
+        let mut filter = Filter::new();
+        let mut first = vec![b'l', b'o', IAC];
+        let mut second = vec![WILL];
+        let mut third = vec![1, b'g'];
+
+        filter.strip(&mut first);
+        filter.strip(&mut second);
+        let replies = filter.strip(&mut third);
+        assert_eq!((first, second, third), (b"lo".to_vec(), vec![], b"g".to_vec()));
+        assert_eq!(replies, vec![IAC, DONT, 1]);
+    }
+
+    #[test]
+    fn any_split_of_the_stream() {
+        let stream = [&b"\r\nWel"[..], &[IAC, WILL, 1, IAC, SB, 24, 1, IAC, SE, IAC, IAC], b"come\r\n", &[IAC, DO, 3]].concat();
+        for max in 1..8 {
+            let mut reader = mock::ChunkedReader::new(&stream[..], mock::Chunks::Random { seed: max as u64, max });
+            let mut filter = Filter::new();
+            let (mut kept, mut replies) = (Vec::new(), Vec::new());
+            let mut buf = [0; 16];
+            loop {
+                let n = reader.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                let mut bytes = buf[..n].to_vec();
+                replies.extend(filter.strip(&mut bytes));
+                kept.extend(bytes);
+            }
+            assert_eq!(kept, [&b"\r\nWel"[..], &[IAC], b"come\r\n"].concat());
+            assert_eq!(replies, vec![IAC, DONT, 1, IAC, WONT, 3]);
+        }
+    }
+}
\ No newline at end of file
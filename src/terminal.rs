@@ -0,0 +1,157 @@
+use std::{env, fmt, io, sync};
+use std::io::prelude::*;
+use std::io::IsTerminal;
+
+use termion::raw::{IntoRawMode, RawTerminal};
+
+use crate::Result;
+
+/// Characters used to draw the boxes of the TUI.
+pub struct Theme {
+    pub name: &'static str,
+    pub top_left: &'static str,
+    pub top_right: &'static str,
+    pub bottom_left: &'static str,
+    pub bottom_right: &'static str,
+    pub horizontal: &'static str,
+    pub vertical: &'static str,
+    /// Stands in for a line break typed into the INPUT box.
+    pub newline: &'static str,
+    /// Beside the pip counts, for our lead in the race growing or shrinking.
+    pub gaining: &'static str,
+    pub losing: &'static str,
+}
+
+pub static UNICODE: Theme = Theme {
+    name: "unicode",
+    top_left: "╔",
+    top_right: "╗",
+    bottom_left: "╚",
+    bottom_right: "╝",
+    horizontal: "═",
+    vertical: "║",
+    newline: "⏎",
+    gaining: "▲",
+    losing: "▼",
+};
+
+pub static ASCII: Theme = Theme {
+    name: "ascii",
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    horizontal: "-",
+    vertical: "|",
+    newline: "\\",
+    gaining: "^",
+    losing: "v",
+};
+
+/// What we could find out about the terminal we're running in.
+pub struct Capabilities {
+    pub size: Option<(u16, u16)>,
+    pub unicode: bool,
+    /// Whether box-drawing characters are likely drawn two columns wide, as
+    /// many terminals do for these "ambiguous width" characters in Chinese,
+    /// Japanese and Korean locales.
+    pub wide_boxes: bool,
+    pub color: bool,
+    pub mouse: bool,
+}
+
+static CAPABILITIES: sync::OnceLock<Capabilities> = sync::OnceLock::new();
+
+fn locale() -> String {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|val| !val.is_empty())
+        .unwrap_or_default()
+}
+
+fn probe() -> Capabilities {
+    let term = env::var("TERM").unwrap_or_default();
+    let locale = locale().to_lowercase();
+    let dumb = term.is_empty() || term == "dumb";
+
+    Capabilities {
+        size: termion::terminal_size().ok(),
+        unicode: locale.contains("utf-8") || locale.contains("utf8"),
+        wide_boxes: ["ja", "zh", "ko"].iter().any(|lang| locale.starts_with(lang)),
+        // not for output going to a file or another program.
+        color: !dumb && env::var_os("NO_COLOR").is_none() && io::stdout().is_terminal(),
+        mouse: ["xterm", "screen", "tmux", "rxvt", "alacritty", "kitty", "foot", "wezterm"]
+            .iter()
+            .any(|t| term.starts_with(t)),
+    }
+}
+
+/// The terminal's capabilities, probed the first time they're asked for.
+pub fn capabilities() -> &'static Capabilities {
+    CAPABILITIES.get_or_init(probe)
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b { "yes" } else { "no" }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.size {
+            Some((cols, rows)) => writeln!(f, "size:    {}x{}", cols, rows)?,
+            None => writeln!(f, "size:    unknown")?,
+        }
+        writeln!(f, "unicode: {}", yes_no(self.unicode))?;
+        writeln!(f, "boxes:   {}", if self.wide_boxes { "probably wide" } else { "narrow" })?;
+        writeln!(f, "color:   {}", yes_no(self.color))?;
+        writeln!(f, "mouse:   {}", yes_no(self.mouse))
+    }
+}
+
+/// Raw mode for as long as this is held. However it's dropped, returning,
+/// by `?` or unwinding from a panic, the terminal is put back as it was and
+/// the cursor shown, so the shell isn't left unusable.
+pub struct RawModeGuard<W: Write> {
+    raw: Option<RawTerminal<io::Stdout>>,
+    // where the cursor's shown again.
+    out: W,
+}
+
+impl RawModeGuard<io::Stdout> {
+    pub fn new() -> Result<RawModeGuard<io::Stdout>> {
+        Ok(RawModeGuard { raw: Some(io::stdout().into_raw_mode()?), out: io::stdout() })
+    }
+}
+
+impl<W: Write> RawModeGuard<W> {
+    /// Back to the terminal's own mode early, to print on the way out.
+    pub fn suspend(&self) -> Result<()> {
+        if let Some(raw) = &self.raw {
+            raw.suspend_raw_mode()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Drop for RawModeGuard<W> {
+    fn drop(&mut self) {
+        // nothing more can be done about a failure here.
+        let _ = self.suspend();
+        let _ = write!(self.out, "{}", termion::cursor::Show);
+        let _ = self.out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guard_shows_the_cursor_when_dropped() {
+        let mut out = Vec::new();
+        // no terminal to put in raw mode under test, only the rest.
+        drop(RawModeGuard { raw: None, out: &mut out });
+        assert_eq!(String::from_utf8(out).unwrap(), termion::cursor::Show.to_string());
+    }
+}
@@ -0,0 +1,349 @@
+/// Breaks `s` into lines of at most `width` characters, at spaces where it
+/// can. Escape sequences take up no room and are never split.
+pub fn wrap(s: &str, width: usize) -> Vec<String> {
+    // a char a line at the least, or the splitting below never ends.
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut ln = String::new();
+
+    for word in s.split(' ') {
+        let needed = if ln.is_empty() { display_width(word) } else { display_width(&ln) + 1 + display_width(word) };
+        if needed > width && !ln.is_empty() {
+            lines.push(ln);
+            ln = String::new();
+        }
+        if !ln.is_empty() {
+            ln.push(' ');
+        }
+        ln.push_str(word);
+
+        // a single word longer than the line gets split wherever it falls.
+        while display_width(&ln) > width {
+            let rest = ln.split_off(column(&ln, width));
+            lines.push(ln);
+            ln = rest;
+        }
+    }
+    lines.push(ln);
+
+    lines
+}
+
+// how many bytes of escape sequence `s` starts with, if any: a CSI, ESC [
+// then parameters up to a final byte; an OSC, ESC ] up to BEL or ESC \; or
+// ESC and whatever one char follows. One that never ends takes the rest
+// of `s`.
+fn escape_len(s: &str) -> Option<usize> {
+    let rest = s.strip_prefix('\x1b')?;
+    if let Some(csi) = rest.strip_prefix('[') {
+        return Some(csi.find(|c| ('@'..='~').contains(&c)).map_or(s.len(), |end| 2 + end + 1));
+    }
+    if let Some(osc) = rest.strip_prefix(']') {
+        let end = [("\x07", 1), ("\x1b\\", 2)].iter().filter_map(|(st, len)| osc.find(st).map(|end| end + len)).min();
+        return Some(end.map_or(s.len(), |end| 2 + end));
+    }
+    Some(1 + rest.chars().next().map_or(0, char::len_utf8))
+}
+
+// `s` in pieces: each escape sequence whole, as Err, and each other char on
+// its own, as Ok.
+fn pieces(s: &str) -> impl Iterator<Item = std::result::Result<&str, &str>> {
+    let mut rest = s;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let len = escape_len(rest).unwrap_or(c.len_utf8());
+        let (piece, after) = rest.split_at(len);
+        rest = after;
+        Some(if escape_len(piece).is_some() { Err(piece) } else { Ok(piece) })
+    })
+}
+
+/// The columns `s` takes up on the terminal: its chars, but not the escape
+/// sequences among them.
+pub fn display_width(s: &str) -> usize {
+    pieces(s).filter(Result::is_ok).count()
+}
+
+// the byte index in `s` of the char shown at column `col`, or its end if it
+// isn't that wide. Escape sequences just before stay with what's before.
+fn column(s: &str, col: usize) -> usize {
+    let mut at = 0;
+    let mut shown = 0;
+    for piece in pieces(s) {
+        if piece.is_ok() {
+            if shown == col {
+                return at;
+            }
+            shown += 1;
+        }
+        at += piece.unwrap_or_else(|e| e).len();
+    }
+    s.len()
+}
+
+// an SGR sequence: ESC [, numbers separated by semicolons, m.
+fn sgr(seq: &str) -> bool {
+    seq.strip_prefix("\x1b[")
+        .and_then(|seq| seq.strip_suffix('m'))
+        .is_some_and(|params| params.chars().all(|c| c.is_ascii_digit() || c == ';'))
+}
+
+/// `ln` with the colors and styles of any SGR sequences in it kept, but any
+/// other escape sequence or control char taken out: the server's lines
+/// don't get to move the cursor, retitle the window or ring the bell. Tabs
+/// are expanded, so wrapping counts the columns they take.
+pub fn keep_sgr(ln: &str) -> String {
+    let kept: String = pieces(ln)
+        .filter(|piece| match piece {
+            Ok(c) => *c == "\t" || !c.starts_with(char::is_control),
+            Err(seq) => sgr(seq),
+        })
+        .map(|piece| piece.unwrap_or_else(|e| e))
+        .collect();
+    expand_tabs(&kept)
+}
+
+/// `ln` with no escape sequences or control chars at all, for when we're
+/// not to use color, and tabs expanded as by `keep_sgr`.
+pub fn plain(ln: &str) -> String {
+    let kept: String = pieces(ln)
+        .filter_map(|piece| piece.ok())
+        .filter(|c| *c == "\t" || !c.starts_with(char::is_control))
+        .collect();
+    expand_tabs(&kept)
+}
+
+/// `rows`, the wrapped rows of one line, each made to stand on its own: the
+/// SGR sequences still in effect from the rows before are repeated at the
+/// start of a row, and a row that ends with any in effect is reset, so the
+/// box's border and the next line aren't colored along with it.
+pub fn carry_sgr(rows: Vec<String>) -> Vec<String> {
+    let mut active = String::new();
+    rows.into_iter()
+        .map(|row| {
+            let mut carried = active.clone();
+            carried.push_str(&row);
+            for seq in pieces(&row).filter_map(|piece| piece.err()).filter(|seq| sgr(seq)) {
+                if seq == "\x1b[m" || seq == "\x1b[0m" {
+                    active.clear();
+                } else {
+                    active.push_str(seq);
+                }
+            }
+            if !active.is_empty() {
+                carried.push_str("\x1b[0m");
+            }
+            carried
+        })
+        .collect()
+}
+
+// the terminal width FIBS assumes when it wraps long messages itself.
+const SERVER_WIDTH: usize = 80;
+// how far short of that width a wrapped line may stop, for the word that
+// didn't fit.
+const SERVER_WRAP_SLACK: usize = 12;
+
+/// Whether `ln` looks like the server broke it at its 80 columns, so the
+/// next line carries on from it: it ends near that width and not at the end
+/// of a sentence. A guess, so only used when asked for.
+pub fn prewrapped(ln: &str) -> bool {
+    let len = ln.trim_end().chars().count();
+    (SERVER_WIDTH - SERVER_WRAP_SLACK..=SERVER_WIDTH).contains(&len)
+        && !ln.trim_end().ends_with(['.', '!', '?', ':'])
+}
+
+/// `s` without the blank lines it starts with. The first line with anything
+/// on it is kept whole, leading spaces and all.
+pub fn skip_blank_lines(s: &str) -> &str {
+    let mut rest = s;
+    while let Some(i) = rest.find('\n') {
+        if !rest[..i].trim().is_empty() {
+            break;
+        }
+        rest = &rest[i + 1..];
+    }
+    rest
+}
+
+// a row of a board as FIBS draws them in boardstyles 1 and 2, or a
+// boardstyle 3 line: the spacing is the picture.
+fn board_row(ln: &str) -> bool {
+    ln.starts_with("board:") || matches!(ln.trim_start().chars().next(), Some('|') | Some('+'))
+}
+
+/// `ln` without the spaces it trails off with, unless it's part of a board.
+/// Leading spaces stay either way.
+pub fn trim_trailing(ln: &str) -> &str {
+    if board_row(ln) { ln } else { ln.trim_end() }
+}
+
+// `s` with each tab spread to the next 8-column stop, as a terminal would,
+// so they can't throw the columns out. Escape sequences take up none.
+fn expand_tabs(s: &str) -> String {
+    let mut expanded = String::new();
+    let mut col = 0;
+    for piece in pieces(s) {
+        match piece {
+            Ok("\t") => {
+                let stop = (col / 8 + 1) * 8;
+                expanded.push_str(&" ".repeat(stop - col));
+                col = stop;
+            }
+            Ok(c) => {
+                expanded.push_str(c);
+                col += 1;
+            }
+            Err(seq) => expanded.push_str(seq),
+        }
+    }
+    expanded
+}
+
+/// The `width` columns of `ln` from column `from`, for lines shown as sent
+/// rather than wrapped, tabs expanded. Escape sequences take up no columns;
+/// those before the cut's end are all kept, so the colors in effect by then
+/// still are.
+pub fn cut(ln: &str, from: usize, width: usize) -> String {
+    let expanded = expand_tabs(ln);
+    let end = from.saturating_add(width);
+    let mut col = 0;
+    pieces(&expanded)
+        .filter(|piece| match piece {
+            Ok(_) => {
+                col += 1;
+                (from..end).contains(&(col - 1))
+            }
+            Err(_) => col < end,
+        })
+        .map(|piece| piece.unwrap_or_else(|e| e))
+        .collect()
+}
+
+/// `s` as a JSON string, quotes and all.
+pub fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrapped_at_spaces() {
+        assert_eq!(wrap("bob says: good game, thanks", 12), vec!["bob says:", "good game,", "thanks"]);
+        assert_eq!(wrap("exactly ten", 11), vec!["exactly ten"]);
+        assert_eq!(wrap("", 10), vec![""]);
+    }
+
+    #[test]
+    fn words_longer_than_the_width() {
+        assert_eq!(wrap("see https://example.com/a/very/long/path ok", 10), vec!["see", "https://ex", "ample.com/", "a/very/lon", "g/path ok"]);
+        assert_eq!(wrap("héllo", 2), vec!["hé", "ll", "o"]);
+        assert_eq!(wrap("abc", 0), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn widths_without_escapes() {
+        assert_eq!(display_width("bob shouts: hi"), 14);
+        assert_eq!(display_width("\x1b[1;31mred\x1b[0m héllo"), 9);
+        assert_eq!(display_width("\x1b[31"), 0);
+        assert_eq!(display_width("\x1b]0;title\x07x"), 1);
+    }
+
+    #[test]
+    fn escapes_never_split_by_wrapping() {
+        let ln = "bot says: \x1b[32mgreen\x1b[0m and \x1b[31mred\x1b[0m";
+        assert_eq!(wrap(ln, 12), vec!["bot says:", "\x1b[32mgreen\x1b[0m and", "\x1b[31mred\x1b[0m"]);
+        assert_eq!(wrap("\x1b[32mabcdef\x1b[0m", 3), vec!["\x1b[32mabc", "def\x1b[0m"]);
+    }
+
+    #[test]
+    fn only_sgr_kept() {
+        assert_eq!(keep_sgr("\x1b[1;33mhi\x1b[m"), "\x1b[1;33mhi\x1b[m");
+        assert_eq!(keep_sgr("\x1b[2J\x1b[Hhi\x07\x1b]0;title\x1b\\!"), "hi!");
+        assert_eq!(plain("\x1b[1;33mhi\x1b[m\tthere"), "hi      there");
+    }
+
+    #[test]
+    fn colors_carried_across_rows() {
+        let rows = carry_sgr(wrap("\x1b[32mgreen all the way\x1b[0m plain", 10));
+        assert_eq!(rows, vec!["\x1b[32mgreen all\x1b[0m", "\x1b[32mthe way\x1b[0m", "plain"]);
+    }
+
+    #[test]
+    fn prewrapped_mid_sentence() {
+        let ln = "The server will be going down for maintenance in a few minutes, please finish";
+        assert!(prewrapped(ln));
+        assert!(!prewrapped("The server will be going down for maintenance in a few minutes. Sorry!"));
+    }
+
+    #[test]
+    fn short_lines_are_not_prewrapped() {
+        assert!(!prewrapped("bob says: hi there"));
+    }
+
+    #[test]
+    fn trailing_spaces() {
+        assert_eq!(trim_trailing("  bob says: hi   "), "  bob says: hi");
+        assert_eq!(trim_trailing("\t"), "");
+    }
+
+    #[test]
+    fn board_rows_keep_their_spacing() {
+        let rows = [
+            "   +13-14-15-16-17-18------19-20-21-22-23-24-+     X: bob - score: 0  ",
+            "   | X           O    |   |  O              X |   ",
+            "   |                  |BAR|                   |    ",
+        ];
+        for row in rows {
+            assert_eq!(trim_trailing(row), row);
+        }
+    }
+
+    #[test]
+    fn motd_after_a_blank_line() {
+        assert_eq!(skip_blank_lines("\r\n   Welcome to FIBS\r\n"), "   Welcome to FIBS\r\n");
+    }
+
+    #[test]
+    fn motd_without_a_blank_line() {
+        assert_eq!(skip_blank_lines("Welcome to FIBS\r\n\r\nlogin: "), "Welcome to FIBS\r\n\r\nlogin: ");
+    }
+
+    #[test]
+    fn cut_across_art() {
+        let art = "  _____ ___ ____  ____  ";
+        assert_eq!(cut(art, 0, 8), "  _____ ");
+        assert_eq!(cut(art, 20, 8), "__  ");
+        assert_eq!(cut("a\tb", 0, 10), "a       b");
+        assert_eq!(cut(art, 40, 8), "");
+    }
+
+    #[test]
+    fn tabs_take_their_columns() {
+        let tell = "bob tells you: scores\t5\t7";
+        assert_eq!(display_width(&plain(tell)), 33);
+        assert_eq!(display_width(&keep_sgr("\x1b[32m\tx")), 9);
+        for width in [10, 20, 30] {
+            assert!(wrap(&keep_sgr(tell), width).iter().all(|row| display_width(row) <= width));
+        }
+    }
+
+    #[test]
+    fn cut_around_escapes() {
+        assert_eq!(cut("\x1b[31mred\x1b[0m text", 1, 3), "\x1b[31med\x1b[0m ");
+        assert_eq!(cut("\x1b[31mred\x1b[0m text", 4, 10), "\x1b[31m\x1b[0mtext");
+        assert_eq!(display_width(&cut("\x1b]0;title\x07\ta", 0, 80)), 9);
+    }
+}
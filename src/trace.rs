@@ -0,0 +1,245 @@
+//! Tracing for the parsers, one switch each, so that a misbehaving board
+//! parser can be watched without the login detector's every byte alongside.
+//! Everything is off until switched on, and goes nowhere until given
+//! somewhere to go with `to`, in whatever `Format` it's given.
+
+use std::{fmt, str, sync, time};
+use std::io::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Category {
+    Board,
+    Clip,
+    Login,
+    Net,
+}
+
+impl Category {
+    pub const ALL: [Category; 4] = [Category::Board, Category::Clip, Category::Login, Category::Net];
+
+    fn name(self) -> &'static str {
+        match self {
+            Category::Board => "board",
+            Category::Clip => "clip",
+            Category::Login => "login",
+            Category::Net => "net",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl str::FromStr for Category {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Category, Error> {
+        Category::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::ConfigError(format!("no trace category {:?}; there's board, clip, login and net", s)))
+    }
+}
+
+// a part of a trace line's format.
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Literal(String),
+    // milliseconds since tracing started.
+    Time,
+    // seconds since the Unix epoch, to the millisecond.
+    Unix,
+    Category,
+    Text,
+}
+
+/// How each traced line is written, from a template of `{time}`
+/// (milliseconds since tracing started), `{unix}` (seconds since the Unix
+/// epoch, to the millisecond), `{category}` and `{text}`, with `{{` and
+/// `}}` for braces, e.g. `{unix} [{category}] {text}`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Format(Vec<Piece>);
+
+impl Format {
+    pub const DEFAULT: &'static str = "{time} {category} {text}";
+
+    fn line(&self, elapsed: time::Duration, unix: time::Duration, category: Category, text: &str) -> String {
+        self.0
+            .iter()
+            .map(|piece| match piece {
+                Piece::Literal(s) => s.clone(),
+                Piece::Time => elapsed.as_millis().to_string(),
+                Piece::Unix => format!("{}.{:03}", unix.as_secs(), unix.subsec_millis()),
+                Piece::Category => category.to_string(),
+                Piece::Text => String::from(text),
+            })
+            .collect()
+    }
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format::DEFAULT.parse().unwrap()
+    }
+}
+
+impl str::FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format, Error> {
+        let bad = |why: String| Error::ConfigError(format!("trace format {:?}: {}", s, why));
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars.as_str().split_once('}').ok_or_else(|| bad(String::from("a { isn't closed")))?;
+                    let piece = match name {
+                        "time" => Piece::Time,
+                        "unix" => Piece::Unix,
+                        "category" => Piece::Category,
+                        "text" => Piece::Text,
+                        _ => return Err(bad(format!("no {{{}}}; there's {{time}}, {{unix}}, {{category}} and {{text}}", name))),
+                    };
+                    chars = rest.chars();
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(literal.split_off(0)));
+                    }
+                    pieces.push(piece);
+                }
+                '}' => return Err(bad(String::from("a } wasn't opened; write }} for one"))),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        if !pieces.contains(&Piece::Text) {
+            return Err(bad(String::from("it needs {text}, or nothing traced would show")));
+        }
+        Ok(Format(pieces))
+    }
+}
+
+static ENABLED: [AtomicBool; 4] = [AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false)];
+
+type Sink = (Box<dyn Write + Send>, time::Instant, Format);
+
+static SINK: sync::Mutex<Option<Sink>> = sync::Mutex::new(None);
+
+pub fn set(category: Category, on: bool) {
+    ENABLED[category as usize].store(on, Ordering::Relaxed);
+}
+
+pub fn enabled(category: Category) -> bool {
+    ENABLED[category as usize].load(Ordering::Relaxed)
+}
+
+/// Where traces go from now on, a line each as `format` has it; its
+/// `{time}` counts from now.
+pub fn to(sink: Box<dyn Write + Send>, format: Format) {
+    if let Ok(mut s) = SINK.lock() {
+        *s = Some((sink, time::Instant::now(), format));
+    }
+}
+
+pub fn has_sink() -> bool {
+    SINK.lock().map(|s| s.is_some()).unwrap_or(false)
+}
+
+/// Writes what `message` makes if `category` is on; it isn't called if not.
+pub fn trace(category: Category, message: impl FnOnce() -> String) {
+    if !enabled(category) {
+        return;
+    }
+    if let Ok(mut s) = SINK.lock() {
+        if let Some((sink, started, format)) = s.as_mut() {
+            let unix = time::SystemTime::now().duration_since(time::UNIX_EPOCH).unwrap_or_default();
+            // tracing failing is no reason to stop anything else.
+            let _ = writeln!(sink, "{}", format.line(started.elapsed(), unix, category, &message()));
+            let _ = sink.flush();
+        }
+    }
+}
+
+/// e.g. "board on, clip off, login off, net off".
+pub fn describe() -> String {
+    Category::ALL
+        .iter()
+        .map(|c| format!("{} {}", c, if enabled(*c) { "on" } else { "off" }))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // whatever was traced, kept in memory.
+    #[derive(Clone, Default)]
+    struct Capture(sync::Arc<sync::Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn categories() {
+        assert_eq!("Board".parse::<Category>().unwrap(), Category::Board);
+        assert!("everything".parse::<Category>().is_err());
+    }
+
+    #[test]
+    fn only_what_is_on() {
+        let capture = Capture::default();
+        to(Box::new(capture.clone()), Format::default());
+
+        set(Category::Net, true);
+        trace(Category::Net, || String::from("read 12 bytes"));
+        trace(Category::Login, || panic!("login is off"));
+        set(Category::Net, false);
+        trace(Category::Net, || String::from("read 3 bytes"));
+
+        // other tests may be tracing alongside; look for ours.
+        let traced = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(traced.lines().any(|ln| ln.ends_with(" net read 12 bytes")));
+        assert!(!traced.contains("read 3 bytes"));
+    }
+
+    #[test]
+    fn formats() {
+        let (elapsed, unix) = (time::Duration::from_millis(1234), time::Duration::from_millis(1760000000042));
+        let line = |f: &str| f.parse::<Format>().unwrap().line(elapsed, unix, Category::Clip, "5 alice");
+
+        assert_eq!(line(Format::DEFAULT), "1234 clip 5 alice");
+        assert_eq!(line("{unix} [{category}] {text}"), "1760000000.042 [clip] 5 alice");
+        assert_eq!(line("{{{category}}}: {text}"), "{clip}: 5 alice");
+
+        assert!("{time} {category}".parse::<Format>().is_err());
+        assert!("{when} {text}".parse::<Format>().is_err());
+        assert!("{text".parse::<Format>().is_err());
+        assert!("{text} }".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use std::{mem, sync, time};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{Error, Result, Update};
+
+// how often an idle front-end looks for an update stashed by a coalescing
+// sender.
+const PENDING_POLL: time::Duration = time::Duration::from_millis(100);
+
+/// How long the front-end can spend on one update before it's taken to be
+/// stuck, as it is writing to a terminal paused with Ctrl-S.
+pub const STALL: time::Duration = time::Duration::from_secs(1);
+
+// `took` while the front-end is waiting on the next update.
+const WAITING: u64 = u64::MAX;
+
+/// What a bounded update queue does when the front-end falls behind.
+#[derive(Clone, Copy, PartialEq)]
+pub enum QueuePolicy {
+    /// Wait for the front-end to catch up.
+    Block,
+    /// Keep only the newest board while the queue is full, waiting as usual
+    /// for everything else; while the front-end is stuck, only the newest of
+    /// anything that's a snapshot of state, as the status line is.
+    Coalesce,
+}
+
+// whether only the newest `update` of its kind matters.
+fn coalescible(update: &Update) -> bool {
+    matches!(update, Update::Board(_) | Update::Status(_) | Update::Notifications(_) | Update::Pending(_) | Update::Score(_) | Update::Banner(_))
+}
+
+// what both ends share.
+struct Shared {
+    // updates held back by a coalescing sender, at most one of each kind.
+    pending: sync::Mutex<Vec<Update>>,
+    started: time::Instant,
+    // when the front-end took the update it's on, in milliseconds since
+    // `started`, or WAITING.
+    took: AtomicU64,
+}
+
+impl Shared {
+    // whether the front-end has been on one update for too long at `now`.
+    fn stalled(&self, now: time::Instant) -> bool {
+        let took = self.took.load(Ordering::Relaxed);
+        took != WAITING && now.duration_since(self.started).as_millis() as u64 >= took + STALL.as_millis() as u64
+    }
+
+    // holds `update` back in place of any before of its kind.
+    fn stash(&self, update: Update) -> Result<()> {
+        let mut pending = self.pending.lock()?;
+        pending.retain(|u| mem::discriminant(u) != mem::discriminant(&update));
+        pending.push(update);
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+enum Tx {
+    Unbounded(sync::mpsc::Sender<Update>),
+    Bounded(sync::mpsc::SyncSender<Update>, QueuePolicy),
+}
+
+/// The sending half of the channel feeding the front-end.
+#[derive(Clone)]
+pub struct UpdateSender {
+    tx: Tx,
+    shared: sync::Arc<Shared>,
+}
+
+pub struct UpdateReceiver {
+    rx: sync::mpsc::Receiver<Update>,
+    shared: sync::Arc<Shared>,
+    // an update read while looking past a board for newer ones.
+    held: Option<Update>,
+    // the front-end was stuck, and hasn't got through what piled up since.
+    catching_up: bool,
+    // the least time between frames, if drawing is capped.
+    frame: Option<time::Duration>,
+    // when the last frame was drawn.
+    drawn: Option<time::Instant>,
+    // updates are being taken in without drawing until the next frame.
+    throttled: bool,
+}
+
+/// A channel of `Update`s, unbounded unless `bound` is given.
+pub fn channel(bound: Option<usize>, policy: QueuePolicy) -> (UpdateSender, UpdateReceiver) {
+    let shared = sync::Arc::new(Shared { pending: sync::Mutex::new(Vec::new()), started: time::Instant::now(), took: AtomicU64::new(WAITING) });
+
+    let (tx, rx) = match bound {
+        Some(bound) => {
+            let (tx, rx) = sync::mpsc::sync_channel(bound);
+            (Tx::Bounded(tx, policy), rx)
+        }
+        None => {
+            let (tx, rx) = sync::mpsc::channel();
+            (Tx::Unbounded(tx), rx)
+        }
+    };
+
+    (UpdateSender { tx, shared: shared.clone() }, UpdateReceiver { rx, shared, held: None, catching_up: false, frame: None, drawn: None, throttled: false })
+}
+
+impl UpdateSender {
+    pub fn send(&self, update: Update) -> Result<()> {
+        match &self.tx {
+            Tx::Unbounded(tx) => {
+                tx.send(update)?;
+            }
+            Tx::Bounded(tx, QueuePolicy::Block) => {
+                tx.send(update)?;
+            }
+            Tx::Bounded(tx, QueuePolicy::Coalesce) => {
+                // waiting on a stuck front-end would wedge us too, so only
+                // chat, which can't be dropped, waits then.
+                if self.shared.stalled(time::Instant::now()) {
+                    if coalescible(&update) {
+                        return self.shared.stash(update);
+                    }
+                } else {
+                    // anything stashed goes out ahead of anything newer.
+                    let stashed = mem::take(&mut *self.shared.pending.lock()?);
+                    for stashed in stashed {
+                        tx.send(stashed)?;
+                    }
+                }
+
+                match tx.try_send(update) {
+                    Ok(()) => {}
+                    Err(sync::mpsc::TrySendError::Full(board @ Update::Board(_))) => {
+                        self.shared.stash(board)?;
+                    }
+                    Err(sync::mpsc::TrySendError::Full(update)) => {
+                        tx.send(update)?;
+                    }
+                    Err(sync::mpsc::TrySendError::Disconnected(_)) => {
+                        return Err(Error::SyncError(String::from("tui thread disconnected")));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl UpdateReceiver {
+    /// The next update. Of boards that arrived together only the newest is
+    /// returned, since it's the only one worth drawing.
+    ///
+    /// If the last update took the front-end longer than `STALL`, what piled
+    /// up meanwhile comes next with `catching_up` true, for the front-end to
+    /// take in without drawing, then `Update::Redraw` to draw the lot once.
+    ///
+    /// With frames capped, an update coming within a frame of the last one
+    /// drawn comes with `throttled` true, and so does everything after it
+    /// until the frame's up and `Update::Frame` draws what they changed, for
+    /// the front-end to take in the ones it can draw that way meanwhile.
+    pub fn recv(&mut self) -> Result<Update> {
+        let now = time::Instant::now();
+        if self.shared.stalled(now) {
+            self.catching_up = true;
+        }
+        if self.catching_up && self.held.is_none() {
+            match self.rx.try_recv() {
+                Ok(update) => self.held = Some(update),
+                // through the backlog.
+                Err(_) if self.shared.pending.lock()?.is_empty() => {
+                    self.catching_up = false;
+                    self.took(now);
+                    return Ok(Update::Redraw);
+                }
+                Err(_) => {}
+            }
+        }
+
+        if let (true, false, Some(frame), Some(drawn)) = (self.throttled, self.catching_up, self.frame, self.drawn) {
+            let due = drawn + frame;
+            if self.held.is_none() {
+                self.shared.took.store(WAITING, Ordering::Relaxed);
+                self.held = self.next_until(Some(due))?;
+            }
+            // what came in this frame is drawn, and anything still to come
+            // goes in the next.
+            if time::Instant::now() >= due {
+                self.throttled = false;
+                self.drawn = Some(time::Instant::now());
+                self.took(time::Instant::now());
+                return Ok(Update::Frame);
+            }
+        }
+
+        self.shared.took.store(WAITING, Ordering::Relaxed);
+        let update = self.latest();
+        let now = time::Instant::now();
+        self.took(now);
+        if let (Some(frame), false) = (self.frame, self.catching_up) {
+            match self.drawn {
+                Some(drawn) if now.duration_since(drawn) < frame => self.throttled = true,
+                _ => self.drawn = Some(now),
+            }
+        }
+        update
+    }
+
+    /// Draws no more often than `rate` frames a second, or as often as
+    /// updates come if None.
+    pub fn cap_frames(&mut self, rate: Option<u32>) {
+        self.frame = rate.filter(|rate| *rate > 0).map(|rate| time::Duration::from_secs(1) / rate);
+    }
+
+    /// Whether updates are being taken in until the next frame; see `recv`.
+    pub fn throttled(&self) -> bool {
+        self.throttled
+    }
+
+    /// Whether updates are still the backlog from when the front-end was
+    /// stuck; see `recv`.
+    pub fn catching_up(&self) -> bool {
+        self.catching_up
+    }
+
+    fn took(&self, now: time::Instant) {
+        self.shared.took.store(now.duration_since(self.shared.started).as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn latest(&mut self) -> Result<Update> {
+        let update = match self.held.take() {
+            Some(update) => update,
+            None => self.next()?,
+        };
+        if !matches!(update, Update::Board(_)) {
+            return Ok(update);
+        }
+
+        let mut latest = update;
+        loop {
+            match self.rx.try_recv() {
+                Ok(board @ Update::Board(_)) => latest = board,
+                Ok(update) => {
+                    self.held = Some(update);
+                    break;
+                }
+                // anything amiss shows up on the next call.
+                Err(_) => break,
+            }
+        }
+        Ok(latest)
+    }
+
+    fn next(&self) -> Result<Update> {
+        // with no deadline, only ever an update or an error.
+        self.next_until(None)?.ok_or_else(|| Error::SyncError(String::from("fibs thread disconnected")))
+    }
+
+    // the next update, or None if none comes by `deadline`.
+    fn next_until(&self, deadline: Option<time::Instant>) -> Result<Option<Update>> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(update) => return Ok(Some(update)),
+                Err(sync::mpsc::TryRecvError::Empty) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let stashed = {
+                let mut pending = self.shared.pending.lock()?;
+                (!pending.is_empty()).then(|| pending.remove(0))
+            };
+            if stashed.is_some() {
+                return Ok(stashed);
+            }
+
+            let wait = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(time::Instant::now()) {
+                    Some(left) if !left.is_zero() => left.min(PENDING_POLL),
+                    _ => return Ok(None),
+                },
+                None => PENDING_POLL,
+            };
+            match self.rx.recv_timeout(wait) {
+                Ok(update) => return Ok(Some(update)),
+                Err(sync::mpsc::RecvTimeoutError::Timeout) => {}
+                Err(sync::mpsc::RecvTimeoutError::Disconnected) => {
+                    return Err(Error::SyncError(String::from("fibs thread disconnected")));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn stuck_front_end_catches_up_then_redraws() {
+        let (tx, mut rx) = channel(Some(1), QueuePolicy::Coalesce);
+        tx.send(Update::Bell).unwrap();
+        assert!(matches!(rx.recv().unwrap(), Update::Bell));
+
+        // the front-end stuck drawing that, the queue fills.
+        thread::sleep(STALL);
+        tx.send(Update::AppendLine(String::from("hi"))).unwrap();
+        for n in 1..=3 {
+            // would wait on the full queue if the front-end weren't stuck.
+            tx.send(Update::Pending(n)).unwrap();
+        }
+
+        assert!(matches!(rx.recv().unwrap(), Update::AppendLine(_)));
+        assert!(rx.catching_up());
+        assert!(matches!(rx.recv().unwrap(), Update::Pending(3)));
+        assert!(rx.catching_up());
+        assert!(matches!(rx.recv().unwrap(), Update::Redraw));
+        assert!(!rx.catching_up());
+
+        // back to normal.
+        tx.send(Update::Pending(4)).unwrap();
+        assert!(matches!(rx.recv().unwrap(), Update::Pending(4)));
+        assert!(!rx.catching_up());
+    }
+
+    #[test]
+    fn frames_capped() {
+        let (tx, mut rx) = channel(None, QueuePolicy::Block);
+        rx.cap_frames(Some(10));
+
+        // the first is drawn straight away.
+        tx.send(Update::Pending(1)).unwrap();
+        assert!(matches!(rx.recv().unwrap(), Update::Pending(1)));
+        assert!(!rx.throttled());
+
+        // the rest of the frame's are only taken in, then drawn together.
+        let started = time::Instant::now();
+        for n in 2..=4 {
+            tx.send(Update::Pending(n)).unwrap();
+        }
+        for n in 2..=4 {
+            assert!(matches!(rx.recv().unwrap(), Update::Pending(m) if m == n));
+            assert!(rx.throttled());
+        }
+        assert!(matches!(rx.recv().unwrap(), Update::Frame));
+        assert!(!rx.throttled());
+        assert!(started.elapsed() >= time::Duration::from_millis(90));
+
+        // uncapped, nothing waits.
+        rx.cap_frames(None);
+        tx.send(Update::Pending(5)).unwrap();
+        tx.send(Update::Pending(6)).unwrap();
+        assert!(matches!(rx.recv().unwrap(), Update::Pending(5)));
+        assert!(matches!(rx.recv().unwrap(), Update::Pending(6)));
+        assert!(!rx.throttled());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{text, Result};
+#[cfg(not(feature = "webhook"))]
+use crate::Error;
+
+#[cfg(feature = "webhook")]
+use std::{io, net, thread, time};
+#[cfg(feature = "webhook")]
+use std::io::prelude::*;
+
+#[cfg(feature = "webhook")]
+use crate::{updates, Error, Update};
+
+// how long the server at the other end gets to connect and answer.
+#[cfg(feature = "webhook")]
+const TIMEOUT: time::Duration = time::Duration::from_secs(10);
+
+/// One of our matches that has just ended, as POSTed to `FIBS_WEBHOOK`:
+///
+/// ```json
+/// {"player": "alice", "opponent": "bob", "winner": "alice",
+///  "match_length": 5, "score": [5, 3], "rating": 1523.45}
+/// ```
+///
+/// `score` is the player's and then the opponent's. FIBS says nothing about
+/// ratings as a match ends, so `rating` is the player's as last reported
+/// before it (null if never); the change is the difference between one
+/// result's and the next's.
+#[cfg_attr(not(feature = "webhook"), allow(dead_code))]
+pub struct MatchResult {
+    pub player: String,
+    pub opponent: String,
+    pub winner: String,
+    pub match_length: u32,
+    pub score: (u32, u32),
+    pub rating: Option<f64>,
+}
+
+#[cfg_attr(not(feature = "webhook"), allow(dead_code))]
+impl MatchResult {
+    pub fn to_json(&self) -> String {
+        format!("{{\"player\": {}, \"opponent\": {}, \"winner\": {}, \"match_length\": {}, \"score\": [{}, {}], \"rating\": {}}}",
+            text::json_string(&self.player),
+            text::json_string(&self.opponent),
+            text::json_string(&self.winner),
+            self.match_length,
+            self.score.0, self.score.1,
+            self.rating.map(|r| format!("{:.2}", r)).unwrap_or_else(|| String::from("null")))
+    }
+}
+
+// "http://host[:port]/path", plain HTTP only.
+#[cfg(feature = "webhook")]
+fn parse_url(url: &str) -> Result<(String, u16, String)> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| Error::ConfigError(format!("webhook {} isn't an http:// URL", url)))?;
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().map_err(|_| Error::ConfigError(format!("bad port in webhook {}", url)))?),
+        None => (authority, 80),
+    };
+    Ok((String::from(host), port, String::from(path)))
+}
+
+#[cfg(feature = "webhook")]
+fn send(url: &str, body: &str) -> Result<()> {
+    let (host, port, path) = parse_url(url)?;
+    let addr = net::ToSocketAddrs::to_socket_addrs(&(host.as_str(), port))?
+        .next()
+        .ok_or_else(|| Error::GAIError(format!("no address for {}", host)))?;
+    let mut tcp = net::TcpStream::connect_timeout(&addr, TIMEOUT)?;
+    tcp.set_read_timeout(Some(TIMEOUT))?;
+
+    write!(tcp, "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        path, host, body.len(), body)?;
+
+    let mut status = String::new();
+    io::BufReader::new(tcp).read_line(&mut status)?;
+    match status.split(' ').nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(Error::IOError(format!("webhook answered {:?}", status.trim_end()))),
+    }
+}
+
+/// Posts `result` to `url` on a thread of its own, so a slow or missing
+/// server never holds up play. Failures show in the FIBS box.
+#[cfg(feature = "webhook")]
+pub fn post(url: &str, result: &MatchResult, updates_tx: &updates::UpdateSender) -> Result<()> {
+    let (url, body, updates_tx) = (String::from(url), result.to_json(), updates_tx.clone());
+    thread::spawn(move || {
+        if let Err(e) = send(&url, &body) {
+            updates_tx.send(Update::AppendLine(format!("couldn't post the match result: {}", e))).ok();
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "webhook"))]
+pub fn post(_url: &str, _result: &MatchResult, _updates_tx: &crate::updates::UpdateSender) -> Result<()> {
+    Err(Error::ConfigError(String::from("FIBS_WEBHOOK is set, but this was built without webhook support")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload() {
+        let result = MatchResult {
+            player: String::from("alice"),
+            opponent: String::from("bob \"the cube\""),
+            winner: String::from("alice"),
+            match_length: 5,
+            score: (5, 3),
+            rating: Some(1523.454),
+        };
+        assert_eq!(
+            result.to_json(),
+            r#"{"player": "alice", "opponent": "bob \"the cube\"", "winner": "alice", "match_length": 5, "score": [5, 3], "rating": 1523.45}"#
+        );
+    }
+
+    #[cfg(feature = "webhook")]
+    #[test]
+    fn urls() {
+        assert_eq!(parse_url("http://example.org:8080/results").unwrap(), (String::from("example.org"), 8080, String::from("/results")));
+        assert_eq!(parse_url("http://example.org").unwrap(), (String::from("example.org"), 80, String::from("/")));
+        assert!(parse_url("https://example.org").is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{clip, text};
+
+/// One player, from a CLIP who-info line:
+///
+/// `5 name opponent watching ready away rating experience idle login
+/// hostname client email`
+#[derive(Debug, PartialEq)]
+pub struct WhoInfo {
+    pub name: String,
+    pub opponent: Option<String>,
+    pub watching: Option<String>,
+    pub ready: bool,
+    pub away: bool,
+    pub rating: f64,
+    pub experience: u32,
+}
+
+// "-" stands for nobody.
+fn someone(field: &str) -> Option<String> {
+    Some(clip::capped(field, clip::MAX_NAME, "name")).filter(|f| f != "-")
+}
+
+impl WhoInfo {
+    pub fn parse(ln: &str) -> Option<WhoInfo> {
+        let fields: Vec<&str> = ln.strip_prefix("5 ")?.split(' ').collect();
+        if fields.len() < 7 {
+            return None;
+        }
+
+        Some(WhoInfo {
+            name: clip::capped(fields[0], clip::MAX_NAME, "name"),
+            opponent: someone(fields[1]),
+            watching: someone(fields[2]),
+            ready: fields[3] == "1",
+            away: fields[4] == "1",
+            rating: fields[5].parse().ok()?,
+            experience: fields[6].parse().ok()?,
+        })
+    }
+}
+
+/// How `export` writes the WHO list.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+impl Format {
+    /// `csv` or `json`, or failing that whichever the file's extension says;
+    /// CSV if neither does.
+    pub fn pick(name: Option<&str>, path: &str) -> Option<Format> {
+        match name.map(str::to_lowercase).as_deref() {
+            Some("csv") => Some(Format::Csv),
+            Some("json") => Some(Format::Json),
+            Some(_) => None,
+            None if path.to_lowercase().ends_with(".json") => Some(Format::Json),
+            None => Some(Format::Csv),
+        }
+    }
+}
+
+// quoted only when it has to be.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        String::from(s)
+    }
+}
+
+/// The players as CSV or a JSON array, one row or object each. CSV has a
+/// header row, and the columns in this order:
+///
+/// `name,opponent,watching,ready,away,rating,experience`
+///
+/// with nobody as an empty field and ready and away as 0 or 1. JSON has the
+/// same names as keys, with null for nobody and true or false.
+pub fn export(players: &[&WhoInfo], format: Format) -> String {
+    match format {
+        Format::Csv => {
+            let mut csv = String::from("name,opponent,watching,ready,away,rating,experience\n");
+            for p in players {
+                csv.push_str(&format!("{},{},{},{},{},{:.2},{}\n",
+                    csv_field(&p.name),
+                    csv_field(p.opponent.as_deref().unwrap_or("")),
+                    csv_field(p.watching.as_deref().unwrap_or("")),
+                    p.ready as u8,
+                    p.away as u8,
+                    p.rating,
+                    p.experience));
+            }
+            csv
+        }
+        Format::Json => {
+            let someone = |p: &Option<String>| p.as_deref().map(text::json_string).unwrap_or_else(|| String::from("null"));
+            let objects: Vec<String> = players
+                .iter()
+                .map(|p| format!("  {{\"name\": {}, \"opponent\": {}, \"watching\": {}, \"ready\": {}, \"away\": {}, \"rating\": {:.2}, \"experience\": {}}}",
+                    text::json_string(&p.name), someone(&p.opponent), someone(&p.watching), p.ready, p.away, p.rating, p.experience))
+                .collect();
+            format!("[\n{}\n]\n", objects.join(",\n"))
+        }
+    }
+}
+
+/// How WHO lines are colored by rating and experience.
+#[derive(Clone)]
+pub struct Grading {
+    /// Below this much experience a player counts as new, whatever their
+    /// rating says.
+    pub new: u32,
+    /// Ratings where each grade starts, ascending.
+    pub thresholds: Vec<f64>,
+    /// The new players' color, then one for each grade: below the first
+    /// threshold, and from each threshold on.
+    pub palette: Vec<u8>,
+}
+
+impl Default for Grading {
+    fn default() -> Grading {
+        Grading {
+            new: 400,
+            thresholds: vec![1500.0, 1700.0, 1900.0],
+            // lightblack, white, lightgreen, lightcyan, lightyellow.
+            palette: vec![8, 7, 10, 14, 11],
+        }
+    }
+}
+
+impl Grading {
+    pub fn color(&self, info: &WhoInfo) -> u8 {
+        let i = if info.experience < self.new {
+            0
+        } else {
+            1 + self.thresholds.iter().filter(|t| info.rating >= **t).count()
+        };
+        // a short palette repeats its last color.
+        self.palette.get(i).or(self.palette.last()).copied().unwrap_or(7)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_who_info() {
+        let info = WhoInfo::parse("5 bob alice - 0 1 1723.45 2301 12 1041253132 host.example.com - -").unwrap();
+        assert_eq!(info.name, "bob");
+        assert_eq!(info.opponent, Some(String::from("alice")));
+        assert_eq!(info.watching, None);
+        assert!(info.away);
+        assert_eq!(info.rating, 1723.45);
+        assert_eq!(info.experience, 2301);
+    }
+
+    #[test]
+    fn exports() {
+        let bob = WhoInfo::parse("5 bob alice - 0 1 1723.45 2301 12 1041253132 host.example.com - -").unwrap();
+        let carol = WhoInfo::parse("5 carol - bob 1 0 1500.00 12 0 1041253132 - - -").unwrap();
+
+        assert_eq!(export(&[&bob, &carol], Format::Csv),
+            "name,opponent,watching,ready,away,rating,experience\nbob,alice,,0,1,1723.45,2301\ncarol,,bob,1,0,1500.00,12\n");
+        assert_eq!(export(&[&carol], Format::Json),
+            "[\n  {\"name\": \"carol\", \"opponent\": null, \"watching\": \"bob\", \"ready\": true, \"away\": false, \"rating\": 1500.00, \"experience\": 12}\n]\n");
+
+        assert_eq!(Format::pick(None, "who.JSON"), Some(Format::Json));
+        assert_eq!(Format::pick(Some("csv"), "who.json"), Some(Format::Csv));
+        assert_eq!(Format::pick(Some("xml"), "who.xml"), None);
+    }
+
+    #[test]
+    fn grades() {
+        let grading = Grading::default();
+        let info = |rating, experience| WhoInfo {
+            name: String::from("bob"),
+            opponent: None,
+            watching: None,
+            ready: true,
+            away: false,
+            rating,
+            experience,
+        };
+
+        assert_eq!(grading.color(&info(1950.0, 10)), 8);
+        assert_eq!(grading.color(&info(1400.0, 1000)), 7);
+        assert_eq!(grading.color(&info(1700.0, 1000)), 14);
+        assert_eq!(grading.color(&info(2100.0, 1000)), 11);
+    }
+}
@@ -0,0 +1,59 @@
+//! Terminal resizes, as `Update::Resize`. The SIGWINCH handler only writes a
+//! byte down a pipe, about all that's safe in a handler; a thread reading the
+//! other end asks the terminal its size and sends that on.
+
+use std::{fs, io, mem, ptr, thread};
+use std::io::prelude::*;
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use crate::{updates, Result, Update};
+
+// the pipe's writing end, for the handler; -1 until there is one.
+static WAKE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" fn on_winch(_: libc::c_int) {
+    let fd = WAKE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        // a full pipe already has a resize waiting to be read.
+        unsafe { libc::write(fd, [0u8].as_ptr() as *const libc::c_void, 1) };
+    }
+}
+
+/// Sends the terminal's new size as it's resized, from now on.
+pub fn spawn(updates_tx: updates::UpdateSender) -> Result<()> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    // never block in the handler.
+    if unsafe { libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK) } != 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    WAKE.store(fds[1], Ordering::Relaxed);
+
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = on_winch as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        // reads of the keyboard carry on rather than failing with EINTR.
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        if libc::sigaction(libc::SIGWINCH, &action, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+    }
+
+    let mut pipe = unsafe { fs::File::from_raw_fd(fds[0]) };
+    thread::spawn(move || {
+        let mut byte = [0; 1];
+        while let Ok(1) = pipe.read(&mut byte) {
+            if let Ok((cols, rows)) = termion::terminal_size() {
+                if updates_tx.send(Update::Resize(cols, rows)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}